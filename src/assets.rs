@@ -0,0 +1,110 @@
+use std::{borrow::Cow, fs, path::Path};
+
+/// Files in this directory replace the embedded ones with the same path
+const ASSET_DIR: &str = "./assets";
+
+/// Base assets built into the binary, by path in the assets directory
+const EMBEDDED: &[(&str, &[u8])] = &[
+	("font.png", include_bytes!("../assets/font.png")),
+	(
+		"spritesheet.png",
+		include_bytes!("../assets/spritesheet.png"),
+	),
+	(
+		"audio/menu_back.wav",
+		include_bytes!("../assets/audio/menu_back.wav"),
+	),
+	(
+		"audio/menu_move.wav",
+		include_bytes!("../assets/audio/menu_move.wav"),
+	),
+	(
+		"audio/menu_select.wav",
+		include_bytes!("../assets/audio/menu_select.wav"),
+	),
+	(
+		"audio/player_shoot.wav",
+		include_bytes!("../assets/audio/player_shoot.wav"),
+	),
+	(
+		"audio/enemy_hit.wav",
+		include_bytes!("../assets/audio/enemy_hit.wav"),
+	),
+	(
+		"audio/explosion.wav",
+		include_bytes!("../assets/audio/explosion.wav"),
+	),
+	(
+		"audio/pickup.wav",
+		include_bytes!("../assets/audio/pickup.wav"),
+	),
+	(
+		"audio/player_hit.wav",
+		include_bytes!("../assets/audio/player_hit.wav"),
+	),
+	(
+		"audio/stage_music.wav",
+		include_bytes!("../assets/audio/stage_music.wav"),
+	),
+	(
+		"levels/boss_rush.hbh",
+		include_bytes!("../assets/levels/boss_rush.hbh"),
+	),
+	("drops.toml", include_bytes!("../assets/drops.toml")),
+	(
+		"levels/endless.hbh",
+		include_bytes!("../assets/levels/endless.hbh"),
+	),
+	(
+		"levels/level1.hbh",
+		include_bytes!("../assets/levels/level1.hbh"),
+	),
+	(
+		"levels/level2.hbh",
+		include_bytes!("../assets/levels/level2.hbh"),
+	),
+	(
+		"levels/level3.hbh",
+		include_bytes!("../assets/levels/level3.hbh"),
+	),
+];
+
+/// Contents of an asset, from the assets directory if it is there or else from the binary
+pub fn read(name: &str) -> Option<Cow<'static, [u8]>> {
+	match fs::read(Path::new(ASSET_DIR).join(name)) {
+		Ok(bytes) => Some(Cow::Owned(bytes)),
+		Err(_) => embedded(name),
+	}
+}
+
+/// Contents of the asset built into the binary, ignoring the assets directory
+pub fn embedded(name: &str) -> Option<Cow<'static, [u8]>> {
+	EMBEDDED
+		.iter()
+		.find(|(embedded, _)| *embedded == name)
+		.map(|(_, bytes)| Cow::Borrowed(*bytes))
+}
+
+/// Sorted names of the assets in `dir` with the extension `ext`, embedded or on disk
+pub fn list(dir: &str, ext: &str) -> Vec<String> {
+	let mut names: Vec<String> = EMBEDDED
+		.iter()
+		.map(|(name, _)| name.to_string())
+		.filter(|name| {
+			let path = Path::new(name);
+			path.parent() == Some(Path::new(dir)) && path.extension().is_some_and(|e| e == ext)
+		})
+		.collect();
+	if let Ok(entries) = fs::read_dir(Path::new(ASSET_DIR).join(dir)) {
+		for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+			if path.is_file() && path.extension().is_some_and(|e| e == ext) {
+				let name = format!("{dir}/{}", path.file_name().unwrap().to_string_lossy());
+				if !names.contains(&name) {
+					names.push(name);
+				}
+			}
+		}
+	}
+	names.sort();
+	names
+}
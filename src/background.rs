@@ -0,0 +1,133 @@
+use cgmath::Point2;
+use serde::{Deserialize, Serialize};
+
+use crate::{coords::Dimensions, rng::Rng};
+
+/// Side of the terrain tiles, in base resolution pixels
+const TILE_SIZE: f32 = 32.;
+/// Chance of each cell of the terrain grid to hold a tile
+const TILE_CHANCE: f32 = 0.3;
+const TILE_COLORS: [[u8; 4]; 2] = [[0x3c, 0x46, 0x5a, 0x30], [0x50, 0x3c, 0x5a, 0x28]];
+const STAR_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+const CLOUD_COLOR: [u8; 4] = [0xd0, 0xd8, 0xff, 0x18];
+/// Puffs making up each cloud
+const CLOUD_PUFFS: u32 = 3;
+
+/// What a layer is made of
+#[derive(Clone, Copy, Debug)]
+enum LayerKind {
+	/// Small dots of varying brightness
+	Stars,
+	/// Blocks on a grid, some cells left empty
+	Tiles,
+	/// Overlapping translucent puffs
+	Clouds,
+}
+
+struct Layer {
+	kind: LayerKind,
+	/// Scrolling speed, in pixels per 60 Hz tick
+	speed: f32,
+	/// Stars or clouds laid out, the tiles fill their grid
+	count: u32,
+}
+
+/// From the farthest, drawn first and scrolling the slowest, to the closest
+const LAYERS: [Layer; 3] = [
+	Layer { kind: LayerKind::Stars, speed: 0.2, count: 60 },
+	Layer { kind: LayerKind::Tiles, speed: 0.5, count: 0 },
+	Layer { kind: LayerKind::Clouds, speed: 1.1, count: 5 },
+];
+
+/// Layers scrolling down behind the playfield. Only the seed is kept, the layout is rolled again
+/// from it when drawn and the scrolling follows the ticks
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Background {
+	seed: u64,
+}
+
+/// Rectangle of a layer, centered on its position
+pub struct Piece {
+	pub center: Point2<f32>,
+	pub dims: Dimensions<f32>,
+	pub color: [u8; 4],
+}
+
+impl Background {
+	pub fn new(seed: u64) -> Background {
+		Background { seed }
+	}
+
+	/// Rectangles of every layer at `tick`, in drawing order, for a playfield of `dims`
+	pub fn pieces(&self, tick: u64, dims: Dimensions<f32>) -> Vec<Piece> {
+		let mut pieces = vec![];
+		for (i, layer) in LAYERS.iter().enumerate() {
+			// Each layer has its own rolls, changing one doesn't move the others
+			let mut rng = Rng::new(self.seed.wrapping_add(i as u64));
+			let scroll = tick as f32 * layer.speed;
+			match layer.kind {
+				LayerKind::Stars => {
+					for _ in 0..layer.count {
+						let x = rng.next_f32() * dims.w;
+						let y = wrap(rng.next_f32() * dims.h + scroll, dims.h, 0.);
+						let size = if rng.next_f32() < 0.2 { 2. } else { 1. };
+						let alpha = 0x60 + (rng.next_f32() * 96.) as u8;
+						let [r, g, b] = STAR_COLOR;
+						pieces.push(Piece {
+							center: (x, y).into(),
+							dims: (size, size).into(),
+							color: [r, g, b, alpha],
+						});
+					}
+				},
+				LayerKind::Tiles => {
+					let cols = (dims.w / TILE_SIZE).ceil() as u32;
+					// One more row than shown, coming in from the top as the grid scrolls
+					let rows = (dims.h / TILE_SIZE).ceil() as u32 + 1;
+					let period = rows as f32 * TILE_SIZE;
+					for row in 0..rows {
+						for col in 0..cols {
+							if rng.next_f32() >= TILE_CHANCE {
+								continue;
+							}
+							let color = TILE_COLORS[(rng.next_f32() * TILE_COLORS.len() as f32) as usize];
+							let y = wrap(row as f32 * TILE_SIZE + scroll, period, TILE_SIZE);
+							pieces.push(Piece {
+								center: ((col as f32 + 0.5) * TILE_SIZE, y + TILE_SIZE / 2.).into(),
+								dims: (TILE_SIZE, TILE_SIZE).into(),
+								color,
+							});
+						}
+					}
+				},
+				LayerKind::Clouds => {
+					let margin = 60.;
+					for _ in 0..layer.count {
+						let x = rng.next_f32() * dims.w;
+						let y = wrap(
+							rng.next_f32() * dims.h + scroll,
+							dims.h + 2. * margin,
+							margin,
+						);
+						for _ in 0..CLOUD_PUFFS {
+							let w = 40. + 50. * rng.next_f32();
+							let h = 16. + 14. * rng.next_f32();
+							let offset = (rng.next_f32() - 0.5) * w;
+							pieces.push(Piece {
+								center: (x + offset, y + (rng.next_f32() - 0.5) * h).into(),
+								dims: (w, h).into(),
+								color: CLOUD_COLOR,
+							});
+						}
+					}
+				},
+			}
+		}
+		pieces
+	}
+}
+
+/// Brings `y` back in a band of height `period` starting `margin` above the playfield
+fn wrap(y: f32, period: f32, margin: f32) -> f32 {
+	y.rem_euclid(period) - margin
+}
@@ -0,0 +1,85 @@
+use cgmath::{Point2, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	coords::{collide_rectangle, Dimensions},
+	gameplay::DT_60,
+};
+
+/// Position and motion shared by the player, the enemies and the projectiles. Flattened in them,
+/// the dumps keep the same fields
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Body {
+	pub pos: Point2<f32>,
+	/// Position before the last tick, the sprite is drawn between the two
+	#[serde(skip)]
+	prev_pos: Option<Point2<f32>>,
+	pub vel: Vector2<f32>,
+}
+
+impl Body {
+	pub fn new(pos: Point2<f32>, vel: Vector2<f32>) -> Body {
+		Body { pos, prev_pos: None, vel }
+	}
+
+	/// Keeps the position before the tick, to draw the entity between two ticks
+	pub fn remember_pos(&mut self) {
+		self.prev_pos = Some(self.pos);
+	}
+
+	/// Moves to `pos` without being drawn sliding there from where it was
+	pub fn teleport(&mut self, pos: Point2<f32>) {
+		self.pos = pos;
+		self.prev_pos = None;
+	}
+
+	/// Where the sprite is drawn, `alpha` of the way from the previous position to the current
+	/// one. Entities spawned during the last tick have no previous position, they are drawn where
+	/// they are
+	pub fn shown_pos(&self, alpha: f32) -> Point2<f32> {
+		self
+			.prev_pos
+			.map_or(self.pos, |prev| prev + (self.pos - prev) * alpha)
+	}
+
+	/// Where the velocity times `speed_factor`, plus `push`, leads in `dt` seconds. Both are in
+	/// pixels per 60 Hz tick
+	pub fn next_pos(&self, speed_factor: f32, push: Vector2<f32>, dt: f32) -> Point2<f32> {
+		self.pos + (self.vel * speed_factor + push) * dt / DT_60
+	}
+
+	/// Moves to `next_pos`
+	pub fn advance(&mut self, speed_factor: f32, push: Vector2<f32>, dt: f32) {
+		self.pos = self.next_pos(speed_factor, push, dt);
+	}
+}
+
+/// Entities colliding with a box centered on their body
+pub trait Collider {
+	fn body(&self) -> &Body;
+
+	fn hit_dims(&self) -> Dimensions<f32>;
+
+	fn collides(&self, other: &impl Collider) -> bool
+	where
+		Self: Sized,
+	{
+		collide_rectangle(
+			self.body().pos,
+			self.hit_dims(),
+			other.body().pos,
+			other.hit_dims(),
+		)
+	}
+}
+
+/// Entities moved by the movement system, from the velocity their kind steered them to
+pub trait Mobile: Collider {
+	fn body_mut(&mut self) -> &mut Body;
+
+	/// Factor of the velocity this tick, None for the bodies placed by their kind instead
+	fn speed_factor(&self) -> Option<f32>;
+
+	/// Whether the hazards slow and push the body
+	fn drifts(&self) -> bool;
+}
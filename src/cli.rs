@@ -0,0 +1,109 @@
+use std::{env, path::PathBuf};
+
+use crate::{error::GameError, game::Difficulty, stats::StatsExport};
+
+/// What the golden image harness does with the renders
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoldenMode {
+	/// Compares the renders with the stored reference images
+	Check,
+	/// Overwrites the stored reference images with the renders
+	Update,
+}
+
+/// Command line arguments
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+	/// Runs the golden image harness instead of the game
+	pub golden: Option<GoldenMode>,
+	/// World dump to start playing from
+	pub world: Option<PathBuf>,
+	/// File where the gameplay events are logged
+	pub event_log: Option<PathBuf>,
+	/// Replay to play instead of starting on the menu
+	pub replay: Option<PathBuf>,
+	/// Level file to play instead of starting on the menu
+	pub level: Option<PathBuf>,
+	/// Enables the practice tools, runs aren't recorded
+	pub practice: bool,
+	/// Event the levels start at in practice
+	pub start_event: Option<u32>,
+	/// Format of the statistics export, instead of the one of the options
+	pub stats: Option<StatsExport>,
+	/// Seed of the random rolls of every level started
+	pub seed: Option<u64>,
+	/// Replaces the difficulty of the options for this launch
+	pub difficulty: Option<Difficulty>,
+}
+
+/// Printed when the arguments can't be understood
+pub const USAGE: &str = "\
+Usage: holy_bullet_hell [options]
+  --practice                              enable the practice tools for every level
+  --start-event <id>                      start the levels at this event in practice
+  --level <path>                          play a level file
+  --replay <path>                         play a replay back
+  --world <path>                          resume playing from a world dump
+  --event-log <path>                      log the gameplay events to this file
+  --stats <off|csv|json>                  format of the statistics export
+  --seed <number>                         seed of the random rolls of every level
+  --difficulty <easy|normal|hard|lunatic> difficulty of this launch
+  --golden-check                          compare the renders with the reference images
+  --golden-update                         overwrite the reference images";
+
+impl Args {
+	pub fn parse() -> Result<Args, GameError> {
+		let mut args = Args::default();
+		let mut iter = env::args().skip(1);
+		while let Some(arg) = iter.next() {
+			// Value following the argument
+			let mut value = |what: &str| {
+				iter
+					.next()
+					.ok_or_else(|| GameError::args(format!("Missing {what} after '{arg}'")))
+			};
+			match arg.as_str() {
+				"--golden-check" => args.golden = Some(GoldenMode::Check),
+				"--golden-update" => args.golden = Some(GoldenMode::Update),
+				"--practice" => args.practice = true,
+				"--event-log" => args.event_log = Some(value("path")?.into()),
+				"--replay" => args.replay = Some(value("path")?.into()),
+				"--level" => args.level = Some(value("path")?.into()),
+				"--world" => args.world = Some(value("path")?.into()),
+				"--stats" => {
+					let format = value("format")?;
+					args.stats = Some(StatsExport::parse(&format).ok_or_else(|| {
+						GameError::args(format!(
+							"Unknown stats format '{format}', use off, csv or json"
+						))
+					})?)
+				},
+				"--start-event" => {
+					let id = value("id")?;
+					args.start_event = Some(
+						id.parse()
+							.map_err(|_| GameError::args(format!("Invalid event id '{id}'")))?,
+					)
+				},
+				"--seed" => {
+					let seed = value("number")?;
+					args.seed = Some(
+						seed
+							.parse()
+							.map_err(|_| GameError::args(format!("Invalid seed '{seed}'")))?,
+					)
+				},
+				"--difficulty" => {
+					let name = value("name")?;
+					args.difficulty = Some(Difficulty::parse(&name).ok_or_else(|| {
+						GameError::args(format!(
+							"Unknown difficulty '{name}', use easy, normal, hard or lunatic"
+						))
+					})?)
+				},
+				other => return Err(GameError::args(format!("Unknown argument '{other}'"))),
+			}
+		}
+		Ok(args)
+	}
+}
@@ -1,5 +1,6 @@
-use cgmath::Point2;
-use num::{NumCast, Zero};
+use cgmath::{InnerSpace, MetricSpace, Point2};
+use num::{Bounded, NumCast, Zero};
+use serde::{Deserialize, Serialize};
 use std::{
 	cmp::PartialOrd,
 	convert::{From, Into},
@@ -8,7 +9,7 @@ use std::{
 };
 use winit::dpi::PhysicalSize;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Dimensions<T: Copy> {
 	pub w: T,
 	pub h: T,
@@ -20,12 +21,26 @@ impl<T: Copy> From<(T, T)> for Dimensions<T> {
 	}
 }
 
-impl<T: Copy + NumCast> Dimensions<T> {
-	pub fn into_dim<V: Copy + NumCast>(self) -> Dimensions<V> {
-		Dimensions { w: num::cast(self.w).unwrap(), h: num::cast(self.h).unwrap() }
+impl<T: Copy + NumCast + PartialOrd + Zero> Dimensions<T> {
+	/// Values out of the range of `V` are clamped to it
+	pub fn into_dim<V: Copy + NumCast + Bounded + Zero>(self) -> Dimensions<V> {
+		Dimensions { w: saturating_cast(self.w), h: saturating_cast(self.h) }
 	}
 }
 
+fn saturating_cast<T: Copy + NumCast + PartialOrd + Zero, V: NumCast + Bounded + Zero>(x: T) -> V {
+	num::cast(x).unwrap_or_else(|| {
+		if x > T::zero() {
+			V::max_value()
+		} else if x < T::zero() {
+			V::min_value()
+		} else {
+			// NaN
+			V::zero()
+		}
+	})
+}
+
 /// Implements a given operation for Dimensions
 ///
 /// Basic usage:
@@ -145,7 +160,7 @@ macro_rules! dim_physical_size_equivalent {
 
 dim_physical_size_equivalent!(i32, u32);
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Rect<T: Copy> {
 	pub top_left: Point2<T>,
 	pub dims: Dimensions<T>,
@@ -210,6 +225,16 @@ macro_rules! apply_interface_int {
 
 apply_interface_int!(i32, u32);
 
+impl RectF {
+	/// The same rectangle extended by `margin` on every side
+	pub fn grow(self, margin: f32) -> RectF {
+		Rect {
+			top_left: (self.top_left.x - margin, self.top_left.y - margin).into(),
+			dims: (self.dims.w + 2. * margin, self.dims.h + 2. * margin).into(),
+		}
+	}
+}
+
 impl RectI {
 	pub fn from_float(pos: Point2<f32>, dims: Dimensions<f32>) -> RectI {
 		Rect {
@@ -272,35 +297,6 @@ impl Iterator for IterPointRect {
 	}
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct CenteredBox {
-	pub center: Point2<f32>,
-	pub dims: Dimensions<f32>,
-}
-
-#[allow(dead_code)]
-impl CenteredBox {
-	fn top(&self) -> f32 {
-		self.center.y - (self.dims.h / 2.)
-	}
-	fn left(&self) -> f32 {
-		self.center.x - (self.dims.w / 2.)
-	}
-	fn bottom(&self) -> f32 {
-		self.center.y + (self.dims.h / 2.)
-	}
-	fn right(&self) -> f32 {
-		self.center.x + (self.dims.w / 2.)
-	}
-
-	pub fn contains(&self, coords: Point2<f32>) -> bool {
-		self.left() <= coords.x
-			&& coords.x < self.right()
-			&& self.top() <= coords.y
-			&& coords.y < self.bottom()
-	}
-}
-
 pub fn collide_rectangle(
 	pos_a: Point2<f32>,
 	size_a: Dimensions<f32>,
@@ -312,3 +308,14 @@ pub fn collide_rectangle(
 		|| pos_a.y + size_a.h / 2. < pos_b.y - size_b.h / 2.
 		|| pos_a.y - size_a.h / 2. > pos_b.y + size_b.h / 2.)
 }
+
+/// Distance from `pos` to the closest point of the segment going from `a` to `b`
+pub fn segment_distance(pos: Point2<f32>, a: Point2<f32>, b: Point2<f32>) -> f32 {
+	let ab = b - a;
+	let len2 = ab.magnitude2();
+	if len2 == 0. {
+		return pos.distance(a);
+	}
+	let t = ((pos - a).dot(ab) / len2).clamp(0., 1.);
+	pos.distance(a + ab * t)
+}
@@ -0,0 +1,42 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::rng::Rng;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+/// Keeps the seeds of the daily runs apart from the ones given on the command line
+const DAILY_SALT: u64 = 0x6862_685f_6461_696c;
+
+/// Days since the Unix epoch in UTC, every player is on the same day at the same moment
+pub fn today() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+		/ SECS_PER_DAY
+}
+
+/// Seed of the run of `day`, the waves roll the same for everyone
+pub fn seed(day: u64) -> u64 {
+	Rng::new(day ^ DAILY_SALT).next_u64()
+}
+
+/// `day` as a `YYYY-MM-DD` date
+pub fn date(day: u64) -> String {
+	// Days to civil date, from Howard Hinnant's date algorithms. Eras are 400 years long
+	let z = day as i64 + 719_468;
+	let era = z.div_euclid(146_097);
+	let day_of_era = z.rem_euclid(146_097);
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	// Months starting in March, February is last
+	let shifted_month = (5 * day_of_year + 2) / 153;
+	let day_of_month = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+	let month = if shifted_month < 10 {
+		shifted_month + 3
+	} else {
+		shifted_month - 9
+	};
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+	format!("{year}-{month:02}-{day_of_month:02}")
+}
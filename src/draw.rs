@@ -1,28 +1,46 @@
-use std::rc::Rc;
+use std::{borrow::Cow, rc::Rc, time::Duration};
 
-use cgmath::{Point2, Vector2};
+use cgmath::{Point2, Vector2, Zero};
 use image::{DynamicImage, GenericImageView, ImageFormat};
 use pixels::{Pixels, SurfaceTexture, TextureError};
 use winit::{
 	dpi::{PhysicalPosition, PhysicalSize},
 	event_loop::ActiveEventLoop,
+	monitor::MonitorHandle,
 	window::{Fullscreen, Window},
 };
 
 use crate::{
-	coords::{text_box, Dimensions, Rect, RectI},
-	game::{Config, Game, GameInfo, MenuChoice},
-	gameplay::{Enemy, EnemyType, Player, ProjType, Projectile, World},
+	assets,
+	coords::{segment_distance, text_box, Dimensions, Rect, RectI},
+	daily,
+	ending::{VictoryChoice, CREDITS, CREDITS_LINE_HEIGHT},
+	error::GameError,
+	frame_graph::{FrameGraph, FRAME_SAMPLES},
+	game::{Config, FullscreenMode, Game, GameInfo, Inputs, LevelMode, MenuChoice, PauseChoice},
+	game_over::GameOverChoice,
+	gameplay::{
+		Enemy, HitArea, HitKind, ItemKind, Player, PopupKind, ProjType, Projectile, Shake, World,
+		CELEBRATION_TICKS, GRAZE_METER_MAX, HYPER_TICKS, ITEM_SIZE, LASER_WIDTH, MILESTONES,
+		PLAYER_HITBOX,
+	},
+	hazard::HazardKind,
+	input_display::{pad_cell, InputHistory},
+	replay::ReplayMode,
+	settings::{Action, ControlPreset},
+	sprites,
+	status::StatusKind,
+	timing::format_time,
 };
 
+pub const WINDOW_TITLE: &str = "Holy Bullet Hell";
+
 #[derive(Debug)]
 pub struct DrawConstants {
-	interface_begin4: u32,
 	pub sizes: [Dimensions<u32>; 3],
 }
 
 pub const DRAW_CONSTANTS: DrawConstants = DrawConstants {
-	interface_begin4: 3,
 	sizes: [
 		Dimensions { w: 1280, h: 720 },
 		Dimensions { w: 1600, h: 900 },
@@ -38,6 +56,55 @@ struct ColorPalette {
 	bg_ui: [u8; 4],
 	menu_select: [u8; 4],
 	menu_text: [u8; 4],
+	panel_label: [u8; 4],
+	item_point: [u8; 4],
+	item_power: [u8; 4],
+	item_chain: [u8; 4],
+	item_life: [u8; 4],
+	item_missile: [u8; 4],
+	item_option: [u8; 4],
+	hit: [u8; 4],
+	/// Line shown before a laser fires, then its beam
+	laser_warning: [u8; 4],
+	laser: [u8; 4],
+	barrel: [u8; 4],
+	hit_critical: [u8; 4],
+	/// Shot stopped by an armored front
+	hit_armored: [u8; 4],
+	/// Tints of the enemies under a status effect, and of the shots applying it
+	slow: [u8; 4],
+	burn: [u8; 4],
+	stun: [u8; 4],
+	ghost: [u8; 4],
+	hyper: [u8; 4],
+	barrier: [u8; 4],
+	/// Halo around the hitbox while focused
+	focus: [u8; 4],
+	wind: [u8; 4],
+	slow_field: [u8; 4],
+	well_area: [u8; 4],
+	well_core: [u8; 4],
+	pause_overlay: [u8; 4],
+	bullet_outline: [u8; 4],
+	/// Enemy bullets while the player is invulnerable after a hit, they go through it
+	bullet_intangible: [u8; 4],
+	/// Parts of the input display not pressed
+	input_off: [u8; 4],
+	boss_bar: [u8; 4],
+	boss_bar_bg: [u8; 4],
+	/// Outlines of the hitbox overlay
+	hitbox_player: [u8; 4],
+	hitbox_enemy: [u8; 4],
+	hitbox_weak_point: [u8; 4],
+	hitbox_player_shot: [u8; 4],
+	hitbox_enemy_bullet: [u8; 4],
+	/// Parts of the bars of the frame time overlay
+	frame_update: [u8; 4],
+	frame_render: [u8; 4],
+	frame_idle: [u8; 4],
+	frame_graph_bg: [u8; 4],
+	/// Line at the length of a 60 Hz frame
+	frame_budget: [u8; 4],
 }
 
 const COLORS: ColorPalette = ColorPalette {
@@ -45,25 +112,187 @@ const COLORS: ColorPalette = ColorPalette {
 	bg_ui: [0x20, 0x11, 0x38, 0xff],
 	menu_select: [0xff, 0x00, 0x00, 0xff],
 	menu_text: [0xff, 0xff, 0xff, 0xff],
+	panel_label: [0xff, 0xff, 0xff, 0xb0],
+	item_point: [0x3c, 0x8c, 0xff, 0xff],
+	item_power: [0xff, 0x3c, 0x3c, 0xff],
+	item_chain: [0xff, 0xd7, 0x00, 0xff],
+	item_life: [0xff, 0x78, 0xb4, 0xff],
+	item_missile: [0x50, 0xdc, 0x50, 0xff],
+	item_option: [0x78, 0xc8, 0xff, 0xff],
+	hit: [0xff, 0xff, 0xff, 0xff],
+	hit_critical: [0xff, 0x8c, 0x00, 0xff],
+	hit_armored: [0x8c, 0x8c, 0x8c, 0xff],
+	laser_warning: [0xff, 0x3c, 0x78, 0x60],
+	laser: [0xff, 0x96, 0xc8, 0xe0],
+	barrel: [0xa0, 0xa0, 0xb4, 0xff],
+	slow: [0x3c, 0xc8, 0xff, 0x90],
+	burn: [0xff, 0x50, 0x00, 0x90],
+	stun: [0xff, 0xff, 0x64, 0x90],
+	ghost: [0x96, 0xc8, 0xff, 0xa0],
+	hyper: [0xff, 0x3c, 0xc8, 0xff],
+	barrier: [0x64, 0xff, 0xc8, 0xff],
+	focus: [0xff, 0xff, 0xff, 0x90],
+	wind: [0x96, 0xdc, 0xff, 0x28],
+	slow_field: [0x8c, 0x50, 0xff, 0x28],
+	well_area: [0x50, 0x28, 0x78, 0x30],
+	well_core: [0x14, 0x00, 0x28, 0xff],
+	pause_overlay: [0x08, 0x0b, 0x1e, 0xb0],
+	bullet_outline: [0xff, 0xff, 0xff, 0xa0],
+	bullet_intangible: [0xc8, 0xc8, 0xdc, 0x50],
+	input_off: [0xff, 0xff, 0xff, 0x30],
+	boss_bar: [0xff, 0x3c, 0x3c, 0xff],
+	boss_bar_bg: [0x40, 0x10, 0x10, 0xc0],
+	hitbox_player: [0x00, 0xff, 0x00, 0xff],
+	hitbox_enemy: [0xff, 0x00, 0x00, 0xff],
+	hitbox_weak_point: [0xff, 0xff, 0x00, 0xff],
+	hitbox_player_shot: [0x00, 0xff, 0xff, 0xff],
+	hitbox_enemy_bullet: [0xff, 0x00, 0xff, 0xff],
+	frame_update: [0x3c, 0xc8, 0x50, 0xff],
+	frame_render: [0x3c, 0x8c, 0xff, 0xff],
+	frame_idle: [0xff, 0xff, 0xff, 0x40],
+	frame_graph_bg: [0x00, 0x00, 0x00, 0xa0],
+	frame_budget: [0xff, 0x3c, 0x3c, 0xc0],
 };
 
+fn status_tint(kind: StatusKind) -> [u8; 4] {
+	match kind {
+		StatusKind::Slow => COLORS.slow,
+		StatusKind::Burn => COLORS.burn,
+		StatusKind::Stun => COLORS.stun,
+	}
+}
+
 #[derive(Debug)]
 pub struct Sheets {
-	font: DynamicImage,
-	spritesheet: DynamicImage,
+	pub font: DynamicImage,
+	pub spritesheet: DynamicImage,
 }
 
 impl Sheets {
-	pub fn load() -> Self {
-		const FONT_FILE: &[u8] = include_bytes!("../assets/font.png");
-		const SPRITESHEET_FILE: &[u8] = include_bytes!("../assets/spritesheet.png");
-		let font: DynamicImage = image::load_from_memory_with_format(FONT_FILE, ImageFormat::Png)
-			.expect("Failed to load font file");
-		let spritesheet: DynamicImage =
-			image::load_from_memory_with_format(SPRITESHEET_FILE, ImageFormat::Png)
-				.expect("Failed to load spritesheet");
-		Sheets { font, spritesheet }
+	pub fn load() -> Result<Sheets, GameError> {
+		Ok(Sheets {
+			font: load_sheet("font.png", assets::read)?,
+			spritesheet: load_sheet("spritesheet.png", assets::read)?,
+		})
 	}
+
+	/// The sheets built into the binary, to show errors when the ones on disk can't be used
+	pub fn embedded() -> Sheets {
+		Sheets {
+			font: load_sheet("font.png", assets::embedded).unwrap(),
+			spritesheet: load_sheet("spritesheet.png", assets::embedded).unwrap(),
+		}
+	}
+}
+
+fn load_sheet(
+	name: &str,
+	read: fn(&str) -> Option<Cow<'static, [u8]>>,
+) -> Result<DynamicImage, GameError> {
+	let file = read(name).ok_or(GameError::asset(name, "missing file"))?;
+	image::load_from_memory_with_format(&file, ImageFormat::Png)
+		.map_err(|err| GameError::asset(name, err))
+}
+
+/// Characters on a line of the error screen, it fits the base resolution at scale 2
+const ERROR_LINE_LEN: usize = 36;
+
+/// How dark the background gets during boss fights, 1 would be pitch black
+const BG_DIM_MAX: f32 = 0.6;
+/// Time for the background to fully dim or light back up
+pub const BG_DIM_FADE_SECS: f32 = 0.5;
+
+/// Angular speeds of the shake offset on each axis, different for it not to go in a line
+const SHAKE_SPEED: Vector2<f32> = Vector2::new(71., 53.);
+
+/// Drawing state that persists between frames
+#[derive(Clone, Debug)]
+pub struct RenderState {
+	/// Current dimming of the background, between 0 and `BG_DIM_MAX`
+	bg_dim: f32,
+	/// Shake of the playfield and the seconds since it started
+	shake: Option<(Shake, f32)>,
+	/// Debug overlay outlining what the collisions test, toggled with F3
+	pub show_hitboxes: bool,
+	/// Recent frame times in the bottom left corner, toggled with F4
+	pub show_frame_graph: bool,
+}
+
+impl RenderState {
+	pub fn new() -> RenderState {
+		RenderState {
+			bg_dim: 0.,
+			shake: None,
+			show_hitboxes: false,
+			show_frame_graph: false,
+		}
+	}
+
+	/// Starts shaking the playfield, unless a stronger shake is going on
+	pub fn shake(&mut self, shake: Shake) {
+		if self
+			.shake
+			.is_none_or(|(current, elapsed)| shake_amplitude(current, elapsed) <= shake.amplitude)
+		{
+			self.shake = Some((shake, 0.));
+		}
+	}
+
+	/// Offset of the playfield in base resolution pixels
+	fn shake_offset(&self) -> Vector2<f32> {
+		let Some((shake, elapsed)) = self.shake else {
+			return Vector2::zero();
+		};
+		let amplitude = shake_amplitude(shake, elapsed);
+		Vector2::new(
+			amplitude * (SHAKE_SPEED.x * elapsed).sin(),
+			amplitude * (SHAKE_SPEED.y * elapsed).cos(),
+		)
+	}
+
+	/// Moves the background dimming toward its target depending on the boss and its phases, and
+	/// the shake along
+	pub fn update(&mut self, world: &World, dt: f32) {
+		if let Some((shake, elapsed)) = &mut self.shake {
+			*elapsed += dt;
+			if *elapsed >= shake.duration {
+				self.shake = None;
+			}
+		}
+		let target = if world.background_dimmed() {
+			BG_DIM_MAX
+		} else {
+			0.
+		};
+		let step = BG_DIM_MAX * dt / BG_DIM_FADE_SECS;
+		if self.bg_dim < target {
+			self.bg_dim = (self.bg_dim + step).min(target);
+		} else {
+			self.bg_dim = (self.bg_dim - step).max(target);
+		}
+	}
+}
+
+/// Amplitude left of a shake after `elapsed` seconds
+fn shake_amplitude(shake: Shake, elapsed: f32) -> f32 {
+	shake.amplitude * (1. - elapsed / shake.duration).max(0.)
+}
+
+/// Seconds the player sprite stays shown or hidden while blinking, at the start and at the end
+/// of the invulnerability after a hit. The blinking speeds up as it runs out
+const BLINK_SLOW: f32 = 0.12;
+const BLINK_FAST: f32 = 0.04;
+
+/// Brightness added to the background when a milestone is reached
+const MILESTONE_FLASH: f32 = 1.5;
+
+/// Size the option satellites are drawn at, half the player sprite
+const OPTION_SIZE: Dimensions<f32> = Dimensions { w: 24., h: 24. };
+
+/// Darkens a color, `amount` being the fraction of brightness removed, a negative amount brightens
+fn dimmed(color: [u8; 4], amount: f32) -> [u8; 4] {
+	let dim = |c: u8| (c as f32 * (1. - amount)).round() as u8;
+	[dim(color[0]), dim(color[1]), dim(color[2]), color[3]]
 }
 
 pub const CHAR_DIMS: Dimensions<u32> = Dimensions { w: 4, h: 6 };
@@ -81,26 +310,70 @@ pub fn conv_srgb_to_linear(x: f64) -> f64 {
 	}
 }
 
-pub fn create_window(event_loop: &ActiveEventLoop) -> Window {
-	let win_size = PhysicalSize::new(DRAW_CONSTANTS.sizes[1].w, DRAW_CONSTANTS.sizes[1].h);
+/// Fullscreen setting matching the config, `None` when windowed
+fn fullscreen_for(config: &Config, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+	// Last entry must be the screen size
+	if config.resolution_choice != N_SIZES - 1 {
+		return None;
+	}
+	match config.fullscreen_mode {
+		FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+		FullscreenMode::Exclusive => {
+			// Biggest video mode of the monitor, with the highest refresh rate
+			let video_mode = monitor.as_ref().and_then(|monitor| {
+				monitor.video_modes().max_by_key(|mode| {
+					(
+						mode.size().width,
+						mode.size().height,
+						mode.refresh_rate_millihertz(),
+					)
+				})
+			});
+			match video_mode {
+				Some(mode) => Some(Fullscreen::Exclusive(mode)),
+				// Some platforms don't expose video modes, borderless is the closest thing
+				None => Some(Fullscreen::Borderless(monitor)),
+			}
+		},
+	}
+}
+
+pub fn create_window(event_loop: &ActiveEventLoop, config: &Config) -> Window {
+	let monitor = event_loop
+		.available_monitors()
+		.nth(config.monitor)
+		.or(event_loop.primary_monitor());
+	// Window is on the top left corner of the chosen monitor
+	let position = monitor
+		.as_ref()
+		.map_or(PhysicalPosition::new(0, 0), |monitor| monitor.position());
+	let win_size: PhysicalSize<u32> = DRAW_CONSTANTS.sizes[config.resolution_choice as usize].into();
 	let window_attributes = Window::default_attributes()
-		.with_title("Holy Bullet Hell")
+		.with_title(WINDOW_TITLE)
 		.with_inner_size(win_size)
 		.with_resizable(false)
-		.with_fullscreen(None)
-		// Window is on the top left corner
-		.with_position(PhysicalPosition::new(0, 0));
+		.with_fullscreen(fullscreen_for(config, monitor))
+		.with_position(position);
 	event_loop.create_window(window_attributes).unwrap()
 }
 
+/// Where the frames end up
+#[derive(Debug)]
+enum RenderTarget {
+	/// Displayed in the window
+	Window(Box<Pixels>),
+	/// Kept in memory, used to render without a window
+	Offscreen(Vec<u8>),
+}
+
 #[derive(Debug)]
 pub struct FrameBuffer {
-	pub buffer: Pixels,
+	target: RenderTarget,
 	pub dims: Dimensions<u32>,
 }
 
 impl FrameBuffer {
-	pub fn new(window: &Window) -> Self {
+	pub fn new(window: &Window, vsync: bool) -> Self {
 		let dims: Dimensions<u32> = window.inner_size().into();
 		let bg_color_wgpu: pixels::wgpu::Color = {
 			pixels::wgpu::Color {
@@ -114,49 +387,106 @@ impl FrameBuffer {
 			let surface_texture = SurfaceTexture::new(dims.w, dims.h, &window);
 			pixels::PixelsBuilder::new(dims.w, dims.h, surface_texture)
 				.clear_color(bg_color_wgpu)
+				.enable_vsync(vsync)
 				.build()
 				.unwrap()
 		};
-		FrameBuffer { buffer, dims }
+		FrameBuffer { target: RenderTarget::Window(Box::new(buffer)), dims }
 	}
 
-	fn resize_buffer(&mut self, size: &PhysicalSize<u32>) -> Result<(), TextureError> {
-		// Resize the window surface
-		self.buffer.resize_surface(size.width, size.height)?;
-		// Resize the pixel buffer
-		self.buffer.resize_buffer(size.width, size.height)?;
+	/// Creates a frame buffer that isn't tied to any window
+	pub fn offscreen(dims: Dimensions<u32>) -> Self {
+		let buffer = vec![0x00; (dims.w * dims.h * 4) as usize];
+		FrameBuffer { target: RenderTarget::Offscreen(buffer), dims }
+	}
+
+	pub fn frame(&self) -> &[u8] {
+		match &self.target {
+			RenderTarget::Window(buffer) => buffer.frame(),
+			RenderTarget::Offscreen(buffer) => buffer,
+		}
+	}
+
+	fn frame_mut(&mut self) -> &mut [u8] {
+		match &mut self.target {
+			RenderTarget::Window(buffer) => buffer.frame_mut(),
+			RenderTarget::Offscreen(buffer) => buffer,
+		}
+	}
+
+	/// The surface takes the whole window, the pixel buffer keeps the logical `dims` and is
+	/// scaled up and centered in it, with bars of the clear color around
+	fn resize_buffer(
+		&mut self,
+		size: &PhysicalSize<u32>,
+		dims: Dimensions<u32>,
+	) -> Result<(), TextureError> {
+		match &mut self.target {
+			RenderTarget::Window(buffer) => {
+				// Resize the window surface
+				buffer.resize_surface(size.width, size.height)?;
+				// Resize the pixel buffer
+				buffer.resize_buffer(dims.w, dims.h)?;
+			},
+			RenderTarget::Offscreen(buffer) => {
+				buffer.resize((dims.w * dims.h * 4) as usize, 0x00);
+			},
+		}
 		// Update the dimensions
-		self.dims = (*size).into();
+		self.dims = dims;
 		Ok(())
 	}
 
+	/// The present mode is only picked when building, so the surface is rebuilt and needs a
+	/// resize afterwards
+	pub fn set_vsync(&mut self, window: &Window, vsync: bool) {
+		if let RenderTarget::Window(_) = self.target {
+			// Drop the old surface first, a window can only have one
+			self.target = RenderTarget::Offscreen(Vec::new());
+			*self = FrameBuffer::new(window, vsync);
+		}
+	}
+
+	fn render(&self) {
+		// Nothing to show when offscreen
+		if let RenderTarget::Window(buffer) = &self.target {
+			buffer.render().unwrap();
+		}
+	}
+
 	fn fill_with_color(&mut self, color: [u8; 4]) {
 		self
-			.buffer
 			.frame_mut()
 			.chunks_exact_mut(4)
 			.for_each(|pixel| pixel.copy_from_slice(&color));
 	}
 
 	fn iter_pixel_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
-		self.buffer.frame_mut().chunks_exact_mut(4)
+		self.frame_mut().chunks_exact_mut(4)
 	}
 }
 
 pub trait ResizableWindow {
-	fn request_window_resize(&mut self, index: u8) -> PhysicalSize<u32>;
+	fn request_window_resize(&mut self, config: &Config) -> PhysicalSize<u32>;
 }
 
 impl ResizableWindow for Window {
-	fn request_window_resize(&mut self, index: u8) -> PhysicalSize<u32> {
-		// Last entry must be the screen size
-		if index == N_SIZES - 1 {
-			let fs = Fullscreen::Borderless(self.current_monitor());
-			self.set_fullscreen(Some(fs));
-		} else {
+	fn request_window_resize(&mut self, config: &Config) -> PhysicalSize<u32> {
+		let monitor = self
+			.available_monitors()
+			.nth(config.monitor)
+			.or(self.current_monitor());
+		let fullscreen = fullscreen_for(config, monitor.clone());
+		if fullscreen.is_none() {
+			// Leaving fullscreen first so that the window can be moved
 			self.set_fullscreen(None);
+			if let Some(monitor) = monitor {
+				self.set_outer_position(monitor.position());
+			}
+		} else {
+			self.set_fullscreen(fullscreen);
 		}
-		let size: PhysicalSize<u32> = DRAW_CONSTANTS.sizes[index as usize].into();
+		let size: PhysicalSize<u32> = DRAW_CONSTANTS.sizes[config.resolution_choice as usize].into();
 		let _ = self.request_inner_size(size);
 		size
 	}
@@ -167,26 +497,128 @@ impl Game {
 		self.window.request_redraw();
 	}
 
+	/// Frames are drawn at the base resolution times the biggest scale fitting the window, the
+	/// rest of the window is letterboxed
 	pub fn resize(&mut self, size: &PhysicalSize<u32>) {
-		self.frame_buffer.resize_buffer(size).unwrap();
-		self.config.scale4 = 4 * size.width / DRAW_CONSTANTS.sizes[0].w;
+		// Minimized
+		if size.width == 0 || size.height == 0 {
+			return;
+		}
+		let base = DRAW_CONSTANTS.sizes[0];
+		self.config.scale4 = (4 * size.width / base.w)
+			.min(4 * size.height / base.h)
+			.max(1);
+		let dims = base * self.config.scale4 / 4;
+		self.frame_buffer.resize_buffer(size, dims).unwrap();
 	}
 
 	pub fn render(&mut self) {
-		self.frame_buffer.buffer.render().unwrap();
+		if self.render_state.show_frame_graph {
+			draw_frame_graph(
+				&mut self.frame_buffer,
+				&self.infos.frame_graph,
+				self.config.scale4,
+			);
+		}
+		self.frame_buffer.render();
+		self.infos.render_time = self
+			.infos
+			.t
+			.elapsed()
+			.saturating_sub(self.infos.update_time);
 	}
 
 	pub fn draw_in_game(&mut self) {
-		self.frame_buffer.fill_with_color(COLORS.bg);
-		let world = &mut self.world.as_mut().unwrap();
-
-		world.draw_gameplay(&mut self.frame_buffer, &self.sheets, self.config.scale4);
-		world.draw_interface(
+		// The level was just left for the victory screen
+		let Some(world) = self.world.as_ref() else {
+			return;
+		};
+		self.render_state.update(world, self.infos.dt.as_secs_f32());
+		let level_name = match self.infos.level_id {
+			Some(id) => self.levels[id as usize].name.as_str(),
+			None => "",
+		};
+		draw_world(
 			&mut self.frame_buffer,
 			&self.sheets,
+			world,
+			&self.render_state,
 			&self.config,
 			&self.infos,
+			level_name,
 		);
+		// Mode specific status, under the lives
+		let mut status = vec![];
+		if let ReplayMode::Playback(playback) = &self.replay {
+			status.push(format!("REPLAY X{}", playback.speed()));
+			status.push(format!("{}/{}", world.tick, playback.replay.frames.len()));
+			if playback.paused {
+				status.push("PAUSED".to_string());
+			}
+			// Real time of the recorded run, the timer shows the in-game time of the playback
+			if !playback.replay.rta.is_zero() {
+				status.push(format!("RTA {}", format_time(playback.replay.rta)));
+			}
+		} else if self.config.practice {
+			status.push("PRACTICE".to_string());
+			status.push(format!("ATTEMPT {}", self.section.attempts));
+			if self.section.invincible {
+				status.push("INVINCIBLE".to_string());
+			}
+			status.push(format!("SLOT {}", self.quick_saves.slot + 1));
+			if !self.quick_saves.is_used() {
+				status.push("EMPTY".to_string());
+			}
+		} else if let Some(day) = self.daily {
+			status.push(format!("DAILY {}", daily::date(day)));
+			if let Some(best) = self.progress.best_daily(day) {
+				status.push(format!("BEST {}", best.score));
+			}
+		}
+		draw_status(
+			&mut self.frame_buffer,
+			&self.sheets,
+			world,
+			&self.config,
+			&status,
+		);
+		// Run timer, above the FPS
+		let clock = self.shown_clock();
+		let timer = format!("{} {}", clock.name(), format_time(self.clock_time(clock)));
+		let timer_dims = text_box(timer.len(), 4);
+		let timer_y = DRAW_CONSTANTS.sizes[0].h as i32 - 20 - 2 * timer_dims.h - 10;
+		draw_text(
+			&mut self.frame_buffer,
+			&self.sheets.font,
+			world.panel_rect(self.config.scale4, (20, timer_y), timer_dims),
+			COLORS.panel_label,
+			&timer,
+		);
+		if self.config.input_display {
+			draw_input_display(
+				&mut self.frame_buffer,
+				&self.sheets,
+				world,
+				&self.config,
+				&self.inputs,
+				&self.input_history,
+			);
+		}
+	}
+
+	/// Bullets drifting behind the menus, dimmed so the entries stay readable
+	fn draw_title_scene(&mut self) {
+		let scale = self.frame_buffer.dims.h as f32 / DRAW_CONSTANTS.sizes[0].h as f32;
+		for proj in self.title_scene.projectiles.iter() {
+			let color = dimmed(COLORS.menu_text, 0.7);
+			draw_sprite(
+				&mut self.frame_buffer,
+				&self.sheets.spritesheet,
+				proj.sprite_coords(),
+				Rect::from_float_scale(proj.body.pos, Dimensions { w: 10., h: 10. }, scale),
+				Some([color[0], color[1], color[2], 0x60]),
+			);
+		}
 	}
 
 	fn draw_menu_entry(
@@ -218,6 +650,7 @@ impl Game {
 	pub fn draw_menu(&mut self, choice: MenuChoice) {
 		// Background color
 		self.frame_buffer.fill_with_color(COLORS.bg);
+		self.draw_title_scene();
 
 		// Base positions for the menu entries
 		let (base_x, base_y, title_y) = {
@@ -229,31 +662,47 @@ impl Game {
 
 		match choice {
 			// Main menu
-			MenuChoice::Play | MenuChoice::Quit | MenuChoice::Options => {
-				self.draw_menu_entry("Holy Bullet Hell", (5, 5), (base_x, title_y).into(), false);
+			MenuChoice::Play
+			| MenuChoice::Practice
+			| MenuChoice::Daily
+			| MenuChoice::Modifiers
+			| MenuChoice::Quit
+			| MenuChoice::Options => {
+				let scale = self.frame_buffer.dims.h as f32 / DRAW_CONSTANTS.sizes[0].h as f32;
+				let logo_y = title_y + (self.title_scene.logo_offset() * scale).round() as i32;
+				self.draw_menu_entry("Holy Bullet Hell", (5, 5), (base_x, logo_y).into(), false);
 
-				self.draw_menu_entry(
-					"Start",
-					(3, 3),
-					(base_x, base_y).into(),
-					choice == MenuChoice::Play,
-				);
-				self.draw_menu_entry(
-					"Options",
-					(3, 3),
-					(base_x, base_y + 100).into(),
-					choice == MenuChoice::Options,
-				);
-				self.draw_menu_entry(
-					"Quit",
-					(3, 3),
-					(base_x, base_y + 200).into(),
-					choice == MenuChoice::Quit,
-				);
+				let entries = [
+					(MenuChoice::Play, "Start"),
+					(MenuChoice::Practice, "Practice"),
+					(MenuChoice::Daily, "Daily challenge"),
+					(MenuChoice::Modifiers, "Modifiers"),
+					(MenuChoice::Options, "Options"),
+					(MenuChoice::Quit, "Quit"),
+				];
+				for (i, (entry, text)) in entries.iter().enumerate() {
+					self.draw_menu_entry(
+						text,
+						(3, 3),
+						(base_x, base_y - 40 + 60 * i as i32).into(),
+						choice == *entry,
+					);
+				}
+				// The errors themselves are printed, they can have characters missing from the font
+				if !self.mod_errors.is_empty() {
+					let text = format!("{} mod error(s), see the console", self.mod_errors.len());
+					let win_h = self.frame_buffer.dims.h as i32;
+					self.draw_menu_entry(&text, (2, 2), (base_x, win_h - title_y).into(), false);
+				}
 			},
 			// Level selection menu
 			MenuChoice::Level(id) => {
-				self.draw_menu_entry("Level Selection", (5, 5), (base_x, title_y).into(), false);
+				let title = if self.config.practice {
+					"Practice"
+				} else {
+					"Level Selection"
+				};
+				self.draw_menu_entry(title, (5, 5), (base_x, title_y).into(), false);
 				// Gets the level list while dropping the mutable borrowing of `self`
 				let level_list: Vec<(u32, Rc<String>)> =
 					self.levels.iter().map(|x| (x.id, x.name.clone())).collect();
@@ -266,32 +715,628 @@ impl Game {
 						id == i as u16,
 					);
 				}
+				let mut text = format!(
+					"Character: {}   Weapon (Tab): {}   Difficulty (D): {}",
+					self.rules.character.name(),
+					self.rules.weapon.def().name,
+					self.config.difficulty().name()
+				);
+				if self.config.practice {
+					match self.section.start_of(id as u32) {
+						Some(event) => text += &format!("   Section (S): event {event}"),
+						None => text += "   Section (S): start",
+					}
+				}
+				let win_h = self.frame_buffer.dims.h as i32;
+				self.draw_menu_entry(&text, (2, 2), (base_x, win_h - title_y).into(), false);
+				// Top of the endless leaderboard or high score of the level, above it
+				let best = self.levels.get(id as usize).and_then(|level| {
+					if level.mode == LevelMode::Endless {
+						let best = self.progress.best_endless()?;
+						Some(format!(
+							"Best: {} at wave {} in {:.0}s ({})",
+							best.score,
+							best.waves,
+							best.time,
+							best.difficulty.name()
+						))
+					} else {
+						let best = self.progress.best_score(&level.name)?;
+						Some(format!("Best: {} ({})", best.score, best.difficulty.name()))
+					}
+				});
+				if let Some(text) = best {
+					let pos = (base_x, win_h - title_y - 40).into();
+					self.draw_menu_entry(&text, (2, 2), pos, false);
+				}
 			},
 			// Options menu
-			MenuChoice::Resolution => {
-				self.draw_menu_entry("Resolution", (5, 5), (base_x, title_y).into(), false);
+			MenuChoice::Resolution
+			| MenuChoice::DisplayMode
+			| MenuChoice::Monitor
+			| MenuChoice::Vsync
+			| MenuChoice::Difficulty
+			| MenuChoice::TitleStatus
+			| MenuChoice::MasterVolume
+			| MenuChoice::EffectsVolume
+			| MenuChoice::BulletOutline
+			| MenuChoice::ScreenShake
+			| MenuChoice::InputDisplay
+			| MenuChoice::DiscordPresence
+			| MenuChoice::Assist
+			| MenuChoice::StatsExport
+			| MenuChoice::Controls => {
+				self.draw_menu_entry("Options", (5, 5), (base_x, title_y).into(), false);
 
-				let res_choice = self.config.resolution_choice;
-				for (i, res) in DRAW_CONSTANTS.sizes.iter().enumerate() {
+				let res = DRAW_CONSTANTS.sizes[self.config.resolution_choice as usize];
+				let entries = [
+					(
+						MenuChoice::Resolution,
+						format!("Resolution: {:4} X {:4}", res.w, res.h),
+					),
+					(
+						MenuChoice::DisplayMode,
+						format!("Fullscreen: {}", self.config.fullscreen_mode.name()),
+					),
+					(
+						MenuChoice::Monitor,
+						format!("Monitor: {}", self.config.monitor + 1),
+					),
+					(
+						MenuChoice::Vsync,
+						format!("Vsync: {}", on_off(self.config.vsync)),
+					),
+					(
+						MenuChoice::Difficulty,
+						format!("Difficulty: {}", self.config.difficulty().name()),
+					),
+					(
+						MenuChoice::TitleStatus,
+						format!("Title status: {}", on_off(self.config.title_status)),
+					),
+					(
+						MenuChoice::MasterVolume,
+						format!("Volume: {}%", self.config.master_volume),
+					),
+					(
+						MenuChoice::EffectsVolume,
+						format!("Effects volume: {}%", self.config.effects_volume),
+					),
+					(
+						MenuChoice::BulletOutline,
+						format!("Bullet outline: {}", on_off(self.config.bullet_outline)),
+					),
+					(
+						MenuChoice::ScreenShake,
+						format!("Screen shake: {}", on_off(self.config.screen_shake)),
+					),
+					(
+						MenuChoice::InputDisplay,
+						format!("Input display: {}", on_off(self.config.input_display)),
+					),
+					(
+						MenuChoice::DiscordPresence,
+						format!(
+							"Discord presence: {}{}",
+							on_off(self.config.discord_presence),
+							// Nothing is sent without one
+							if self.config.discord_client_id.is_empty() {
+								" (no client id)"
+							} else {
+								""
+							}
+						),
+					),
+					(
+						MenuChoice::Assist,
+						format!("Assist mode: {}", on_off(self.config.assist.enabled)),
+					),
+					(
+						MenuChoice::StatsExport,
+						format!("Stats export: {}", self.config.stats_export.name()),
+					),
+					(MenuChoice::Controls, "Controls".to_string()),
+				];
+				// More entries than the other menus, they start higher and are closer to fit
+				for (i, (entry, text)) in entries.iter().enumerate() {
 					self.draw_menu_entry(
-						&format!("{:4} X {:4}", res.w, res.h),
+						text,
 						(3, 3),
-						(base_x, base_y + 100 * i as i32).into(),
-						res_choice == i as u8,
+						(base_x, base_y - 200 + 34 * i as i32).into(),
+						choice == *entry,
+					);
+				}
+			},
+			// Assist menu
+			MenuChoice::AssistEnabled
+			| MenuChoice::AssistHp
+			| MenuChoice::AssistImmunity
+			| MenuChoice::AssistBulletSpeed => {
+				self.draw_menu_entry("Assist mode", (5, 5), (base_x, title_y).into(), false);
+
+				let assist = &self.config.assist;
+				let entries = [
+					(
+						MenuChoice::AssistEnabled,
+						format!("Assist mode: {}", on_off(assist.enabled)),
+					),
+					(
+						MenuChoice::AssistHp,
+						format!("Extra lives: +{}", assist.extra_hp),
+					),
+					(
+						MenuChoice::AssistImmunity,
+						format!("Invulnerability: {:3}%", assist.immunity),
+					),
+					(
+						MenuChoice::AssistBulletSpeed,
+						format!("Bullet speed: {:3}%", assist.bullet_speed),
+					),
+				];
+				for (i, (entry, text)) in entries.iter().enumerate() {
+					self.draw_menu_entry(
+						text,
+						(3, 3),
+						(base_x, base_y + 60 * i as i32).into(),
+						choice == *entry,
 					);
 				}
+				// Changes made from the pause menu wait for the next level
+				let lines = [
+					"Assisted runs are marked on their score and replay",
+					"Used from the next level started",
+				];
+				for (i, line) in lines.into_iter().enumerate() {
+					self.draw_menu_entry(
+						line,
+						(2, 2),
+						(base_x, base_y + 60 * entries.len() as i32 + 30 * i as i32).into(),
+						false,
+					);
+				}
+			},
+			// Controls menu
+			MenuChoice::Preset | MenuChoice::Binding(_) | MenuChoice::Controller => {
+				self.draw_menu_entry("Controls", (5, 5), (base_x, title_y).into(), false);
+
+				let bindings = &self.config.bindings;
+				let preset = ControlPreset::matching(bindings).map_or("Custom", |preset| preset.name());
+				let mut entries = vec![(MenuChoice::Preset, format!("Preset: {preset}"))];
+				for action in Action::ALL {
+					let text = if self.rebinding == Some(action) {
+						format!("{}: press a key", action.name())
+					} else {
+						format!("{}: {}", action.name(), bindings.shown_key(action))
+					};
+					entries.push((MenuChoice::Binding(action), text));
+				}
+				let controller = self.gamepads.active_name().unwrap_or("None".to_string());
+				entries.push((MenuChoice::Controller, format!("Controller: {controller}")));
+				let preview = printable(&bindings.preview());
+				// Starts higher like the options menu, closer together as there are more entries
+				for (i, (entry, text)) in entries.iter().enumerate() {
+					self.draw_menu_entry(
+						&printable(text),
+						(3, 3),
+						(base_x, base_y - 160 + 34 * i as i32).into(),
+						choice == *entry,
+					);
+				}
+				// The whole layout, to check a preset at a glance
+				let win_h = self.frame_buffer.dims.h as i32;
+				self.draw_menu_entry(
+					&preview,
+					(2, 2),
+					(base_x, win_h - title_y - 30).into(),
+					false,
+				);
+				self.draw_menu_entry(
+					"The menus use the arrows, pause with Escape",
+					(2, 2),
+					(base_x, win_h - title_y).into(),
+					false,
+				);
 			},
+			// Modifiers menu
+			MenuChoice::BulletSpeed
+			| MenuChoice::DoubleDamage
+			| MenuChoice::InfiniteBombs
+			| MenuChoice::Mirrored
+			| MenuChoice::SecondLoop => {
+				self.draw_menu_entry("Modifiers", (5, 5), (base_x, title_y).into(), false);
+
+				let modifiers = &self.rules.modifiers;
+				let second_loop = if self.progress.second_loop_unlocked(&self.levels) {
+					on_off(modifiers.second_loop)
+				} else {
+					"Locked"
+				};
+				let entries = [
+					(
+						MenuChoice::BulletSpeed,
+						format!("Bullet speed: {:3}%", modifiers.bullet_speed),
+					),
+					(
+						MenuChoice::DoubleDamage,
+						format!("Double damage: {}", on_off(modifiers.double_damage)),
+					),
+					(
+						MenuChoice::InfiniteBombs,
+						format!("Infinite bombs: {}", on_off(modifiers.infinite_bombs)),
+					),
+					(
+						MenuChoice::Mirrored,
+						format!("Mirrored stage: {}", on_off(modifiers.mirrored)),
+					),
+					(
+						MenuChoice::SecondLoop,
+						format!("Second loop: {second_loop}"),
+					),
+				];
+				let scoring = modifiers.is_scoring();
+				for (i, (entry, text)) in entries.iter().enumerate() {
+					self.draw_menu_entry(
+						text,
+						(3, 3),
+						(base_x, base_y + 60 * i as i32).into(),
+						choice == *entry,
+					);
+				}
+				if !scoring {
+					self.draw_menu_entry(
+						"Runs with modifiers aren't scored",
+						(2, 2),
+						(base_x, base_y + 60 * entries.len() as i32).into(),
+						false,
+					);
+				}
+			},
+		}
+	}
+}
+
+impl Game {
+	/// Shows what went wrong and how to leave the screen
+	pub fn draw_error(&mut self) {
+		self.frame_buffer.fill_with_color(COLORS.bg);
+		let Some(error) = &self.error else {
+			return;
+		};
+		let lines = wrap(&printable(&error.to_string()), ERROR_LINE_LEN);
+		let hint = if error.is_fatal() {
+			"Press Escape to quit"
+		} else {
+			"Press Escape to go back to the menu"
+		};
+
+		let frame_buffer_dims = self.frame_buffer.dims;
+		let base_x = frame_buffer_dims.w as i32 / 2;
+		let title_y = frame_buffer_dims.h as i32 / 10;
+		self.draw_menu_entry("Error", (5, 5), (base_x, title_y).into(), false);
+		for (i, line) in lines.iter().enumerate() {
+			let y = 3 * title_y + 60 * i as i32;
+			self.draw_menu_entry(line, (2, 2), (base_x, y).into(), false);
+		}
+		let hint_y = frame_buffer_dims.h as i32 - title_y;
+		self.draw_menu_entry(hint, (2, 2), (base_x, hint_y).into(), true);
+	}
+}
+
+impl Game {
+	/// Pause menu over the frozen level, which is dimmed to keep the entries readable
+	pub fn draw_pause(&mut self, choice: PauseChoice) {
+		let frame_buffer_dims = self.frame_buffer.dims;
+		draw_rect(
+			&mut self.frame_buffer,
+			frame_buffer_dims.into_rect(),
+			COLORS.pause_overlay,
+		);
+		let base_x = frame_buffer_dims.w as i32 / 2;
+		let base_y = frame_buffer_dims.h as i32 / 2;
+		let title_y = frame_buffer_dims.h as i32 / 10;
+		self.draw_menu_entry("Paused", (5, 5), (base_x, title_y).into(), false);
+		if self.gamepads.lost.is_some() {
+			self.draw_menu_entry(
+				"Controller disconnected",
+				(2, 2),
+				(base_x, 2 * title_y).into(),
+				true,
+			);
+		}
+
+		let entries = [
+			(PauseChoice::Resume, "Resume"),
+			(PauseChoice::Restart, "Restart level"),
+			(PauseChoice::Options, "Options"),
+			(PauseChoice::Quit, "Quit to title"),
+		];
+		for (i, (entry, text)) in entries.iter().enumerate() {
+			self.draw_menu_entry(
+				text,
+				(3, 3),
+				(base_x, base_y + 80 * i as i32).into(),
+				choice == *entry,
+			);
+		}
+	}
+
+	pub fn draw_game_over(&mut self, choice: GameOverChoice) {
+		let frame_buffer_dims = self.frame_buffer.dims;
+		draw_rect(
+			&mut self.frame_buffer,
+			frame_buffer_dims.into_rect(),
+			COLORS.pause_overlay,
+		);
+		let base_x = frame_buffer_dims.w as i32 / 2;
+		let base_y = frame_buffer_dims.h as i32 / 2;
+		let title_y = frame_buffer_dims.h as i32 / 10;
+		self.draw_menu_entry("Game over", (5, 5), (base_x, title_y).into(), false);
+		if let Some(world) = &self.world {
+			let lines = [
+				format!("Score: {}  Grazes: {}", world.score, world.stats.grazes),
+				format!(
+					"Kills: {}  Game time: {}",
+					world.stats.kills,
+					format_time(world.clock.now())
+				),
+			];
+			for (i, line) in lines.iter().enumerate() {
+				let y = 2 * title_y + 50 * i as i32;
+				self.draw_menu_entry(line, (3, 3), (base_x, y).into(), false);
+			}
+		}
+
+		let entries = [
+			(GameOverChoice::Retry, "Retry"),
+			(GameOverChoice::Quit, "Quit to title"),
+		];
+		for (i, (entry, text)) in entries.iter().enumerate() {
+			self.draw_menu_entry(
+				text,
+				(3, 3),
+				(base_x, base_y + 80 * i as i32).into(),
+				choice == *entry,
+			);
+		}
+	}
+
+	pub fn draw_victory(&mut self, choice: VictoryChoice) {
+		self.frame_buffer.fill_with_color(COLORS.bg);
+		let Some(summary) = self.summary.clone() else {
+			return;
+		};
+		let frame_buffer_dims = self.frame_buffer.dims;
+		let base_x = frame_buffer_dims.w as i32 / 2;
+		let title_y = frame_buffer_dims.h as i32 / 10;
+		let title = if summary.last_level {
+			"Victory!"
+		} else {
+			"Stage clear!"
+		};
+		self.draw_menu_entry(title, (5, 5), (base_x, title_y).into(), false);
+
+		let tally = summary.tally;
+		let total = if !summary.scored {
+			format!("Total: {} (not scored)", tally.total())
+		} else if summary.assisted {
+			format!("Total: {} (assist mode)", tally.total())
+		} else if let Some(rank) = summary.high_score {
+			format!("Total: {} (high score #{})", tally.total(), rank + 1)
+		} else {
+			format!("Total: {}", tally.total())
+		};
+		let lines = [
+			format!("{} cleared", summary.level_name),
+			format!("Score: {}", tally.base),
+			format!("No miss bonus: {}", tally.no_miss),
+			format!("Graze bonus: {} ({} grazes)", tally.graze, summary.grazes),
+			format!("Time bonus: {}", tally.time),
+			format!(
+				"Missile bonus: {} ({} left)",
+				tally.missiles, summary.missiles
+			),
+			total,
+			format!("Real time: {}", format_time(summary.rta)),
+			format!("Game time: {}", format_time(summary.igt)),
+			format!("Difficulty: {}", summary.difficulty.name()),
+		];
+		for (i, line) in lines.iter().enumerate() {
+			let y = 2 * title_y + 20 + 40 * i as i32;
+			self.draw_menu_entry(&printable(line), (2, 2), (base_x, y).into(), false);
+		}
+		let continue_text = if summary.last_level {
+			"Credits"
+		} else {
+			"Back to title"
+		};
+		let entries = [
+			(VictoryChoice::Continue, continue_text),
+			(VictoryChoice::Retry, "Retry"),
+		];
+		for (i, (entry, text)) in entries.iter().enumerate() {
+			let y = frame_buffer_dims.h as i32 - title_y - 50 * (1 - i as i32);
+			self.draw_menu_entry(text, (3, 3), (base_x, y).into(), choice == *entry);
+		}
+	}
+
+	pub fn draw_credits(&mut self) {
+		self.frame_buffer.fill_with_color(COLORS.bg);
+		let frame_buffer_dims = self.frame_buffer.dims;
+		let base_x = frame_buffer_dims.w as i32 / 2;
+		// The credits are laid out in the base resolution
+		let scale = frame_buffer_dims.h as f32 / DRAW_CONSTANTS.sizes[0].h as f32;
+		for (i, line) in CREDITS.iter().enumerate() {
+			let base_y =
+				DRAW_CONSTANTS.sizes[0].h as f32 + i as f32 * CREDITS_LINE_HEIGHT - self.credits_scroll;
+			let y = (base_y * scale).round() as i32;
+			if line.is_empty() || y < 0 || y > frame_buffer_dims.h as i32 {
+				continue;
+			}
+			self.draw_menu_entry(line, (3, 3), (base_x, y).into(), i == 0);
 		}
 	}
 }
 
+fn on_off(on: bool) -> &'static str {
+	if on {
+		"On"
+	} else {
+		"Off"
+	}
+}
+
+/// Draws the playfield and the interface, without touching any window
+pub fn draw_world(
+	frame_buffer: &mut FrameBuffer,
+	sheets: &Sheets,
+	world: &World,
+	render_state: &RenderState,
+	config: &Config,
+	infos: &GameInfo,
+	level_name: &str,
+) {
+	let bg = world
+		.milestones
+		.checked_sub(1)
+		.map_or(COLORS.bg, |reached| MILESTONES[reached].bg);
+	// A milestone flashes the background, fading back over its celebration
+	let flash = world.celebrated_milestone().map_or(0., |_| {
+		let elapsed =
+			world.tick.saturating_sub(world.milestone_tick) as f32 / CELEBRATION_TICKS as f32;
+		MILESTONE_FLASH * (1. - elapsed)
+	});
+	frame_buffer.fill_with_color(dimmed(bg, render_state.bg_dim - flash));
+	let scale = config.scale4 as f32 / 4.;
+	for piece in world.background.pieces(world.tick, world.rect().dims) {
+		draw_rect(
+			frame_buffer,
+			Rect::from_float_scale(piece.center, piece.dims, scale),
+			dimmed(piece.color, render_state.bg_dim - flash),
+		);
+	}
+	world.draw_gameplay(
+		frame_buffer,
+		sheets,
+		config.scale4,
+		config.bullet_outline,
+		infos.interpolation(),
+	);
+	if render_state.show_hitboxes {
+		world.draw_hitboxes(frame_buffer, config.scale4);
+	}
+	// The playfield shakes under the interface, which stays still
+	let offset = render_state.shake_offset() * config.scale4 as f32 / 4.;
+	let offset = Vector2::new(offset.x.round() as i32, offset.y.round() as i32);
+	if offset != Vector2::zero() {
+		let dims = (
+			world.interface_begin_x(config.scale4) as i32,
+			frame_buffer.dims.h as i32,
+		);
+		shift_area(
+			frame_buffer,
+			Rect { top_left: (0, 0).into(), dims: dims.into() },
+			offset,
+		);
+	}
+	world.draw_interface(frame_buffer, sheets, config, infos, level_name);
+}
+
+/// Moves the pixels of `area` by `offset`, the edges uncovered repeat the closest pixel moved
+fn shift_area(frame_buffer: &mut FrameBuffer, area: RectI, offset: Vector2<i32>) {
+	let Rect { top_left, dims } = area;
+	let w = frame_buffer.dims.w as i32;
+	let source = frame_buffer.frame_mut().to_vec();
+	let frame = frame_buffer.frame_mut();
+	for coords in area.iter() {
+		let src_x = (coords.x - offset.x).clamp(top_left.x, top_left.x + dims.w - 1);
+		let src_y = (coords.y - offset.y).clamp(top_left.y, top_left.y + dims.h - 1);
+		let dst = (coords.y * w + coords.x) as usize * 4;
+		let src = (src_y * w + src_x) as usize * 4;
+		frame[dst..dst + 4].copy_from_slice(&source[src..src + 4]);
+	}
+}
+
+/// Radius of the dark center drawn for the gravity wells, in base resolution pixels
+const WELL_CORE_RADIUS: f32 = 10.;
+
 macro_rules! opacity {
 	($color: expr, $bg: expr, $alpha:expr, $index: literal) => {
 		($alpha * ($color[$index] as f32) + (1. - $alpha) * ($bg[$index] as f32)).round() as u8
 	};
 }
 
-pub fn draw_rect(frame_buffer: &mut FrameBuffer, dst: RectI, mut color: [u8; 4]) {
+/// Height of the frame time overlay in base resolution pixels
+const FRAME_GRAPH_H: i32 = 60;
+/// Frame time reaching the top of the overlay, two 60 Hz frames
+const FRAME_GRAPH_SPAN: f32 = 2. / 60.;
+/// Width of each bar, in base resolution pixels
+const FRAME_BAR_W: i32 = 2;
+
+/// Bars of the recent frames stacking the update, the render and the time left waiting, over the
+/// bottom left corner of the screen
+fn draw_frame_graph(frame_buffer: &mut FrameBuffer, graph: &FrameGraph, scale4: u32) {
+	let base_h = DRAW_CONSTANTS.sizes[0].h as i32;
+	let left = 8;
+	let bottom = base_h - 8;
+	let bar_h = |time: Duration| {
+		(time.as_secs_f32() / FRAME_GRAPH_SPAN * FRAME_GRAPH_H as f32)
+			.round()
+			.min(FRAME_GRAPH_H as f32) as i32
+	};
+	// Rectangle placed in base resolution from its bottom left corner
+	let rect = |x: i32, y: i32, w: i32, h: i32| {
+		Rect { top_left: (x, y - h).into(), dims: (w, h).into() }.scale4(scale4)
+	};
+	let graph_w = FRAME_SAMPLES as i32 * FRAME_BAR_W;
+	draw_rect(
+		frame_buffer,
+		rect(left, bottom, graph_w, FRAME_GRAPH_H),
+		COLORS.frame_graph_bg,
+	);
+	for (i, sample) in graph.samples().enumerate() {
+		let x = left + i as i32 * FRAME_BAR_W;
+		let update_h = bar_h(sample.update);
+		let render_h = bar_h(sample.update + sample.render) - update_h;
+		let total_h = bar_h(sample.total).max(update_h + render_h);
+		let parts = [
+			(0, update_h, COLORS.frame_update),
+			(update_h, render_h, COLORS.frame_render),
+			(
+				update_h + render_h,
+				total_h - update_h - render_h,
+				COLORS.frame_idle,
+			),
+		];
+		for (y, h, color) in parts {
+			draw_rect(frame_buffer, rect(x, bottom - y, FRAME_BAR_W, h), color);
+		}
+	}
+	draw_rect(
+		frame_buffer,
+		rect(left, bottom - FRAME_GRAPH_H / 2, graph_w, 1),
+		COLORS.frame_budget,
+	);
+}
+
+/// One pixel wide border along the inside of `dst`
+fn draw_outline(frame_buffer: &mut FrameBuffer, dst: RectI, color: [u8; 4]) {
+	let Rect { top_left, dims } = dst;
+	let edges = [
+		(0, 0, dims.w, 1),
+		(0, dims.h - 1, dims.w, 1),
+		(0, 1, 1, dims.h - 2),
+		(dims.w - 1, 1, 1, dims.h - 2),
+	];
+	for (x, y, w, h) in edges {
+		let edge = Rect {
+			top_left: (top_left.x + x, top_left.y + y).into(),
+			dims: (w, h).into(),
+		};
+		draw_rect(frame_buffer, edge, color);
+	}
+}
+
+pub fn draw_rect(frame_buffer: &mut FrameBuffer, dst: RectI, color: [u8; 4]) {
 	let frame_buffer_dims = frame_buffer.dims;
 	// Transparent
 	if color[3] == 0x00 {
@@ -303,19 +1348,64 @@ pub fn draw_rect(frame_buffer: &mut FrameBuffer, dst: RectI, mut color: [u8; 4])
 			let pixel_index = coords.y * frame_buffer_dims.w as i32 + coords.x;
 			let pixel_byte_index = pixel_index as usize * 4;
 			let pixel_bytes = pixel_byte_index..(pixel_byte_index + 4);
+			// Blended with each pixel under it
+			let mut px = color;
 			if color[3] != 0xff {
-				let old_color = frame_buffer
-					.buffer
-					.frame_mut()
-					.get(pixel_bytes.clone())
-					.unwrap();
+				let old_color = frame_buffer.frame_mut().get(pixel_bytes.clone()).unwrap();
 				let alpha = color[3] as f32 / 255.;
-				color[0] = opacity!(color, old_color, alpha, 0);
-				color[1] = opacity!(color, old_color, alpha, 1);
-				color[2] = opacity!(color, old_color, alpha, 2);
-				color[3] = 0xff;
+				px[0] = opacity!(color, old_color, alpha, 0);
+				px[1] = opacity!(color, old_color, alpha, 1);
+				px[2] = opacity!(color, old_color, alpha, 2);
+				px[3] = 0xff;
+			}
+			frame_buffer.frame_mut()[pixel_bytes].copy_from_slice(&px);
+		}
+	}
+}
+
+/// Fills the pixels closer to `center` than `radius`, blended like `draw_rect`
+fn draw_disc(frame_buffer: &mut FrameBuffer, center: Point2<i32>, radius: i32, color: [u8; 4]) {
+	let bounds = Rect {
+		top_left: (center.x - radius, center.y - radius).into(),
+		dims: (2 * radius + 1, 2 * radius + 1).into(),
+	};
+	for coords in bounds.iter() {
+		let (dx, dy) = (coords.x - center.x, coords.y - center.y);
+		if dx * dx + dy * dy <= radius * radius {
+			draw_rect(
+				frame_buffer,
+				Rect { top_left: coords, dims: (1, 1).into() },
+				color,
+			);
+		}
+	}
+}
+
+/// Fills the pixels closer than `width / 2` to the segment from `from` to `to`, blended like
+/// `draw_rect`. Each pixel is drawn once, the blending stays even along the beam
+fn draw_beam(
+	frame_buffer: &mut FrameBuffer,
+	from: Point2<f32>,
+	to: Point2<f32>,
+	width: f32,
+	color: [u8; 4],
+) {
+	let half = width / 2.;
+	let dims = frame_buffer.dims;
+	let min_x = (from.x.min(to.x) - half).max(0.) as i32;
+	let min_y = (from.y.min(to.y) - half).max(0.) as i32;
+	let max_x = (from.x.max(to.x) + half).min(dims.w as f32) as i32;
+	let max_y = (from.y.max(to.y) + half).min(dims.h as f32) as i32;
+	for y in min_y..max_y {
+		for x in min_x..max_x {
+			let center = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+			if segment_distance(center, from, to) <= half {
+				draw_rect(
+					frame_buffer,
+					Rect { top_left: (x, y).into(), dims: (1, 1).into() },
+					color,
+				);
 			}
-			frame_buffer.buffer.frame_mut()[pixel_bytes].copy_from_slice(&color);
 		}
 	}
 }
@@ -338,10 +1428,49 @@ fn char_position(c: char) -> Option<(u32, u32)> {
 		ch if sixth_line.contains(ch) => {
 			Some((sixth_line.chars().position(|c| c == ch).unwrap() as u32, 5))
 		},
-		_ => unimplemented!("Character {c} doesn't exist in font"),
+		_ => None,
 	}
 }
 
+/// Uppercased text where the characters missing from the font are replaced by '?'
+fn printable(text: &str) -> String {
+	text
+		.to_uppercase()
+		.chars()
+		.map(|c| {
+			if c == ' ' || char_position(c).is_some() {
+				c
+			} else {
+				'?'
+			}
+		})
+		.collect()
+}
+
+/// Splits the text into lines of at most `max_len` characters, between words when possible
+fn wrap(text: &str, max_len: usize) -> Vec<String> {
+	let mut lines = vec![];
+	let mut line = String::new();
+	for word in text.split_whitespace() {
+		if !line.is_empty() && line.len() + 1 + word.len() > max_len {
+			lines.push(std::mem::take(&mut line));
+		}
+		if !line.is_empty() {
+			line.push(' ');
+		}
+		line.push_str(word);
+		// Words too long for a line, like paths, are cut
+		while line.len() > max_len {
+			let rest = line.split_off(max_len);
+			lines.push(std::mem::replace(&mut line, rest));
+		}
+	}
+	if !line.is_empty() {
+		lines.push(line);
+	}
+	lines
+}
+
 #[derive(Clone, Debug)]
 struct SpriteCoords {
 	sheet_pos: Point2<u32>,
@@ -372,7 +1501,12 @@ fn draw_text(
 		draw_sprite(
 			frame_buffer,
 			font_sheet,
-			SpriteCoords { sheet_pos: char_position(c).unwrap().into(), dims: (4, 6).into() },
+			SpriteCoords {
+				sheet_pos: char_position(c)
+					.unwrap_or_else(|| panic!("Character '{c}' doesn't exist in font"))
+					.into(),
+				dims: (4, 6).into(),
+			},
 			dst_c,
 			Some(color),
 		);
@@ -413,25 +1547,22 @@ fn draw_sprite(
 			Some(col) => col,
 		};
 		if px[3] != 0xff {
-			let background = frame_buffer
-				.buffer
-				.frame_mut()
-				.get(pixel_bytes.clone())
-				.unwrap();
+			let background = frame_buffer.frame_mut().get(pixel_bytes.clone()).unwrap();
 			let alpha = px[3] as f32 / 255.;
 			px[0] = opacity!(px, background, alpha, 0);
 			px[1] = opacity!(px, background, alpha, 1);
 			px[2] = opacity!(px, background, alpha, 2);
 			px[3] = 0xff;
 		}
-		frame_buffer.buffer.frame_mut()[pixel_bytes].copy_from_slice(&px);
+		frame_buffer.frame_mut()[pixel_bytes].copy_from_slice(&px);
 	}
 }
 
 impl Player {
-	fn sprite_coords(&self) -> SpriteCoords {
+	/// Another sprite while invulnerable after a hit
+	fn sprite_coords(&self, now: Duration) -> SpriteCoords {
 		SpriteCoords {
-			sheet_pos: if self.immunity_over() { (1, 0) } else { (1, 1) }.into(),
+			sheet_pos: self.pose().tile(self.immunity_left(now) > 0.).into(),
 			dims: (8, 8).into(),
 		}
 	}
@@ -443,101 +1574,469 @@ impl Player {
 
 impl Enemy {
 	fn sprite_coords(&self) -> SpriteCoords {
-		SpriteCoords {
-			sheet_pos: match self.variant {
-				EnemyType::Basic => (2, 0),
-				EnemyType::Sniper => (3, 0),
-			}
-			.into(),
-			dims: (8, 8).into(),
-		}
+		SpriteCoords { sheet_pos: self.variant.def().sprite.into(), dims: (8, 8).into() }
 	}
 }
 
 impl Projectile {
 	fn sprite_coords(&self) -> SpriteCoords {
 		SpriteCoords {
-			sheet_pos: match self.variant {
-				ProjType::Basic => (2, 1),
-				ProjType::Aimed => (3, 1),
-				ProjType::PlayerShoot => (0, 1),
-			}
-			.into(),
+			sheet_pos: sprites::projectile_tile(&self.variant).into(),
 			dims: (8, 8).into(),
 		}
 	}
 }
 
+/// Lines of text in the side panel, under the lives
+fn draw_status(
+	frame_buffer: &mut FrameBuffer,
+	sheets: &Sheets,
+	world: &World,
+	config: &Config,
+	lines: &[String],
+) {
+	const TEXT_SCALE: u32 = 4;
+	const MARGIN: i32 = 20;
+	for (i, line) in lines.iter().enumerate() {
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			world.panel_rect(
+				config.scale4,
+				(MARGIN, 290 + 30 * i as i32),
+				text_box(line.len(), TEXT_SCALE),
+			),
+			COLORS.panel_label,
+			line,
+		);
+	}
+}
+
+/// Direction pad, buttons and history of the inputs, in the panel under the graze meter
+fn draw_input_display(
+	frame_buffer: &mut FrameBuffer,
+	sheets: &Sheets,
+	world: &World,
+	config: &Config,
+	inputs: &Inputs,
+	history: &InputHistory,
+) {
+	const TEXT_SCALE: u32 = 3;
+	const MARGIN: i32 = 20;
+	const TOP: i32 = 470;
+	let panel =
+		|top_left: (i32, i32), dims: Dimensions<i32>| world.panel_rect(config.scale4, top_left, dims);
+	let lit = pad_cell(inputs);
+	for col in 0..3 {
+		for row in 0..3 {
+			let color = if (col, row) == lit {
+				COLORS.menu_text
+			} else {
+				COLORS.input_off
+			};
+			draw_rect(
+				frame_buffer,
+				panel((MARGIN + 24 * col, TOP + 24 * row), (20, 20).into()),
+				color,
+			);
+		}
+	}
+	let buttons = [
+		(inputs.shoot, "SHOT"),
+		(inputs.dash, "DASH"),
+		(inputs.hyper, "HYPER"),
+		(inputs.focus, "FOCUS"),
+	];
+	for (i, (pressed, name)) in buttons.into_iter().enumerate() {
+		let color = if pressed {
+			COLORS.menu_text
+		} else {
+			COLORS.input_off
+		};
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel(
+				(MARGIN + 90, TOP + 24 * i as i32),
+				text_box(name.len(), TEXT_SCALE),
+			),
+			color,
+			name,
+		);
+	}
+	for (i, line) in history.lines().enumerate() {
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel(
+				(MARGIN, TOP + 104 + 22 * i as i32),
+				text_box(line.len(), TEXT_SCALE),
+			),
+			COLORS.panel_label,
+			&line,
+		);
+	}
+}
+
 impl World {
-	pub fn draw_gameplay(&self, frame_buffer: &mut FrameBuffer, sheets: &Sheets, scale4: u32) {
+	pub fn draw_gameplay(
+		&self,
+		frame_buffer: &mut FrameBuffer,
+		sheets: &Sheets,
+		scale4: u32,
+		bullet_outline: bool,
+		alpha: f32,
+	) {
 		let scale = scale4 as f32 / 4.;
+		// Hazard zones, under everything else
+		for active in self.hazards.iter() {
+			let color = match active.hazard.kind {
+				HazardKind::Wind(_) => COLORS.wind,
+				HazardKind::Slow(_) => COLORS.slow_field,
+			};
+			let zone = active.hazard.zone;
+			let center = zone.top_left + Vector2::new(zone.dims.w, zone.dims.h) / 2.;
+			draw_rect(
+				frame_buffer,
+				Rect::from_float_scale(center, zone.dims, scale),
+				color,
+			);
+		}
+		// Gravity wells, their pull area and their core
+		for active in self.wells.iter() {
+			let center = active.well.center * scale;
+			let center = Point2::new(center.x.round() as i32, center.y.round() as i32);
+			draw_disc(
+				frame_buffer,
+				center,
+				(active.well.radius * scale) as i32,
+				COLORS.well_area,
+			);
+			draw_disc(
+				frame_buffer,
+				center,
+				(WELL_CORE_RADIUS * scale) as i32,
+				COLORS.well_core,
+			);
+		}
 		// Player
 		let player = &self.player;
-		draw_sprite(
-			frame_buffer,
-			&sheets.spritesheet,
-			player.sprite_coords(),
-			Rect::from_float_scale(player.pos, player.size, scale),
-			None,
-		);
-		// Player hitbox
+		let now = self.clock.now();
+		// Dash afterimages, behind the player
+		for ghost in self.ghosts.iter() {
+			let [r, g, b, a] = COLORS.ghost;
+			draw_sprite(
+				frame_buffer,
+				&sheets.spritesheet,
+				player.sprite_coords(now),
+				Rect::from_float_scale(ghost.pos, player.size, scale),
+				Some([r, g, b, (a as f32 * ghost.opacity()) as u8]),
+			);
+		}
+		// Options, behind the player as they trail it
+		for pos in player.option_positions() {
+			draw_sprite(
+				frame_buffer,
+				&sheets.spritesheet,
+				SpriteCoords { sheet_pos: sprites::OPTION.into(), dims: (8, 8).into() },
+				Rect::from_float_scale(pos, OPTION_SIZE, scale),
+				None,
+			);
+		}
+		// Drawn between the last two ticks, `alpha` of the way
+		let player_pos = player.body.shown_pos(alpha);
+		// Blinks while invulnerable after a hit
+		let immunity_left = player.immunity_left(now);
+		let blink = BLINK_FAST + (BLINK_SLOW - BLINK_FAST) * immunity_left;
+		let hidden = immunity_left > 0. && (now.as_secs_f32() / blink) as u32 % 2 == 1;
+		// Reflect barrier, a halo around the player sprite
+		if player.barrier > 0 {
+			let [r, g, b, _] = COLORS.barrier;
+			draw_sprite(
+				frame_buffer,
+				&sheets.spritesheet,
+				player.sprite_coords(now),
+				Rect::from_float_scale(player_pos, player.size * 1.25, scale),
+				Some([r, g, b, 0x80]),
+			);
+		}
+		if !hidden {
+			draw_sprite(
+				frame_buffer,
+				&sheets.spritesheet,
+				player.sprite_coords(now),
+				Rect::from_float_scale(player_pos, player.size, scale),
+				None,
+			);
+		}
+		// Player hitbox, with a halo while focused so it stands out
+		if player.focused {
+			draw_sprite(
+				frame_buffer,
+				&sheets.spritesheet,
+				player.sprite_coords_hit(),
+				Rect::from_float_scale(player_pos, PLAYER_HITBOX * 2., scale),
+				Some(COLORS.focus),
+			);
+		}
 		draw_sprite(
 			frame_buffer,
 			&sheets.spritesheet,
 			player.sprite_coords_hit(),
-			Rect::from_float_scale(player.pos, player.hitbox.dims, scale),
+			Rect::from_float_scale(player_pos, PLAYER_HITBOX, scale),
 			None,
 		);
 
 		// Enemies
 		for enemy in self.enemies.iter() {
+			let enemy_pos = enemy.body.shown_pos(alpha);
 			draw_sprite(
 				frame_buffer,
 				&sheets.spritesheet,
 				enemy.sprite_coords(),
-				Rect::from_float_scale(enemy.pos, enemy.size, scale),
+				Rect::from_float_scale(enemy_pos, enemy.size, scale),
 				None,
 			);
+			// Blended over the sprite
+			if let Some(kind) = enemy.status.shown() {
+				draw_sprite(
+					frame_buffer,
+					&sheets.spritesheet,
+					enemy.sprite_coords(),
+					Rect::from_float_scale(enemy_pos, enemy.size, scale),
+					Some(status_tint(kind)),
+				);
+			}
 			draw_rect(
 				frame_buffer,
-				Rect::life_bar_full(enemy.pos, enemy.size).scale4(scale4),
+				Rect::life_bar_full(enemy_pos, enemy.size).scale4(scale4),
 				[0xff, 0x00, 0x00, 0xff],
 			);
 			draw_rect(
 				frame_buffer,
-				Rect::life_bar(
-					enemy.pos,
-					enemy.size,
-					enemy.hp / Enemy::max_hp(enemy.variant),
-				)
-				.scale4(scale4),
+				Rect::life_bar(enemy_pos, enemy.size, enemy.hp / enemy.max_hp()).scale4(scale4),
 				[0x00, 0xff, 0x00, 0xff],
 			);
+			// Barrel of the enemies turning toward the player, where they aim
+			if enemy.variant.def().turn_rate.is_some() {
+				let muzzle = enemy_pos + enemy.aim * enemy.size.h * 0.6;
+				draw_beam(
+					frame_buffer,
+					enemy_pos * scale,
+					muzzle * scale,
+					6. * scale,
+					COLORS.barrel,
+				);
+			}
+		}
+		// Explosions of the enemies killed, the size of the enemy
+		for explosion in self.explosions.iter() {
+			if let Some(tile) = sprites::EXPLOSION.frame(explosion.ticks) {
+				draw_sprite(
+					frame_buffer,
+					&sheets.spritesheet,
+					SpriteCoords { sheet_pos: tile.into(), dims: (8, 8).into() },
+					Rect::from_float_scale(explosion.pos, explosion.size, scale),
+					None,
+				);
+			}
+		}
+
+		// Items
+		for item in self.items.iter() {
+			let color = match item.kind {
+				ItemKind::Point => COLORS.item_point,
+				ItemKind::Power => COLORS.item_power,
+				ItemKind::Missile => COLORS.item_missile,
+				ItemKind::Option => COLORS.item_option,
+				ItemKind::Chain => COLORS.item_chain,
+				ItemKind::Barrier => COLORS.barrier,
+				ItemKind::Life => COLORS.item_life,
+			};
+			draw_rect(
+				frame_buffer,
+				Rect::from_float_scale(item.pos, ITEM_SIZE, scale),
+				color,
+			);
 		}
 
 		//projectiles
 		for proj in self.projectiles.iter() {
+			let proj_pos = proj.body.shown_pos(alpha);
+			// Thin line while it warns, the whole beam once it fires
+			if let (ProjType::Laser { warning, .. }, Some(end)) = (&proj.variant, proj.laser_end()) {
+				let (width, color) = if *warning > 0 {
+					(2., COLORS.laser_warning)
+				} else {
+					(LASER_WIDTH * scale, COLORS.laser)
+				};
+				draw_beam(
+					frame_buffer,
+					proj.body.pos * scale,
+					end * scale,
+					width,
+					color,
+				);
+			}
+			if bullet_outline && !proj.variant.is_player_shot() {
+				draw_rect(
+					frame_buffer,
+					Rect::from_float_scale(proj_pos, Dimensions { w: 14., h: 14. }, scale),
+					COLORS.bullet_outline,
+				);
+			}
 			draw_sprite(
 				frame_buffer,
 				&sheets.spritesheet,
 				proj.sprite_coords(),
-				Rect::from_float_scale(proj.pos, Dimensions { w: 10., h: 10. }, scale),
-				None,
+				Rect::from_float_scale(proj_pos, Dimensions { w: 10., h: 10. }, scale),
+				if immunity_left > 0. && !proj.variant.is_player_shot() {
+					Some(COLORS.bullet_intangible)
+				} else {
+					proj.status.map(|kind| {
+						let [r, g, b, _] = status_tint(kind);
+						[r, g, b, 0xff]
+					})
+				},
+			);
+		}
+
+		// Boss health bar across the top of the playfield, with the phase under it
+		if let Some((enemy, boss)) = self
+			.enemies
+			.iter()
+			.find_map(|enemy| enemy.boss.as_ref().map(|boss| (enemy, boss)))
+		{
+			let w = self.rect().dims.w as i32 - 40;
+			draw_rect(
+				frame_buffer,
+				Rect { top_left: (20, 12).into(), dims: Dimensions { w, h: 8 } }.scale4(scale4),
+				COLORS.boss_bar_bg,
+			);
+			let hp_w = (w as f32 * (enemy.hp / enemy.max_hp()).clamp(0., 1.)).round() as i32;
+			draw_rect(
+				frame_buffer,
+				Rect { top_left: (20, 12).into(), dims: Dimensions { w: hp_w, h: 8 } }.scale4(scale4),
+				COLORS.boss_bar,
+			);
+			let text = printable(&format!(
+				"{} {}/{}",
+				enemy.variant.def().name,
+				boss.phase + 1,
+				boss.phases
+			));
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				Rect { top_left: (20, 26).into(), dims: text_box(text.len(), 2) }.scale4(scale4),
+				COLORS.menu_text,
+				&text,
+			);
+		}
+
+		// Milestone banner, at the top of the playfield
+		if let Some(milestone) = self.celebrated_milestone() {
+			let text = format!("{} POINTS!", milestone.score);
+			let dims = text_box(text.len(), 3);
+			let x = (self.rect().dims.w as i32 - dims.w) / 2;
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				Rect { top_left: (x, 60).into(), dims }.scale4(scale4),
+				COLORS.item_chain,
+				&text,
+			);
+		}
+
+		// Particles of the explosions and impacts
+		for particle in self.particles.iter() {
+			draw_rect(
+				frame_buffer,
+				Rect::from_float_scale(particle.pos, Dimensions { w: 3., h: 3. }, scale),
+				particle.shown_color(),
+			);
+		}
+
+		// Hit effects, over the enemies they hit
+		for spark in self.hit_sparks.iter() {
+			let (size, color) = match spark.kind {
+				HitKind::Normal => (6., COLORS.hit),
+				HitKind::Critical => (14., COLORS.hit_critical),
+				HitKind::Armored => (4., COLORS.hit_armored),
+			};
+			draw_rect(
+				frame_buffer,
+				Rect::from_float_scale(spark.pos, Dimensions { w: size, h: size }, scale),
+				color,
+			);
+		}
+
+		// Damage and score popups, over everything else
+		let text_scale = (scale4 / 2).max(1);
+		for popup in self.popups.iter() {
+			let [r, g, b, a] = match popup.kind {
+				PopupKind::Damage(HitKind::Normal) => COLORS.hit,
+				PopupKind::Damage(HitKind::Critical) => COLORS.hit_critical,
+				PopupKind::Damage(HitKind::Armored) => COLORS.hit_armored,
+				PopupKind::Score => COLORS.menu_text,
+			};
+			let dims = text_box(popup.text.len(), text_scale);
+			let center = popup.pos * scale;
+			let top_left = Point2::new(
+				(center.x - dims.w as f32 / 2.).round() as i32,
+				(center.y - dims.h as f32 / 2.).round() as i32,
+			);
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				Rect { top_left, dims },
+				[r, g, b, (a as f32 * popup.opacity()) as u8],
+				&popup.text,
 			);
 		}
 	}
 
+	/// Outlines of the hit areas where the collisions see them, not where they are interpolated
+	fn draw_hitboxes(&self, frame_buffer: &mut FrameBuffer, scale4: u32) {
+		for (area, pos, dims) in self.hit_areas() {
+			let color = match area {
+				HitArea::Player => COLORS.hitbox_player,
+				HitArea::Enemy => COLORS.hitbox_enemy,
+				HitArea::WeakPoint => COLORS.hitbox_weak_point,
+				HitArea::PlayerShot => COLORS.hitbox_player_shot,
+				HitArea::EnemyBullet => COLORS.hitbox_enemy_bullet,
+			};
+			draw_outline(
+				frame_buffer,
+				RectI::from_float(pos, dims).scale4(scale4),
+				color,
+			);
+		}
+	}
+
+	/// The interface fills the space right of the playfield
+	fn interface_begin_x(&self, scale4: u32) -> u32 {
+		(self.rect().dims.w * scale4 as f32 / 4.).round() as u32
+	}
+
+	/// Rect placed in base resolution, relative to the panel's top left corner
+	fn panel_rect(&self, scale4: u32, top_left: (i32, i32), dims: Dimensions<i32>) -> RectI {
+		Rect { top_left: top_left.into(), dims }
+			.to_interface(self.interface_begin_x(scale4) as i32, scale4)
+	}
+
 	pub fn draw_interface(
 		&self,
 		frame_buffer: &mut FrameBuffer,
 		sheets: &Sheets,
 		config: &Config,
 		infos: &GameInfo,
+		level_name: &str,
 	) {
 		let frame_buffer_dims = frame_buffer.dims;
 		let win_w = frame_buffer_dims.w;
-		let interf_begin_x = DRAW_CONSTANTS.interface_begin4 * win_w / 4;
 		let scale4 = config.scale4;
+		let interf_begin_x = self.interface_begin_x(scale4);
 		// Interface background
 		frame_buffer
 			.iter_pixel_mut()
@@ -547,57 +2046,240 @@ impl World {
 					pixel.copy_from_slice(&COLORS.bg_ui)
 				}
 			});
-		// HP
-		for i in 0..self.player.hp {
-			draw_rect(
+
+		// Everything below is placed in base resolution, relative to the panel's top left corner
+		let panel =
+			|top_left: (i32, i32), dims: Dimensions<i32>| self.panel_rect(scale4, top_left, dims);
+		const TEXT_SCALE: u32 = 4;
+		const MARGIN: i32 = 20;
+
+		// Level name
+		let level_name = level_name.to_uppercase();
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN, 20), text_box(level_name.len(), 2 * TEXT_SCALE)),
+			[0xff, 0x00, 0x00, 0xff],
+			&level_name,
+		);
+
+		// Score
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN, 100), text_box("SCORE".len(), TEXT_SCALE)),
+			COLORS.panel_label,
+			"SCORE",
+		);
+		let score_str = format!("{score:08}", score = self.score);
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN, 130), text_box(score_str.len(), TEXT_SCALE)),
+			COLORS.menu_text,
+			&score_str,
+		);
+		// Waves of an endless run, right of the score
+		if let Some(endless) = &self.endless {
+			draw_text(
 				frame_buffer,
-				Rect {
-					top_left: ((20 + 60 * i) as i32, 120).into(),
-					dims: (40, 40).into(),
-				}
-				.to_interface(interf_begin_x as i32, scale4),
-				[0x11, 0x81, 0x0c, 0xff],
-			)
+				&sheets.font,
+				panel((MARGIN + 200, 100), text_box("WAVE".len(), TEXT_SCALE)),
+				COLORS.panel_label,
+				"WAVE",
+			);
+			let wave_str = format!("{}", endless.wave);
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				panel((MARGIN + 200, 130), text_box(wave_str.len(), TEXT_SCALE)),
+				COLORS.menu_text,
+				&wave_str,
+			);
 		}
 
-		const TEXT_SCALE: u32 = 4;
-		// Use base window size for interface to scale
-		let win_w = DRAW_CONSTANTS.sizes[0].w as i32;
+		if !self.is_scoring() {
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				panel((MARGIN, 158), text_box("NOT SCORED".len(), TEXT_SCALE)),
+				[0xff, 0x00, 0x00, 0xff],
+				"NOT SCORED",
+			);
+		} else if self.assist.enabled {
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				panel((MARGIN, 158), text_box("ASSIST MODE".len(), TEXT_SCALE)),
+				COLORS.panel_label,
+				"ASSIST MODE",
+			);
+		}
 
-		let fps_str = format!("FPS: {fps:3}", fps = infos.fps);
-		let text_dims = text_box(fps_str.len(), TEXT_SCALE);
+		// Lives
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN, 190), text_box("LIVES".len(), TEXT_SCALE)),
+			COLORS.panel_label,
+			"LIVES",
+		);
+		// A heart per life left, then an empty one per life lost. Narrower when they don't fit
+		let slots = self.player.max_hp().max(self.player.hp).max(1);
+		let step = (280 / slots as i32).min(48);
+		let size = step * 5 / 6;
+		for i in 0..slots {
+			let tile = if i < self.player.hp { (0, 4) } else { (1, 4) };
+			draw_sprite(
+				frame_buffer,
+				&sheets.spritesheet,
+				SpriteCoords { sheet_pos: tile.into(), dims: (8, 8).into() },
+				panel((MARGIN + step * i as i32, 220), (size, size).into()),
+				None,
+			);
+		}
 
+		// Bomb stock, right of the lives label
 		draw_text(
 			frame_buffer,
 			&sheets.font,
-			Rect { top_left: (win_w - text_dims.w, 12).into(), dims: text_dims }
-				.to_interface(0, scale4),
-			[0xff, 0xff, 0xff, 0xb0],
-			&fps_str,
+			panel((MARGIN + 200, 158), text_box("BOMBS".len(), TEXT_SCALE)),
+			COLORS.panel_label,
+			"BOMBS",
+		);
+		let bombs_str = if self.modifiers.infinite_bombs {
+			"INF".to_string()
+		} else {
+			format!("{}", self.player.bombs)
+		};
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN + 200, 190), text_box(bombs_str.len(), TEXT_SCALE)),
+			COLORS.menu_text,
+			&bombs_str,
 		);
 
-		let score_str = format!("SCORE: {score:3}", score = self.score);
-		let score_dims = text_box(score_str.len(), TEXT_SCALE);
+		// Power
 		draw_text(
 			frame_buffer,
 			&sheets.font,
-			Rect { top_left: (win_w - score_dims.w, 60).into(), dims: score_dims }
-				.to_interface(0, scale4),
-			[0xff, 0xff, 0xff, 0xb0],
-			&score_str,
+			panel((MARGIN, 280), text_box("POWER".len(), TEXT_SCALE)),
+			COLORS.panel_label,
+			"POWER",
+		);
+		let power_str = format!("{} TIER {}", self.player.power, self.player.damage_tier());
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN, 310), text_box(power_str.len(), TEXT_SCALE)),
+			COLORS.menu_text,
+			&power_str,
+		);
+		// Missile stock, right of the power
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN + 200, 280), text_box("MISSILES".len(), TEXT_SCALE)),
+			COLORS.panel_label,
+			"MISSILES",
+		);
+		let missiles_str = format!("{}", self.player.missiles);
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel(
+				(MARGIN + 200, 310),
+				text_box(missiles_str.len(), TEXT_SCALE),
+			),
+			COLORS.menu_text,
+			&missiles_str,
+		);
+
+		// Chain and the time left to extend it
+		if self.chain.count > 0 {
+			let chain_str = format!("CHAIN X{}", self.chain.count);
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				panel((MARGIN, 350), text_box(chain_str.len(), TEXT_SCALE)),
+				COLORS.item_chain,
+				&chain_str,
+			);
+			let bar_w = (200. * self.chain.time_left(self.tick)).round() as i32;
+			draw_rect(
+				frame_buffer,
+				panel((MARGIN, 380), (bar_w, 8).into()),
+				COLORS.item_chain,
+			);
+		}
+
+		// Graze meter, or the time left of the hyper
+		let (label, fill, color) = if self.player.hyper > 0 {
+			(
+				"HYPER",
+				self.player.hyper as f32 / HYPER_TICKS as f32,
+				COLORS.hyper,
+			)
+		} else if self.player.graze >= GRAZE_METER_MAX {
+			("HYPER READY", 1., COLORS.hyper)
+		} else {
+			(
+				"GRAZE",
+				self.player.graze as f32 / GRAZE_METER_MAX as f32,
+				COLORS.menu_text,
+			)
+		};
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN, 410), text_box(label.len(), TEXT_SCALE)),
+			COLORS.panel_label,
+			label,
+		);
+		// Grazes of the whole level, right of the meter
+		let grazes_str = format!("{}", self.stats.grazes);
+		draw_text(
+			frame_buffer,
+			&sheets.font,
+			panel((MARGIN + 220, 432), text_box(grazes_str.len(), TEXT_SCALE)),
+			COLORS.panel_label,
+			&grazes_str,
+		);
+		draw_rect(
+			frame_buffer,
+			panel((MARGIN, 440), ((200. * fill).round() as i32, 8).into()),
+			color,
 		);
 
-		let level_name = "LEVEL 1";
+		// Kill multiplier and the time left before it goes down, right of the chain
+		if self.combo.multiplier > 1 {
+			let combo_str = format!("COMBO X{}", self.combo.multiplier);
+			draw_text(
+				frame_buffer,
+				&sheets.font,
+				panel((MARGIN + 220, 350), text_box(combo_str.len(), TEXT_SCALE)),
+				COLORS.menu_text,
+				&combo_str,
+			);
+			let bar_w = (120. * self.combo.time_left(self.tick)).round() as i32;
+			draw_rect(
+				frame_buffer,
+				panel((MARGIN + 220, 380), (bar_w, 8).into()),
+				COLORS.menu_text,
+			);
+		}
+
+		// FPS, at the bottom of the panel
+		let base_h = DRAW_CONSTANTS.sizes[0].h as i32;
+		let fps_str = format!("FPS: {fps:3}", fps = infos.fps);
+		let fps_dims = text_box(fps_str.len(), TEXT_SCALE);
 		draw_text(
 			frame_buffer,
 			&sheets.font,
-			Rect {
-				top_left: (20, 200).into(),
-				dims: text_box(level_name.len(), 2 * TEXT_SCALE),
-			}
-			.to_interface(interf_begin_x as i32, scale4),
-			[0xff, 0x00, 0x00, 0xff],
-			level_name,
+			panel((MARGIN, base_h - MARGIN - fps_dims.h), fps_dims),
+			COLORS.panel_label,
+			&fps_str,
 		);
 	}
 }
@@ -0,0 +1,223 @@
+use std::{rc::Rc, time::Duration};
+
+use winit::{event::ElementState, keyboard::Key};
+
+use crate::{
+	draw::DRAW_CONSTANTS,
+	game::{Difficulty, Game, MenuChoice, RunState},
+	gameplay::World,
+	progress::ScoreRecord,
+	replay::ReplayMode,
+	sound::SoundBase,
+};
+
+/// Lines of the credits, an empty one leaves a gap
+pub const CREDITS: &[&str] = &[
+	"Holy Bullet Hell",
+	"",
+	"Design and programming",
+	"The Holy Bullet Hell contributors",
+	"",
+	"Built with",
+	"winit, pixels, kira",
+	"cgmath, image, serde",
+	"",
+	"Thanks for playing!",
+];
+
+/// Speed of the credits, in base resolution pixels per second
+const CREDITS_SPEED: f32 = 60.;
+/// Space between two lines of the credits, in base resolution pixels
+pub const CREDITS_LINE_HEIGHT: f32 = 60.;
+
+/// Bonus for clearing a level without losing a life
+const NO_MISS_BONUS: u64 = 50_000;
+/// Per enemy bullet grazed during the level
+const GRAZE_BONUS: u64 = 20;
+/// Per second of game time under `TIME_PAR`
+const TIME_BONUS: u64 = 200;
+const TIME_PAR: Duration = Duration::from_secs(180);
+/// Per homing missile left
+const MISSILE_BONUS: u64 = 2_000;
+
+/// Score of a cleared level, with the bonuses added at the end
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tally {
+	/// Scored while playing
+	pub base: u64,
+	pub no_miss: u64,
+	pub graze: u64,
+	pub time: u64,
+	pub missiles: u64,
+}
+
+impl Tally {
+	fn new(world: &World) -> Tally {
+		let under_par = TIME_PAR.saturating_sub(world.clock.now()).as_secs();
+		Tally {
+			base: world.score,
+			no_miss: if world.stats.deaths == 0 {
+				NO_MISS_BONUS
+			} else {
+				0
+			},
+			graze: GRAZE_BONUS * world.stats.grazes as u64,
+			time: TIME_BONUS * under_par,
+			missiles: MISSILE_BONUS * world.player.missiles as u64,
+		}
+	}
+
+	pub fn total(&self) -> u64 {
+		self.base + self.no_miss + self.graze + self.time + self.missiles
+	}
+}
+
+/// Entries of the victory screen
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum VictoryChoice {
+	/// To the credits after the last level, back to the title screen otherwise
+	Continue,
+	/// Starts the same level over
+	Retry,
+}
+
+/// Run shown on the victory screen
+#[derive(Clone, Debug)]
+pub struct RunSummary {
+	pub level_name: Rc<String>,
+	pub tally: Tally,
+	/// Homing missiles left, paid by the tally
+	pub missiles: u32,
+	/// Runs with cheats aren't scored
+	pub scored: bool,
+	/// Played in assist mode, shown next to the score
+	pub assisted: bool,
+	/// Enemy bullets grazed during the level
+	pub grazes: u32,
+	/// Wall clock time since the level started, pauses included
+	pub rta: Duration,
+	/// Time the simulation went through
+	pub igt: Duration,
+	pub difficulty: Difficulty,
+	/// The whole game was cleared, the credits follow
+	pub last_level: bool,
+	/// Place of the total in the high scores of the level, none when it didn't make it or
+	/// wasn't recorded
+	pub high_score: Option<usize>,
+}
+
+impl Game {
+	/// Whether clearing the level being played finishes the game, it is the last stage of the base
+	/// game. Replays being watched don't have an ending
+	pub fn is_final_level(&self) -> bool {
+		!matches!(self.replay, ReplayMode::Playback(_))
+			&& self
+				.final_level
+				.is_some_and(|id| self.infos.level_id == Some(id))
+	}
+
+	/// Leaves the cleared level for the victory screen
+	pub fn start_victory(&mut self) {
+		let last_level = self.is_final_level();
+		let Some(world) = self.world.take() else {
+			return;
+		};
+		let level_id = self.infos.level_id.unwrap_or_default();
+		let level_name = self.levels[level_id as usize].name.clone();
+		let tally = Tally::new(&world);
+		let high_score = self.record_high_score(&world, &level_name, tally.total());
+		self.summary = Some(RunSummary {
+			level_name,
+			tally,
+			missiles: world.player.missiles,
+			scored: world.is_scoring(),
+			assisted: world.assist.enabled,
+			grazes: world.stats.grazes,
+			rta: self.infos.since_level_begin(),
+			igt: world.clock.now(),
+			difficulty: world.difficulty,
+			last_level,
+			high_score,
+		});
+		self.credits_scroll = 0.;
+		self.state = RunState::Victory(VictoryChoice::Continue);
+		self.update_presence();
+		if let Err(err) = self.finish_replay() {
+			self.fail(err);
+		}
+	}
+
+	/// Adds the total of the tally to the high scores of the level, unless the run used cheats or
+	/// assists or was a practice run. Returns its place when it made it
+	fn record_high_score(&mut self, world: &World, level_name: &str, total: u64) -> Option<usize> {
+		if self.config.practice || !world.is_scoring() || world.assist.enabled {
+			return None;
+		}
+		let record = ScoreRecord { score: total, difficulty: world.difficulty };
+		match self.progress.record_score(level_name, record) {
+			Ok(rank) => rank,
+			Err(err) => {
+				self.fail(err);
+				None
+			},
+		}
+	}
+
+	/// Scrolls the credits, going back to the title screen once they are gone
+	pub fn update_credits(&mut self, dt: Duration) {
+		self.credits_scroll += CREDITS_SPEED * dt.as_secs_f32();
+		// They start below the screen and end when the last line went past the top
+		let length = DRAW_CONSTANTS.sizes[0].h as f32 + CREDITS.len() as f32 * CREDITS_LINE_HEIGHT;
+		if self.credits_scroll > length {
+			self.leave_ending();
+		}
+	}
+
+	/// Continue goes from the victory screen to the credits, or to the title screen when levels
+	/// are left, Enter or Escape skips the credits
+	pub fn ending_key_handling(&mut self, key_state: &ElementState, key: &Key) {
+		use winit::keyboard::NamedKey::*;
+		if key_state == &ElementState::Released {
+			return;
+		}
+		match (self.state, key) {
+			(RunState::Victory(choice), Key::Named(ArrowDown) | Key::Named(ArrowUp)) => {
+				self.audio.play_sound(SoundBase::MenuMove);
+				self.state = RunState::Victory(match choice {
+					VictoryChoice::Continue => VictoryChoice::Retry,
+					VictoryChoice::Retry => VictoryChoice::Continue,
+				});
+			},
+			(RunState::Victory(VictoryChoice::Retry), Key::Named(Enter)) => {
+				self.audio.play_sound(SoundBase::MenuSelect);
+				self.summary = None;
+				self.start_level(self.infos.level_id.unwrap_or_default());
+				self.state = RunState::Playing;
+				self.update_presence();
+			},
+			(RunState::Victory(VictoryChoice::Continue), Key::Named(Enter)) => {
+				self.audio.play_sound(SoundBase::MenuSelect);
+				if self
+					.summary
+					.as_ref()
+					.is_some_and(|summary| summary.last_level)
+				{
+					self.state = RunState::Credits;
+				} else {
+					self.leave_ending();
+				}
+			},
+			(RunState::Credits, Key::Named(Enter) | Key::Named(Escape)) => {
+				self.audio.play_sound(SoundBase::MenuBack);
+				self.leave_ending();
+			},
+			_ => {},
+		}
+	}
+
+	fn leave_ending(&mut self) {
+		self.summary = None;
+		self.state = RunState::Menu(MenuChoice::Play);
+		self.update_presence();
+	}
+}
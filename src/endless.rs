@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	enemies::{self, EnemyType},
+	rng::Rng,
+};
+
+/// Ticks between the field being clear and the next wave
+pub const WAVE_PAUSE_TICKS: u64 = 90;
+/// Ticks after which the next wave comes even if the previous one is still there
+pub const WAVE_TIMEOUT_TICKS: u64 = 1200;
+/// Ticks between two enemies of a wave spawning
+pub const WAVE_SPAWN_DELAY: u64 = 12;
+const MAX_WAVE_SIZE: u32 = 14;
+
+/// Enemy types thrown in the waves, with the first wave they can be part of. Picked at random among
+/// the unlocked ones
+const ROSTER: [(&str, u32); 7] = [
+	("basic", 0),
+	("sniper", 2),
+	("kamikaze", 3),
+	("tank", 4),
+	("turret", 5),
+	("lancer", 6),
+	("carrier", 8),
+];
+
+/// State of an endless run, the level events are replaced by waves growing as they are cleared
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Endless {
+	/// Waves sent so far
+	pub wave: u32,
+	/// Tick at which the last wave was sent
+	last_wave: u64,
+	/// Tick from which the field was clear, none while enemies are left
+	clear_since: Option<u64>,
+}
+
+impl Endless {
+	/// Tells if the next wave is due, the field being clear or not at `tick`
+	pub fn wave_due(&mut self, tick: u64, field_clear: bool) -> bool {
+		if !field_clear {
+			self.clear_since = None;
+		} else if self.clear_since.is_none() {
+			self.clear_since = Some(tick);
+		}
+		let paused = self
+			.clear_since
+			.is_some_and(|since| tick >= since + WAVE_PAUSE_TICKS);
+		paused || tick >= self.last_wave + WAVE_TIMEOUT_TICKS
+	}
+
+	/// Rolls the enemies of the next wave, as the type and the spawn position in fractions of the
+	/// playfield width. Every wave is bigger and mixes more types than the previous one
+	pub fn next_wave(&mut self, tick: u64, rng: &mut Rng) -> Vec<(EnemyType, f32)> {
+		let roster: Vec<EnemyType> = ROSTER
+			.iter()
+			.filter(|(_, first_wave)| *first_wave <= self.wave)
+			.filter_map(|(name, _)| enemies::find(name))
+			.collect();
+		let size = (3 + self.wave / 2).min(MAX_WAVE_SIZE);
+		self.wave += 1;
+		self.last_wave = tick;
+		self.clear_since = None;
+		(0..size)
+			.map(|_| {
+				let variant = roster[(rng.next_f32() * roster.len() as f32) as usize];
+				// Away from the edges, where the enemies would leave right away
+				(variant, 0.1 + 0.8 * rng.next_f32())
+			})
+			.collect()
+	}
+}
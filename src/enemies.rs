@@ -0,0 +1,390 @@
+use cgmath::{InnerSpace, Point2, Vector2, Zero};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	collections::HashMap,
+	sync::{LazyLock, RwLock},
+};
+
+use crate::{
+	assets,
+	coords::{Dimensions, RectF},
+	error::GameError,
+	gameplay::{DamageType, Enemy, ItemKind, ProjType, Projectile, DT_60},
+};
+
+/// Base drop tables, by enemy name
+const DROPS_FILE: &str = "drops.toml";
+
+/// How an enemy type looks, moves and shoots
+#[derive(Clone, Debug)]
+pub struct EnemyDef {
+	/// Used in the level files and the world dumps
+	pub name: String,
+	pub max_hp: f32,
+	pub size: Dimensions<f32>,
+	/// Seconds between two shots
+	pub fire_cooldown: f32,
+	/// Position in the sprite sheet, in 8 pixels tiles
+	pub sprite: (u32, u32),
+	/// Moves the enemy once it is on screen by setting its velocity, the player being at the given
+	/// position
+	pub movement: fn(&mut Enemy, RectF, Point2<f32>),
+	/// Bullets fired from `pos` with the player at `target`, `speed` is the bullet speed
+	pub shot: fn(pos: Point2<f32>, target: Point2<f32>, speed: f32) -> Vec<Projectile>,
+	/// Turns its aim toward the player by at most this many radians per tick, `target` is then
+	/// where it aims. None aims at the player directly
+	pub turn_rate: Option<f32>,
+	/// Bullets fired from where it died, `speed` is the bullet speed
+	pub death_shot: fn(pos: Point2<f32>, speed: f32) -> Vec<Projectile>,
+	/// Blows up when its body reaches the player, hurting it like the others. It isn't a kill
+	pub explodes_on_contact: bool,
+	/// Enemy type let out every `fire_cooldown` instead of firing
+	pub releases: Option<EnemyType>,
+	/// Rolled one by one when the enemy is killed
+	pub drops: Vec<ItemDrop>,
+	/// Part of the enemy taking more damage
+	pub weak_point: Option<WeakPoint>,
+	/// Multiplies the damage of the shots hitting its front, the face turned down the stage. Sides
+	/// and rear take full damage
+	pub front_armor: Option<f32>,
+	pub resistances: Resistances,
+}
+
+/// Damage multipliers of an enemy by damage type, under 1 for armor and over 1 for a weakness
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Resistances {
+	pub normal: f32,
+	pub pierce: f32,
+	pub explosive: f32,
+	pub beam: f32,
+}
+
+impl Default for Resistances {
+	fn default() -> Resistances {
+		Resistances { normal: 1., pierce: 1., explosive: 1., beam: 1. }
+	}
+}
+
+impl Resistances {
+	pub fn factor(&self, damage_type: DamageType) -> f32 {
+		match damage_type {
+			DamageType::Normal => self.normal,
+			DamageType::Pierce => self.pierce,
+			DamageType::Explosive => self.explosive,
+			DamageType::Beam => self.beam,
+		}
+	}
+}
+
+/// Sub-rectangle of an enemy where shots deal critical hits
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct WeakPoint {
+	/// Center of the weak point, from the center of the enemy
+	pub offset: (f32, f32),
+	pub size: (f32, f32),
+	/// Multiplies the damage of the shots hitting it
+	pub multiplier: f32,
+}
+
+/// Items an enemy can drop when it is killed
+#[derive(Clone, Debug, Deserialize)]
+pub struct ItemDrop {
+	pub kind: ItemKind,
+	/// Probability of the drop, between 0 and 1
+	pub chance: f32,
+	/// Items dropped at once
+	#[serde(default = "one")]
+	pub count: u32,
+}
+
+fn one() -> u32 {
+	1
+}
+
+/// Registered enemy type, stored by name in the world dumps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnemyType(usize);
+
+impl EnemyType {
+	pub const BASIC: EnemyType = EnemyType(0);
+	pub const SNIPER: EnemyType = EnemyType(1);
+
+	pub fn def(self) -> &'static EnemyDef {
+		REGISTRY.read().unwrap()[self.0]
+	}
+}
+
+// Definitions live as long as the game, they are leaked to be shared without holding the lock
+static REGISTRY: LazyLock<RwLock<Vec<&'static EnemyDef>>> =
+	LazyLock::new(|| RwLock::new(base_defs().into_iter().map(leak).collect()));
+
+fn leak(def: EnemyDef) -> &'static EnemyDef {
+	Box::leak(Box::new(def))
+}
+
+/// Adds an enemy type usable in the level files, its name must not be taken
+pub fn register(def: EnemyDef) -> Result<EnemyType, String> {
+	if find(&def.name).is_some() {
+		return Err(format!("Enemy type '{}' already exists", def.name));
+	}
+	let mut registry = REGISTRY.write().unwrap();
+	registry.push(leak(def));
+	Ok(EnemyType(registry.len() - 1))
+}
+
+/// Replaces the drop table of an enemy type
+fn set_drops(variant: EnemyType, drops: Vec<ItemDrop>) {
+	let mut registry = REGISTRY.write().unwrap();
+	let def = EnemyDef { drops, ..registry[variant.0].clone() };
+	registry[variant.0] = leak(def);
+}
+
+/// Reads the drop tables of the base enemy types
+pub fn load_drops() -> Result<(), GameError> {
+	let file = assets::read(DROPS_FILE).ok_or(GameError::asset(DROPS_FILE, "missing file"))?;
+	let raw = std::str::from_utf8(&file).map_err(|err| GameError::asset(DROPS_FILE, err))?;
+	let tables: HashMap<String, Vec<ItemDrop>> =
+		toml::from_str(raw).map_err(|err| GameError::asset(DROPS_FILE, err))?;
+	for (name, drops) in tables {
+		let variant = find(&name).ok_or_else(|| {
+			GameError::asset(DROPS_FILE, format!("Enemy type '{name}' doesn't exist"))
+		})?;
+		set_drops(variant, drops);
+	}
+	Ok(())
+}
+
+pub fn find(name: &str) -> Option<EnemyType> {
+	REGISTRY
+		.read()
+		.unwrap()
+		.iter()
+		.position(|def| def.name == name)
+		.map(EnemyType)
+}
+
+impl Serialize for EnemyType {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.def().name.serialize(serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for EnemyType {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<EnemyType, D::Error> {
+		let name = String::deserialize(deserializer)?;
+		find(&name).ok_or_else(|| de::Error::custom(format!("Enemy type '{name}' doesn't exist")))
+	}
+}
+
+/// Enemy types of the base game, in the order of the `EnemyType` constants
+fn base_defs() -> Vec<EnemyDef> {
+	const SPEED: f32 = 0.5;
+	vec![
+		EnemyDef {
+			name: "basic".into(),
+			max_hp: 15.,
+			size: (48., 48.).into(),
+			fire_cooldown: 25. * DT_60,
+			sprite: (2, 0),
+			movement: |enemy, bounds, _| {
+				enemy.body.vel = Vector2::unit_y() * SPEED;
+				if enemy.body.pos.x <= bounds.dims.w / 2. {
+					enemy.body.vel -= Vector2::unit_x() * SPEED;
+				} else if enemy.body.pos.x > bounds.dims.w / 2. {
+					enemy.body.vel += Vector2::unit_x() * SPEED;
+				}
+			},
+			shot: |pos, _, speed| {
+				vec![Projectile::new(
+					pos,
+					Vector2::unit_y() * speed,
+					ProjType::Basic,
+				)]
+			},
+			turn_rate: None,
+			death_shot: |_, _| vec![],
+			explodes_on_contact: false,
+			releases: None,
+			drops: vec![],
+			// The core, at the bottom of the sprite
+			weak_point: Some(WeakPoint { offset: (0., 12.), size: (16., 16.), multiplier: 2. }),
+			front_armor: None,
+			// Plated, piercing shots go through the armor
+			resistances: Resistances { normal: 0.75, pierce: 1.25, ..Default::default() },
+		},
+		EnemyDef {
+			name: "sniper".into(),
+			max_hp: 8.,
+			size: (32., 48.).into(),
+			fire_cooldown: 40. * DT_60,
+			sprite: (3, 0),
+			movement: |enemy, bounds, _| {
+				let mid_up: Point2<f32> = (bounds.dims.w / 2., 0.).into();
+				let to_mid = (mid_up - enemy.body.pos).normalize();
+				// Orthogonal, needs better solution because only one direction works
+				enemy.body.vel = Vector2::new(to_mid.y, -to_mid.x) * SPEED * 5.;
+			},
+			shot: |pos, target, speed| {
+				let delta = target - pos;
+				let mut to_player = Vector2::zero();
+				if delta != Vector2::zero() {
+					to_player = delta.normalize();
+				}
+				vec![Projectile::new(pos, speed * to_player, ProjType::Aimed)]
+			},
+			turn_rate: None,
+			death_shot: |_, _| vec![],
+			explodes_on_contact: false,
+			releases: None,
+			drops: vec![],
+			weak_point: None,
+			front_armor: None,
+			// Light frame, blown apart by explosions
+			resistances: Resistances { explosive: 1.5, ..Default::default() },
+		},
+		EnemyDef {
+			name: "lancer".into(),
+			max_hp: 20.,
+			size: (48., 48.).into(),
+			// Long enough for each laser to be over before the next warning
+			fire_cooldown: 150. * DT_60,
+			sprite: (0, 5),
+			movement: |enemy, bounds, _| {
+				// Drifts slowly down and toward the closest side
+				let side = if enemy.body.pos.x < bounds.dims.w / 2. {
+					-1.
+				} else {
+					1.
+				};
+				enemy.body.vel = Vector2::new(side, 1.) * SPEED * 0.5;
+			},
+			shot: |pos, target, _| {
+				let delta = target - pos;
+				let mut to_player = Vector2::unit_y();
+				if delta != Vector2::zero() {
+					to_player = delta.normalize();
+				}
+				vec![Projectile::new(pos, to_player, ProjType::laser())]
+			},
+			turn_rate: None,
+			death_shot: |_, _| vec![],
+			explodes_on_contact: false,
+			releases: None,
+			drops: vec![],
+			weak_point: None,
+			front_armor: None,
+			resistances: Resistances::default(),
+		},
+		EnemyDef {
+			name: "turret".into(),
+			max_hp: 40.,
+			size: (48., 48.).into(),
+			fire_cooldown: 60. * DT_60,
+			sprite: (1, 5),
+			// Parks where it was placed, only despawn events and damage remove it
+			movement: |enemy, _, _| enemy.body.vel = Vector2::zero(),
+			shot: |pos, target, speed| {
+				let aim = (target - pos).normalize();
+				[-0.2, 0., 0.2]
+					.map(|angle: f32| {
+						let (sin, cos) = angle.sin_cos();
+						let vel = Vector2::new(aim.x * cos - aim.y * sin, aim.x * sin + aim.y * cos);
+						Projectile::new(pos, speed * vel, ProjType::Aimed)
+					})
+					.to_vec()
+			},
+			turn_rate: Some(0.03),
+			death_shot: |_, _| vec![],
+			explodes_on_contact: false,
+			releases: None,
+			drops: vec![],
+			weak_point: None,
+			front_armor: None,
+			// Armored all around
+			resistances: Resistances { normal: 0.75, pierce: 0.75, ..Default::default() },
+		},
+		EnemyDef {
+			name: "kamikaze".into(),
+			max_hp: 6.,
+			size: (32., 32.).into(),
+			// Its shot is empty, it only rams the player
+			fire_cooldown: 1.,
+			sprite: (2, 5),
+			movement: |enemy, _, target| {
+				const ACCELERATION: f32 = 0.15;
+				const MAX_SPEED: f32 = 6.;
+				let to_player = target - enemy.body.pos;
+				if to_player != Vector2::zero() {
+					enemy.body.vel += to_player.normalize() * ACCELERATION;
+				}
+				if enemy.body.vel.magnitude() > MAX_SPEED {
+					enemy.body.vel = enemy.body.vel.normalize() * MAX_SPEED;
+				}
+			},
+			shot: |_, _, _| vec![],
+			turn_rate: None,
+			// A small ring, slower than the other bullets
+			death_shot: |pos, speed| {
+				const RING_BULLETS: u32 = 8;
+				(0..RING_BULLETS)
+					.map(|i| {
+						let angle = i as f32 * std::f32::consts::TAU / RING_BULLETS as f32;
+						let vel = Vector2::new(angle.cos(), angle.sin()) * speed * 0.5;
+						Projectile::new(pos, vel, ProjType::Basic)
+					})
+					.collect()
+			},
+			explodes_on_contact: true,
+			releases: None,
+			drops: vec![],
+			weak_point: None,
+			front_armor: None,
+			resistances: Resistances::default(),
+		},
+		EnemyDef {
+			name: "carrier".into(),
+			max_hp: 80.,
+			size: (64., 64.).into(),
+			// Seconds between two basic enemies let out
+			fire_cooldown: 2.,
+			sprite: (3, 5),
+			movement: |enemy, _, _| enemy.body.vel = Vector2::unit_y() * SPEED * 0.4,
+			shot: |_, _, _| vec![],
+			turn_rate: None,
+			death_shot: |_, _| vec![],
+			explodes_on_contact: false,
+			releases: Some(EnemyType::BASIC),
+			drops: vec![],
+			weak_point: None,
+			front_armor: None,
+			resistances: Resistances::default(),
+		},
+		EnemyDef {
+			name: "tank".into(),
+			max_hp: 30.,
+			size: (48., 48.).into(),
+			fire_cooldown: 70. * DT_60,
+			sprite: (0, 6),
+			// Rolls down slowly, letting the player get around it
+			movement: |enemy, _, _| enemy.body.vel = Vector2::unit_y() * SPEED * 0.6,
+			shot: |pos, target, speed| {
+				let delta = target - pos;
+				let mut to_player = Vector2::unit_y();
+				if delta != Vector2::zero() {
+					to_player = delta.normalize();
+				}
+				vec![Projectile::new(pos, speed * to_player, ProjType::Aimed)]
+			},
+			turn_rate: None,
+			death_shot: |_, _| vec![],
+			explodes_on_contact: false,
+			releases: None,
+			drops: vec![],
+			weak_point: None,
+			// Plated front, shots from below barely scratch it
+			front_armor: Some(0.25),
+			resistances: Resistances::default(),
+		},
+	]
+}
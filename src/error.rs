@@ -0,0 +1,72 @@
+use std::{
+	fmt::{self, Display},
+	path::{Path, PathBuf},
+};
+
+/// Problems with the files the game reads and writes
+#[derive(Debug)]
+pub enum GameError {
+	/// A built-in asset or its replacement in the assets directory can't be used
+	Asset {
+		name: String,
+		reason: String,
+	},
+	Level {
+		name: String,
+		reason: String,
+	},
+	Config {
+		path: PathBuf,
+		reason: String,
+	},
+	/// A world dump, replay or save that can't be read or written
+	UserFile {
+		path: PathBuf,
+		reason: String,
+	},
+	/// Command line arguments that can't be understood
+	Args {
+		reason: String,
+	},
+}
+
+impl GameError {
+	pub fn asset(name: &str, reason: impl Display) -> GameError {
+		GameError::Asset { name: name.into(), reason: reason.to_string() }
+	}
+
+	pub fn level(name: &str, reason: impl Display) -> GameError {
+		GameError::Level { name: name.into(), reason: reason.to_string() }
+	}
+
+	pub fn config(path: &Path, reason: impl Display) -> GameError {
+		GameError::Config { path: path.into(), reason: reason.to_string() }
+	}
+
+	pub fn user_file(path: &Path, reason: impl Display) -> GameError {
+		GameError::UserFile { path: path.into(), reason: reason.to_string() }
+	}
+
+	pub fn args(reason: impl Display) -> GameError {
+		GameError::Args { reason: reason.to_string() }
+	}
+
+	/// The game can't run without what failed to load, it has to quit
+	pub fn is_fatal(&self) -> bool {
+		!matches!(self, GameError::UserFile { .. })
+	}
+}
+
+impl Display for GameError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			GameError::Asset { name, reason } => write!(f, "Asset '{name}': {reason}"),
+			GameError::Level { name, reason } => write!(f, "Level '{name}': {reason}"),
+			GameError::Config { path, reason } => write!(f, "Config '{}': {reason}", path.display()),
+			GameError::UserFile { path, reason } => write!(f, "File '{}': {reason}", path.display()),
+			GameError::Args { reason } => write!(f, "Arguments: {reason}"),
+		}
+	}
+}
+
+impl std::error::Error for GameError {}
@@ -0,0 +1,133 @@
+use cgmath::{InnerSpace, Point2, Vector2, Zero};
+use serde::{Deserialize, Serialize};
+
+/// Chords a curve is cut into to measure it
+const LENGTH_SAMPLES: u32 = 16;
+
+/// Part of a flight path, its points are offsets from where the enemy spawned
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Segment {
+	/// Straight to `to`, at `speed` pixels per 60 Hz tick
+	Line { speed: f32, to: Vector2<f32> },
+	/// Cubic Bezier curve from the end of the previous segment to `to`, bent toward the two
+	/// control points
+	Bezier {
+		speed: f32,
+		c1: Vector2<f32>,
+		c2: Vector2<f32>,
+		to: Vector2<f32>,
+	},
+}
+
+impl Segment {
+	fn speed(&self) -> f32 {
+		match *self {
+			Segment::Line { speed, .. } | Segment::Bezier { speed, .. } => speed,
+		}
+	}
+
+	fn end(&self) -> Vector2<f32> {
+		match *self {
+			Segment::Line { to, .. } | Segment::Bezier { to, .. } => to,
+		}
+	}
+
+	/// Point `t` of the way along the segment, between 0 and 1
+	fn point(&self, start: Vector2<f32>, t: f32) -> Vector2<f32> {
+		match *self {
+			Segment::Line { to, .. } => start + (to - start) * t,
+			Segment::Bezier { c1, c2, to, .. } => {
+				let u = 1. - t;
+				start * (u * u * u) + c1 * (3. * u * u * t) + c2 * (3. * u * t * t) + to * (t * t * t)
+			},
+		}
+	}
+
+	/// Length in pixels, measured along chords for the curves
+	fn length(&self, start: Vector2<f32>) -> f32 {
+		match *self {
+			Segment::Line { to, .. } => (to - start).magnitude(),
+			Segment::Bezier { .. } => (1..=LENGTH_SAMPLES)
+				.map(|i| {
+					let t0 = (i - 1) as f32 / LENGTH_SAMPLES as f32;
+					let t1 = i as f32 / LENGTH_SAMPLES as f32;
+					(self.point(start, t1) - self.point(start, t0)).magnitude()
+				})
+				.sum(),
+		}
+	}
+
+	/// Parses a segment, `line(<speed>,<x>,<y>)` or
+	/// `bezier(<speed>,<x1>,<y1>,<x2>,<y2>,<x>,<y>)` with offsets from the spawn position
+	pub fn parse(word: &str) -> Result<Segment, String> {
+		let (kind, args) = word
+			.strip_suffix(')')
+			.and_then(|w| w.split_once('('))
+			.ok_or(format!("Invalid path segment '{word}'"))?;
+		let args = args
+			.split(',')
+			.map(|arg| arg.parse::<f32>())
+			.collect::<Result<Vec<f32>, _>>()
+			.map_err(|_| format!("Invalid {kind} segment '{word}'"))?;
+		match (kind, args.as_slice()) {
+			("line", &[speed, x, y]) => Ok(Segment::Line { speed, to: (x, y).into() }),
+			("bezier", &[speed, x1, y1, x2, y2, x, y]) => Ok(Segment::Bezier {
+				speed,
+				c1: (x1, y1).into(),
+				c2: (x2, y2).into(),
+				to: (x, y).into(),
+			}),
+			("line" | "bezier", _) => Err(format!("Wrong number of values in '{word}'")),
+			_ => Err(format!("Unknown path segment '{kind}'")),
+		}
+	}
+}
+
+/// Path of an enemy spawned with one, replacing the movement of its type until it is over
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FlightPath {
+	/// Where the enemy spawned, the segments are offsets from it
+	origin: Point2<f32>,
+	segments: Vec<Segment>,
+	/// Segment being flown, and how far along it between 0 and 1
+	segment: usize,
+	progress: f32,
+}
+
+impl FlightPath {
+	pub fn new(origin: Point2<f32>, segments: Vec<Segment>) -> FlightPath {
+		FlightPath { origin, segments, segment: 0, progress: 0. }
+	}
+
+	/// Moves along the path by `ticks` 60 Hz ticks, `flip` turns the offsets to the stage as it
+	/// is shown. None once the last segment is over
+	pub fn advance(
+		&mut self,
+		ticks: f32,
+		flip: impl Fn(Vector2<f32>) -> Vector2<f32>,
+	) -> Option<Point2<f32>> {
+		let segment = self.segments.get(self.segment)?;
+		let start = self.start();
+		let length = segment.length(start);
+		if length > 0. {
+			self.progress += segment.speed() * ticks / length;
+		} else {
+			self.progress = 1.;
+		}
+		let offset = segment.point(start, self.progress.min(1.));
+		if self.progress >= 1. {
+			// The rest of the tick is lost, the next segment starts from its beginning
+			self.segment += 1;
+			self.progress = 0.;
+		}
+		Some(self.origin + flip(offset))
+	}
+
+	/// Offset the current segment starts from, the end of the previous one
+	fn start(&self) -> Vector2<f32> {
+		match self.segment {
+			0 => Vector2::zero(),
+			i => self.segments[i - 1].end(),
+		}
+	}
+}
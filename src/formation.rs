@@ -0,0 +1,68 @@
+use cgmath::Vector2;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use crate::{enemies::EnemyType, flight_path::Segment};
+
+/// How the members of a formation are laid out around its position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FormationShape {
+	/// Side by side, centered on the position
+	Line,
+	/// Leader at the tip, the others behind it on alternating sides
+	V,
+	/// Around the position, `spacing` being the radius
+	Circle,
+	/// One behind the other, the leader first
+	Column,
+}
+
+impl FormationShape {
+	pub fn parse(word: &str) -> Result<FormationShape, String> {
+		match word {
+			"line" => Ok(FormationShape::Line),
+			"v" => Ok(FormationShape::V),
+			"circle" => Ok(FormationShape::Circle),
+			"column" => Ok(FormationShape::Column),
+			_ => Err(format!("Unknown formation shape '{word}'")),
+		}
+	}
+}
+
+/// Group of enemies of one type spawned by a single level event
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Formation {
+	pub variant: EnemyType,
+	pub shape: FormationShape,
+	pub count: u32,
+	/// Pixels between two neighbours
+	pub spacing: f32,
+	/// Ticks between two members spawning, the leader comes first
+	pub delay: u64,
+	/// Flown by every member from its own spawn position, keeping the shape along it
+	pub path: Option<Vec<Segment>>,
+}
+
+impl Formation {
+	/// Offset of member `i` from the position of the formation, "behind" being up the stage
+	pub fn offset(&self, i: u32) -> Vector2<f32> {
+		let i = i as f32;
+		match self.shape {
+			FormationShape::Line => {
+				let center = (self.count as f32 - 1.) / 2.;
+				Vector2::new((i - center) * self.spacing, 0.)
+			},
+			FormationShape::V => {
+				// 1 and 2 are the first pair behind the leader, 3 and 4 the second one...
+				let rank = (i / 2.).ceil();
+				let side = if i as u32 % 2 == 1 { -1. } else { 1. };
+				Vector2::new(side * rank, -rank) * self.spacing
+			},
+			FormationShape::Circle => {
+				let angle = TAU * i / self.count as f32;
+				Vector2::new(angle.cos(), angle.sin()) * self.spacing
+			},
+			FormationShape::Column => Vector2::new(0., -i * self.spacing),
+		}
+	}
+}
@@ -0,0 +1,34 @@
+use std::{collections::VecDeque, time::Duration};
+
+/// Bars of the graph, one taken every refresh of the fps counter
+pub const FRAME_SAMPLES: usize = 60;
+
+/// How a frame was spent
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameTime {
+	/// From the start of the frame to the start of the next one, waiting for vsync included
+	pub total: Duration,
+	/// Ticks of the level
+	pub update: Duration,
+	/// Drawing and handing the frame to the GPU, with the rest of the work of the frame
+	pub render: Duration,
+}
+
+/// Recent frame times, the oldest at the front
+#[derive(Clone, Debug, Default)]
+pub struct FrameGraph {
+	samples: VecDeque<FrameTime>,
+}
+
+impl FrameGraph {
+	pub fn push(&mut self, sample: FrameTime) {
+		self.samples.push_back(sample);
+		if self.samples.len() > FRAME_SAMPLES {
+			self.samples.pop_front();
+		}
+	}
+
+	pub fn samples(&self) -> impl Iterator<Item = &FrameTime> {
+		self.samples.iter()
+	}
+}
@@ -1,20 +1,50 @@
-use smol_str::SmolStr;
+use serde::{Deserialize, Serialize};
 use std::{
+	collections::HashMap,
 	fs,
 	path::Path,
 	rc::Rc,
+	str::{FromStr, SplitWhitespace},
 	time::{Duration, Instant},
 };
 use winit::{event::ElementState, event_loop::ActiveEventLoop, keyboard::Key, window::Window};
 
 use crate::{
-	coords::Dimensions,
-	draw::{create_window, FrameBuffer, ResizableWindow, Sheets, DRAW_CONSTANTS},
-	gameplay::{Cooldown, EnemyType, Event, EventType, World},
+	assets,
+	background::Background,
+	coords::{Dimensions, Rect},
+	daily,
+	draw::{
+		create_window, FrameBuffer, RenderState, ResizableWindow, Sheets, DRAW_CONSTANTS, N_SIZES,
+		WINDOW_TITLE,
+	},
+	ending::{RunSummary, VictoryChoice},
+	endless::Endless,
+	enemies::{self, EnemyType},
+	error::GameError,
+	flight_path::Segment,
+	formation::{Formation, FormationShape},
+	frame_graph::{FrameGraph, FrameTime},
+	game_over::GameOverChoice,
+	gamepad::Gamepads,
+	gameplay::{Cooldown, Event, EventCondition, EventType, Repeat, SpawnPos, World, DT_60, TICK},
+	hazard::{GravityWell, Hazard, HazardKind},
+	input_display::InputHistory,
+	practice::{QuickSaves, RewindBuffer, Section},
+	presence::Presence,
+	progress::{EndlessRecord, Progress},
+	replay::{Replay, ReplayMode},
+	rng::Rng,
+	rules::{Assist, Modifiers, Rules},
+	session_log::SessionLog,
+	settings::{Action, Bindings, ControlPreset, VOLUME_STEP},
 	sound::{Audio, SoundBase},
+	stats::StatsExport,
+	title::TitleScene,
 };
 
-const WORLD_SIZE: Dimensions<f32> = Dimensions {
+/// Default playfield, levels can declare their own with `$playfield <w> <h>`
+pub const WORLD_SIZE: Dimensions<f32> = Dimensions {
 	w: DRAW_CONSTANTS.sizes[0].w as f32 * 0.75,
 	h: DRAW_CONSTANTS.sizes[0].h as f32,
 };
@@ -22,9 +52,16 @@ const WORLD_SIZE: Dimensions<f32> = Dimensions {
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum RunState {
 	Playing,
-	_Paused,
+	Paused(PauseChoice),
 	Menu(MenuChoice),
-	_GameOver,
+	/// The player ran out of lives, the level can be retried
+	GameOver(GameOverChoice),
+	/// Shows `Game::summary` after a level is cleared, the level can be retried
+	Victory(VictoryChoice),
+	/// Rolls after the victory screen, then goes back to the title screen
+	Credits,
+	/// Shows `Game::error`
+	Error,
 	Quitting,
 }
 
@@ -32,6 +69,11 @@ pub enum RunState {
 pub enum MenuChoice {
 	// Main menu
 	Play,
+	/// The level selection, the level is then played with the practice tools
+	Practice,
+	/// The endless level with the waves of the day, the same for everyone
+	Daily,
+	Modifiers,
 	Options,
 	Quit,
 	// Play menu
@@ -39,71 +81,692 @@ pub enum MenuChoice {
 	Level(u16),
 	// Options menu
 	Resolution,
+	DisplayMode,
+	Monitor,
+	Vsync,
+	Difficulty,
+	TitleStatus,
+	MasterVolume,
+	EffectsVolume,
+	BulletOutline,
+	ScreenShake,
+	InputDisplay,
+	DiscordPresence,
+	Assist,
+	StatsExport,
+	Controls,
+	// Assist menu
+	AssistEnabled,
+	AssistHp,
+	AssistImmunity,
+	AssistBulletSpeed,
+	// Controls menu
+	Preset,
+	Binding(Action),
+	Controller,
+	// Modifiers menu
+	BulletSpeed,
+	DoubleDamage,
+	InfiniteBombs,
+	Mirrored,
+	SecondLoop,
+}
+
+/// Entries of the pause menu, opened with Escape during a level
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PauseChoice {
+	Resume,
+	/// Starts the level over from its events, the run so far is dropped
+	Restart,
+	Options,
+	/// Back to the title screen
+	Quit,
 }
 
 #[derive(Clone, Debug)]
 pub struct Level {
 	pub id: u32,
 	pub name: Rc<String>,
-	event_list: Vec<Event>,
+	/// Size of the play area in base resolution pixels, independent of the window
+	pub playfield: Dimensions<f32>,
+	/// File in the audio directory looped while the level is played, none when silent
+	pub music: Option<String>,
+	/// Fixed seed of the random rolls, a new one every run when missing
+	pub seed: Option<u64>,
+	pub mode: LevelMode,
+	pub event_list: Vec<Event>,
+}
+
+/// How a level is played, set by `$mode` in its file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LevelMode {
+	/// Plays its events
+	#[default]
+	Stage,
+	/// Generated waves replace the events, until the player runs out of lives
+	Endless,
+	/// The bosses of the stages one after the other, the lives are given back between two
+	BossRush,
 }
 
 pub const LEVEL_REF: u32 = u32::MAX;
+/// Wait before the first boss of a boss rush, and before the next ones once the lives are back
+const BOSS_RUSH_FIRST_WAIT: Duration = Duration::from_secs(2);
+const BOSS_RUSH_WAIT: Duration = Duration::from_secs(1);
 impl Level {
-	fn level_parser(game: &mut Game, level_file: &str) {
-		let level_raw_data = fs::read_to_string(level_file).unwrap();
+	/// Reads a level file, the error tells what is wrong in it
+	pub fn parse(id: u32, level_raw_data: &str) -> Result<Level, String> {
 		let mut level = Level {
-			id: game.levels.len() as u32,
+			id,
 			event_list: vec![],
 			name: Rc::new(String::new()),
+			playfield: WORLD_SIZE,
+			music: None,
+			seed: None,
+			mode: LevelMode::Stage,
 		};
 
+		// Named flight paths, given to the enemies spawned with their name
+		let mut paths: HashMap<&str, Vec<Segment>> = HashMap::new();
 		let meta_data = level_raw_data
 			.split('\n')
 			.filter_map(|x| x.strip_prefix('$'));
 
 		for data in meta_data {
-			let data = data.split_once(char::is_whitespace).unwrap();
+			let data = data
+				.split_once(char::is_whitespace)
+				.ok_or(format!("Missing value after '${data}'"))?;
 			match data.0 {
 				"title" => {
 					level.name = Rc::new(data.1.into());
 				},
-				data => {
-					unimplemented!("'{data}' keyword doesn't exist")
+				"playfield" => {
+					let mut words = data.1.split_whitespace();
+					let w = parse_next(&mut words, "playfield width")?;
+					let h = parse_next(&mut words, "playfield height")?;
+					level.playfield = check_playfield((w, h).into())?;
+				},
+				"music" => {
+					let file_name = data.1.trim();
+					check_music(file_name)?;
+					level.music = Some(file_name.into());
+				},
+				"seed" => {
+					let mut words = data.1.split_whitespace();
+					level.seed = Some(parse_next(&mut words, "seed")?);
+				},
+				"mode" => level.mode = parse_mode(data.1.trim())?,
+				"path" => {
+					let mut words = data.1.split_whitespace();
+					let name = words.next().ok_or("Missing path name")?;
+					paths.insert(name, parse_segments(name, words)?);
 				},
+				data => return Err(format!("'{data}' keyword doesn't exist")),
 			}
 		}
 
 		let events = level_raw_data
 			.split('\n')
 			.filter_map(|x| x.strip_prefix('@'));
-		let id: u32 = 0;
-		for event in events {
+		// Events are numbered from 0 in the order of the file, for the references
+		for (id, event) in events.enumerate() {
+			let id = id as u32;
+			let (event, repeat) = parse_repeat(event)?;
 			let mut event = event.split_whitespace();
-			match event.next().unwrap() {
+			match event.next().ok_or("Empty event")? {
 				"spawn-enemy" => {
-					let variant = match event.next().unwrap() {
-						"basic" => EnemyType::Basic,
-						"sniper" => EnemyType::Sniper,
-						other => unimplemented!("Enemy type '{other}' doesn't exist"),
-					};
-					let t: f32 = event.next().unwrap().parse().unwrap();
+					let name = event.next().ok_or("Missing enemy type")?;
+					let variant = find_enemy(name)?;
+					let t: f32 = parse_next(&mut event, "time")?;
 					let t = Duration::from_secs_f32(t);
-					let x: f32 = event.next().unwrap().parse().unwrap();
-					let y: f32 = event.next().unwrap().parse().unwrap();
-					let ref_evt = event.next().unwrap().parse::<u32>().ok().map(|x| (x, t));
-					let variant = EventType::_SpawnEnemy((x, y).into(), variant);
+					let pos = parse_spawn_pos(&mut event)?;
+					let (ref_evt, condition) = parse_ref(&mut event)?;
+					let path = parse_path(&mut event, &paths)?;
+					let variant = EventType::_SpawnEnemy(pos, variant, path);
 					// Events are all relative, the "absolute" events will be relative to the beginning of the level
-					let evt = match ref_evt {
-						Some(_) => Event { id, time: None, variant, ref_evt },
-						None => Event { id, time: None, variant, ref_evt: Some((LEVEL_REF, t)) },
+					level.event_list.push(Event {
+						id,
+						time: None,
+						variant,
+						ref_evt: Some((ref_evt, t)),
+						repeat,
+						condition,
+					});
+				},
+				"spawn-boss" => {
+					let name = event.next().ok_or("Missing enemy type")?;
+					let variant = find_enemy(name)?;
+					let phases = check_phases(parse_next(&mut event, "phase count")?)?;
+					let t: f32 = parse_next(&mut event, "time")?;
+					let t = Duration::from_secs_f32(t);
+					let pos = parse_spawn_pos(&mut event)?;
+					let (ref_evt, condition) = parse_ref(&mut event)?;
+					level.event_list.push(Event {
+						id,
+						time: None,
+						variant: EventType::SpawnBoss(pos, variant, phases),
+						ref_evt: Some((ref_evt, t)),
+						repeat,
+						condition,
+					});
+				},
+				"checkpoint" => {
+					let t: f32 = parse_next(&mut event, "time")?;
+					let t = Duration::from_secs_f32(t);
+					let (ref_evt, condition) = parse_ref(&mut event)?;
+					let variant = EventType::Checkpoint;
+					level.event_list.push(Event {
+						id,
+						time: None,
+						variant,
+						ref_evt: Some((ref_evt, t)),
+						repeat,
+						condition,
+					});
+				},
+				"hazard" => {
+					let kind = parse_hazard_kind(&mut event)?;
+					let t: f32 = parse_next(&mut event, "time")?;
+					let t = Duration::from_secs_f32(t);
+					let duration: f32 = parse_next(&mut event, "duration")?;
+					let x = parse_next(&mut event, "x position")?;
+					let y = parse_next(&mut event, "y position")?;
+					let w = parse_next(&mut event, "width")?;
+					let h = parse_next(&mut event, "height")?;
+					let (ref_evt, condition) = parse_ref(&mut event)?;
+					let hazard = Hazard {
+						kind,
+						zone: Rect { top_left: (x, y).into(), dims: (w, h).into() },
+						duration: (duration / DT_60).round() as u64,
+					};
+					level.event_list.push(Event {
+						id,
+						time: None,
+						variant: EventType::Hazard(hazard),
+						ref_evt: Some((ref_evt, t)),
+						repeat,
+						condition,
+					});
+				},
+				"gravity-well" => {
+					let t: f32 = parse_next(&mut event, "time")?;
+					let t = Duration::from_secs_f32(t);
+					let duration: f32 = parse_next(&mut event, "duration")?;
+					let x = parse_next(&mut event, "x position")?;
+					let y = parse_next(&mut event, "y position")?;
+					let radius = parse_next(&mut event, "radius")?;
+					let strength = parse_next(&mut event, "strength")?;
+					let (ref_evt, condition) = parse_ref(&mut event)?;
+					let well = GravityWell {
+						center: (x, y).into(),
+						radius,
+						strength,
+						duration: (duration / DT_60).round() as u64,
 					};
-					level.event_list.push(evt);
+					level.event_list.push(Event {
+						id,
+						time: None,
+						variant: EventType::GravityWell(well),
+						ref_evt: Some((ref_evt, t)),
+						repeat,
+						condition,
+					});
+				},
+				"spawn-formation" => {
+					let name = event.next().ok_or("Missing enemy type")?;
+					let variant = find_enemy(name)?;
+					let shape = FormationShape::parse(event.next().ok_or("Missing formation shape")?)?;
+					let count = check_members(parse_next(&mut event, "member count")?)?;
+					let spacing = parse_next(&mut event, "spacing")?;
+					let delay: f32 = parse_next(&mut event, "spawn delay")?;
+					let t: f32 = parse_next(&mut event, "time")?;
+					let t = Duration::from_secs_f32(t);
+					let pos = parse_spawn_pos(&mut event)?;
+					let (ref_evt, condition) = parse_ref(&mut event)?;
+					let path = parse_path(&mut event, &paths)?;
+					let formation = Formation {
+						variant,
+						shape,
+						count,
+						spacing,
+						delay: (delay / DT_60).round() as u64,
+						path,
+					};
+					level.event_list.push(Event {
+						id,
+						time: None,
+						variant: EventType::SpawnFormation(pos, formation),
+						ref_evt: Some((ref_evt, t)),
+						repeat,
+						condition,
+					});
+				},
+				"despawn" => {
+					let name = event.next().ok_or("Missing enemy type")?;
+					let variant = find_enemy(name)?;
+					let t: f32 = parse_next(&mut event, "time")?;
+					let t = Duration::from_secs_f32(t);
+					let (ref_evt, condition) = parse_ref(&mut event)?;
+					level.event_list.push(Event {
+						id,
+						time: None,
+						variant: EventType::Despawn(variant),
+						ref_evt: Some((ref_evt, t)),
+						repeat,
+						condition,
+					});
 				},
-				evt => unimplemented!("Unknown event '{evt}'"),
+				evt => return Err(format!("Unknown event '{evt}'")),
 			}
 		}
-		game.levels.push(level);
+		Ok(level)
+	}
+
+	/// Reads a level file in the format of its extension, `.toml` or `.hbh`
+	pub fn parse_file(id: u32, file_name: &str, raw: &str) -> Result<Level, String> {
+		if file_name.ends_with(".toml") {
+			Level::parse_toml(id, raw)
+		} else {
+			Level::parse(id, raw)
+		}
+	}
+
+	/// Reads a level written in TOML, see `LevelFile`
+	pub fn parse_toml(id: u32, raw: &str) -> Result<Level, String> {
+		let file: LevelFile = toml::from_str(raw).map_err(|err| err.to_string())?;
+		let mut level = Level {
+			id,
+			event_list: vec![],
+			name: Rc::new(file.title),
+			playfield: WORLD_SIZE,
+			music: None,
+			seed: file.seed,
+			mode: LevelMode::Stage,
+		};
+		if let Some(playfield) = file.playfield {
+			level.playfield = check_playfield(playfield.into())?;
+		}
+		if let Some(music) = file.music {
+			check_music(&music)?;
+			level.music = Some(music);
+		}
+		if let Some(mode) = file.mode {
+			level.mode = parse_mode(&mode)?;
+		}
+		let mut paths = HashMap::new();
+		for (name, segments) in file.path.iter() {
+			let segments = parse_segments(name, segments.iter().map(String::as_str))?;
+			paths.insert(name.as_str(), segments);
+		}
+		let path = |name: Option<String>| -> Result<Option<Vec<Segment>>, String> {
+			name
+				.map(|name| {
+					paths
+						.get(name.as_str())
+						.cloned()
+						.ok_or(format!("Path '{name}' doesn't exist"))
+				})
+				.transpose()
+		};
+
+		// Events are numbered from 0 in the order of the file, for the references
+		for (id, entry) in file.event.into_iter().enumerate() {
+			let id = id as u32;
+			let event_error = |err: String| format!("Event {id}: {err}");
+			let (ref_evt, condition) = match entry.after {
+				None => (LEVEL_REF, EventCondition::Triggered),
+				Some(EventRef::Event(ref_evt)) => (ref_evt, EventCondition::Triggered),
+				Some(EventRef::Word(word)) => {
+					parse_ref(&mut word.split_whitespace()).map_err(event_error)?
+				},
+			};
+			let repeat = entry
+				.repeat
+				.map(|repeat| check_repeat(repeat.count, repeat.interval))
+				.transpose()
+				.map_err(event_error)?;
+			let variant = match entry.kind {
+				EventEntryKind::SpawnEnemy { enemy, pos, path: name } => EventType::_SpawnEnemy(
+					pos.resolve().map_err(event_error)?,
+					find_enemy(&enemy).map_err(event_error)?,
+					path(name).map_err(event_error)?,
+				),
+				EventEntryKind::SpawnBoss { enemy, phases, pos } => EventType::SpawnBoss(
+					pos.resolve().map_err(event_error)?,
+					find_enemy(&enemy).map_err(event_error)?,
+					check_phases(phases).map_err(event_error)?,
+				),
+				EventEntryKind::SpawnFormation {
+					enemy,
+					shape,
+					count,
+					spacing,
+					delay,
+					pos,
+					path: name,
+				} => {
+					let formation = Formation {
+						variant: find_enemy(&enemy).map_err(event_error)?,
+						shape: FormationShape::parse(&shape).map_err(event_error)?,
+						count: check_members(count).map_err(event_error)?,
+						spacing,
+						delay: (delay / DT_60).round() as u64,
+						path: path(name).map_err(event_error)?,
+					};
+					EventType::SpawnFormation(pos.resolve().map_err(event_error)?, formation)
+				},
+				EventEntryKind::Checkpoint => EventType::Checkpoint,
+				EventEntryKind::Hazard { kind, duration, zone: (x, y, w, h) } => {
+					EventType::Hazard(Hazard {
+						kind: parse_hazard_kind(&mut kind.split_whitespace()).map_err(event_error)?,
+						zone: Rect { top_left: (x, y).into(), dims: (w, h).into() },
+						duration: (duration / DT_60).round() as u64,
+					})
+				},
+				EventEntryKind::GravityWell { duration, center, radius, strength } => {
+					EventType::GravityWell(GravityWell {
+						center: center.into(),
+						radius,
+						strength,
+						duration: (duration / DT_60).round() as u64,
+					})
+				},
+				EventEntryKind::Despawn { enemy } => {
+					EventType::Despawn(find_enemy(&enemy).map_err(event_error)?)
+				},
+			};
+			level.event_list.push(Event {
+				id,
+				time: None,
+				variant,
+				ref_evt: Some((ref_evt, Duration::from_secs_f32(entry.time))),
+				repeat,
+				condition,
+			});
+		}
+		Ok(level)
+	}
+}
+
+/// Level file in TOML, read into the same level as a `.hbh` file. The values with a syntax of
+/// their own in `.hbh` keep it: path segments, spawn positions like `top(0.2)`, formation shapes,
+/// hazard kinds and the `clear` and `cleared(<id>)` references
+#[derive(Deserialize)]
+struct LevelFile {
+	title: String,
+	playfield: Option<(f32, f32)>,
+	/// File in the audio directory
+	music: Option<String>,
+	seed: Option<u64>,
+	/// `stage`, `endless` or `boss-rush`
+	mode: Option<String>,
+	/// Named flight paths, as lists of segments
+	#[serde(default)]
+	path: HashMap<String, Vec<String>>,
+	#[serde(default)]
+	event: Vec<EventEntry>,
+}
+
+/// Event of a TOML level, its `type` giving the other fields
+#[derive(Deserialize)]
+struct EventEntry {
+	/// Seconds after the reference
+	time: f32,
+	/// Reference event, the start of the level when missing
+	after: Option<EventRef>,
+	repeat: Option<RepeatEntry>,
+	#[serde(flatten)]
+	kind: EventEntryKind,
+}
+
+/// Id of an event, or `clear` or `cleared(<id>)`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EventRef {
+	Event(u32),
+	Word(String),
+}
+
+#[derive(Deserialize)]
+struct RepeatEntry {
+	count: u32,
+	/// Seconds between two triggers
+	interval: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum EventEntryKind {
+	SpawnEnemy {
+		enemy: String,
+		pos: PosEntry,
+		path: Option<String>,
+	},
+	SpawnBoss {
+		enemy: String,
+		phases: u32,
+		pos: PosEntry,
+	},
+	SpawnFormation {
+		enemy: String,
+		shape: String,
+		count: u32,
+		spacing: f32,
+		/// Seconds between two members spawning
+		delay: f32,
+		pos: PosEntry,
+		path: Option<String>,
+	},
+	Checkpoint,
+	Hazard {
+		/// `wind(<push per tick>)` or `slow(<speed factor>)`
+		kind: String,
+		/// Seconds it stays active
+		duration: f32,
+		/// x, y, width and height
+		zone: (f32, f32, f32, f32),
+	},
+	GravityWell {
+		/// Seconds it stays
+		duration: f32,
+		center: (f32, f32),
+		radius: f32,
+		strength: f32,
+	},
+	Despawn {
+		enemy: String,
+	},
+}
+
+/// Spawn position, `[<x>, <y>]` in pixels or relative to the playfield like in `.hbh`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PosEntry {
+	Absolute(f32, f32),
+	Relative(String),
+}
+
+impl PosEntry {
+	fn resolve(self) -> Result<SpawnPos, String> {
+		match self {
+			PosEntry::Absolute(x, y) => Ok(SpawnPos::Absolute((x, y).into())),
+			PosEntry::Relative(word) => parse_spawn_pos(&mut word.split_whitespace()),
+		}
+	}
+}
+
+/// Seed taken from a name, the same every time (FNV-1a)
+fn name_seed(name: &str) -> u64 {
+	name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+		(hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+	})
+}
+
+/// Parses the next word of a level line, `what` names it in the error
+fn parse_next<T: FromStr>(words: &mut SplitWhitespace, what: &str) -> Result<T, String> {
+	let word = words.next().ok_or(format!("Missing {what}"))?;
+	word.parse().map_err(|_| format!("Invalid {what} '{word}'"))
+}
+
+/// Parses a spawn position, `<x> <y>` in pixels or relative to the playfield with `top(<x fraction>)`,
+/// `left(<y>)`, `right(<y>)` or `above-player(<offset>)`
+fn parse_spawn_pos(words: &mut SplitWhitespace) -> Result<SpawnPos, String> {
+	let word = words.clone().next().ok_or("Missing spawn position")?;
+	let Some((helper, arg)) = word.strip_suffix(')').and_then(|w| w.split_once('(')) else {
+		let x = parse_next(words, "x position")?;
+		let y = parse_next(words, "y position")?;
+		return Ok(SpawnPos::Absolute((x, y).into()));
+	};
+	words.next();
+	let arg: f32 = arg
+		.parse()
+		.map_err(|_| format!("Invalid {helper} position '{arg}'"))?;
+	match helper {
+		"top" => Ok(SpawnPos::TopEdge(arg)),
+		"left" => Ok(SpawnPos::LeftEdge(arg)),
+		"right" => Ok(SpawnPos::RightEdge(arg)),
+		"above-player" => Ok(SpawnPos::AbovePlayer(arg)),
+		_ => Err(format!("Unknown spawn position '{helper}'")),
+	}
+}
+
+/// Splits the optional `repeat(<count>,<interval>)` ending an event line from the rest of it, the
+/// event is then triggered `count` more times, `interval` seconds apart
+fn parse_repeat(line: &str) -> Result<(&str, Option<Repeat>), String> {
+	let line = line.trim_end();
+	let Some((rest, word)) = line.rsplit_once(char::is_whitespace) else {
+		return Ok((line, None));
+	};
+	let Some(args) = word
+		.strip_prefix("repeat(")
+		.and_then(|w| w.strip_suffix(')'))
+	else {
+		return Ok((line, None));
+	};
+	let (count, interval) = args
+		.split_once(',')
+		.ok_or(format!("Invalid repeat '{word}'"))?;
+	let count: u32 = count
+		.parse()
+		.map_err(|_| format!("Invalid repeat count '{count}'"))?;
+	let interval: f32 = interval
+		.parse()
+		.map_err(|_| format!("Invalid repeat interval '{interval}'"))?;
+	Ok((rest, Some(check_repeat(count, interval)?)))
+}
+
+/// The playfield is drawn in the base resolution, it has to fit in it
+fn check_playfield(playfield: Dimensions<f32>) -> Result<Dimensions<f32>, String> {
+	let base_size = DRAW_CONSTANTS.sizes[0].into_dim::<f32>();
+	if playfield.w > base_size.w || playfield.h > base_size.h {
+		return Err("Playfield is bigger than the base resolution".into());
+	}
+	Ok(playfield)
+}
+
+fn check_music(file_name: &str) -> Result<(), String> {
+	if assets::read(&format!("audio/{file_name}")).is_none() {
+		return Err(format!("Music file 'audio/{file_name}' doesn't exist"));
+	}
+	Ok(())
+}
+
+fn parse_mode(mode: &str) -> Result<LevelMode, String> {
+	match mode {
+		"stage" => Ok(LevelMode::Stage),
+		"endless" => Ok(LevelMode::Endless),
+		"boss-rush" => Ok(LevelMode::BossRush),
+		mode => Err(format!("Unknown mode '{mode}'")),
+	}
+}
+
+/// Segments of the path `name`, it needs at least one
+fn parse_segments<'a>(
+	name: &str,
+	words: impl Iterator<Item = &'a str>,
+) -> Result<Vec<Segment>, String> {
+	let segments = words.map(Segment::parse).collect::<Result<Vec<_>, _>>()?;
+	if segments.is_empty() {
+		return Err(format!("Path '{name}' has no segment"));
+	}
+	Ok(segments)
+}
+
+fn find_enemy(name: &str) -> Result<EnemyType, String> {
+	enemies::find(name).ok_or(format!("Enemy type '{name}' doesn't exist"))
+}
+
+fn check_phases(phases: u32) -> Result<u32, String> {
+	if phases == 0 {
+		return Err("A boss needs at least one phase".into());
+	}
+	Ok(phases)
+}
+
+fn check_members(count: u32) -> Result<u32, String> {
+	if count == 0 {
+		return Err("A formation needs at least one member".into());
+	}
+	Ok(count)
+}
+
+fn check_repeat(count: u32, interval: f32) -> Result<Repeat, String> {
+	if interval <= 0. {
+		return Err("A repeat interval has to be positive".into());
+	}
+	Ok((count, Duration::from_secs_f32(interval)))
+}
+
+/// Parses the reference of an event: `<id>` waits on the trigger of that event, `cleared(<id>)` on
+/// every enemy it spawned being gone, killed or out of the playfield, and `clear` on the earlier
+/// events being all triggered with no enemy left. Anything else is the start of the level
+fn parse_ref(words: &mut SplitWhitespace) -> Result<(u32, EventCondition), String> {
+	let word = words.next().ok_or("Missing reference event")?;
+	if word == "clear" {
+		return Ok((LEVEL_REF, EventCondition::FieldClear));
+	}
+	if let Some(arg) = word
+		.strip_prefix("cleared(")
+		.and_then(|w| w.strip_suffix(')'))
+	{
+		let id = arg
+			.parse()
+			.map_err(|_| format!("Invalid cleared event '{arg}'"))?;
+		return Ok((id, EventCondition::WaveCleared));
+	}
+	Ok((word.parse().unwrap_or(LEVEL_REF), EventCondition::Triggered))
+}
+
+/// Parses the optional path name ending a spawn, declared beforehand with `$path`
+fn parse_path(
+	words: &mut SplitWhitespace,
+	paths: &HashMap<&str, Vec<Segment>>,
+) -> Result<Option<Vec<Segment>>, String> {
+	words
+		.next()
+		.map(|name| {
+			paths
+				.get(name)
+				.cloned()
+				.ok_or(format!("Path '{name}' doesn't exist"))
+		})
+		.transpose()
+}
+
+/// Parses a hazard kind, `wind(<push per tick>)` or `slow(<speed factor>)`
+fn parse_hazard_kind(words: &mut SplitWhitespace) -> Result<HazardKind, String> {
+	let word = words.next().ok_or("Missing hazard kind")?;
+	let (kind, arg) = word
+		.strip_suffix(')')
+		.and_then(|w| w.split_once('('))
+		.ok_or(format!("Invalid hazard kind '{word}'"))?;
+	let arg: f32 = arg
+		.parse()
+		.map_err(|_| format!("Invalid {kind} strength '{arg}'"))?;
+	match kind {
+		"wind" => Ok(HazardKind::Wind(arg)),
+		"slow" => Ok(HazardKind::Slow(arg)),
+		_ => Err(format!("Unknown hazard kind '{kind}'")),
 	}
 }
 
@@ -113,13 +776,18 @@ impl PartialEq for Level {
 	}
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Inputs {
 	pub left: bool,
 	pub right: bool,
 	pub up: bool,
 	pub down: bool,
 	pub shoot: bool,
+	pub dash: bool,
+	pub hyper: bool,
+	pub focus: bool,
+	pub missile: bool,
+	pub bomb: bool,
 	pub _pause: bool,
 }
 
@@ -129,58 +797,282 @@ impl Inputs {
 	}
 }
 
-#[derive(Clone, Debug)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum FullscreenMode {
+	/// Borderless window covering the whole monitor, fast to switch in and out
+	Borderless,
+	/// Takes over the monitor with a matching video mode
+	Exclusive,
+}
+
+impl FullscreenMode {
+	pub fn name(&self) -> &'static str {
+		match self {
+			FullscreenMode::Borderless => "Borderless",
+			FullscreenMode::Exclusive => "Exclusive",
+		}
+	}
+
+	fn toggle(self) -> FullscreenMode {
+		match self {
+			FullscreenMode::Borderless => FullscreenMode::Exclusive,
+			FullscreenMode::Exclusive => FullscreenMode::Borderless,
+		}
+	}
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Difficulty {
+	/// Dying sends the player back to the last checkpoint. Named casual in older settings
+	#[serde(alias = "Casual")]
+	Easy,
+	#[default]
+	Normal,
+	Hard,
+	Lunatic,
+}
+
+/// Scaling of the enemies in a difficulty
+#[derive(Debug)]
+pub struct DifficultyDef {
+	pub name: &'static str,
+	/// Multiplies the max HP of every enemy
+	pub enemy_hp: f32,
+	/// Multiplies the speed of the enemy bullets
+	pub bullet_speed: f32,
+	/// Multiplies the time between two shots of every enemy
+	pub fire_cooldown: f32,
+}
+
+/// Indexed by `Difficulty`, the whole balance of the difficulties
+const DIFFICULTIES: [DifficultyDef; 4] = [
+	DifficultyDef {
+		name: "Easy",
+		enemy_hp: 0.75,
+		bullet_speed: 0.8,
+		fire_cooldown: 1.3,
+	},
+	DifficultyDef { name: "Normal", enemy_hp: 1., bullet_speed: 1., fire_cooldown: 1. },
+	DifficultyDef {
+		name: "Hard",
+		enemy_hp: 1.25,
+		bullet_speed: 1.15,
+		fire_cooldown: 0.8,
+	},
+	DifficultyDef {
+		name: "Lunatic",
+		enemy_hp: 1.5,
+		bullet_speed: 1.3,
+		fire_cooldown: 0.6,
+	},
+];
+
+impl Difficulty {
+	const ALL: [Difficulty; 4] = [
+		Difficulty::Easy,
+		Difficulty::Normal,
+		Difficulty::Hard,
+		Difficulty::Lunatic,
+	];
+
+	pub fn def(self) -> &'static DifficultyDef {
+		&DIFFICULTIES[self as usize]
+	}
+
+	pub fn name(&self) -> &'static str {
+		self.def().name
+	}
+
+	/// From the command line argument
+	pub fn parse(arg: &str) -> Option<Difficulty> {
+		Difficulty::ALL
+			.into_iter()
+			.find(|difficulty| difficulty.name().eq_ignore_ascii_case(arg))
+	}
+
+	fn cycle(self, step_right: bool) -> Difficulty {
+		let n = Difficulty::ALL.len();
+		let index = self as usize;
+		if step_right {
+			Difficulty::ALL[(index + 1) % n]
+		} else {
+			Difficulty::ALL[(index + n - 1) % n]
+		}
+	}
+}
+
+/// Picked in the options menu and saved to `settings.toml` in the config directory as soon as
+/// they change
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
 	pub resolution_choice: u8,
-	pub _fullscreen: bool,
+	/// Resolution to go back to when F11 or Alt+Enter leaves fullscreen
+	pub windowed_choice: u8,
+	/// Kind of fullscreen used when the last resolution is selected
+	pub fullscreen_mode: FullscreenMode,
+	/// Index of the monitor in the list of available monitors
+	pub monitor: usize,
+	/// Waits for the screen refresh before showing a frame, avoids tearing
+	pub vsync: bool,
 	/// Four times the scaling factor to avoid floating point operations
+	#[serde(skip)]
 	pub scale4: u32,
+	/// Practice mode, allows rewinding and quick saves. Picked on the main menu, not saved
+	#[serde(skip)]
+	pub practice: bool,
+	/// Given on the command line, every level is played in practice mode. Not saved
+	#[serde(skip)]
+	pub practice_arg: bool,
+	/// Difficulty the levels start in
+	pub difficulty: Difficulty,
+	/// Shows the score, fps and entity count in the window title
+	pub title_status: bool,
+	/// In percent, applies to every sound
+	pub master_volume: u32,
+	/// In percent of the master volume
+	pub effects_volume: u32,
+	/// Draws a light outline behind the enemy bullets, to tell them apart from the background
+	pub bullet_outline: bool,
+	/// Shakes the playfield when the player is hit, a hyper starts or a boss dies
+	pub screen_shake: bool,
+	/// Shows the inputs and their recent history in the panel, for streams and replays
+	pub input_display: bool,
+	/// Shows the stage, score and mode on the player's Discord profile
+	pub discord_presence: bool,
+	/// Id of the application registered on the Discord developer portal, nothing is sent without
+	/// one. Only set in the file
+	pub discord_client_id: String,
+	/// Used by the next levels started, not the one being played
+	pub assist: Assist,
+	/// Format of the statistics written when a level ends
+	pub stats_export: StatsExport,
+	/// Given on the command line, used instead of `stats_export` and not saved
+	#[serde(skip)]
+	pub stats_export_arg: Option<StatsExport>,
+	/// Given on the command line, the levels roll the same values every time. Not saved
+	#[serde(skip)]
+	pub seed_arg: Option<u64>,
+	/// Given on the command line, used instead of `difficulty` until it is changed in a menu.
+	/// Not saved
+	#[serde(skip)]
+	pub difficulty_arg: Option<Difficulty>,
+	pub bindings: Bindings,
 }
 
 impl Config {
-	fn new() -> Config {
-		Config { resolution_choice: 1, _fullscreen: false, scale4: 4 }
+	pub fn new() -> Config {
+		Config {
+			resolution_choice: 1,
+			windowed_choice: 1,
+			fullscreen_mode: FullscreenMode::Borderless,
+			monitor: 0,
+			vsync: true,
+			scale4: 4,
+			practice: false,
+			practice_arg: false,
+			difficulty: Difficulty::Normal,
+			title_status: false,
+			master_volume: 100,
+			effects_volume: 100,
+			bullet_outline: false,
+			screen_shake: true,
+			input_display: false,
+			discord_presence: false,
+			discord_client_id: String::new(),
+			assist: Assist::default(),
+			stats_export: StatsExport::Off,
+			stats_export_arg: None,
+			seed_arg: None,
+			difficulty_arg: None,
+			bindings: Bindings::default(),
+		}
+	}
+
+	pub fn stats_export(&self) -> StatsExport {
+		self.stats_export_arg.unwrap_or(self.stats_export)
+	}
+
+	pub fn difficulty(&self) -> Difficulty {
+		self.difficulty_arg.unwrap_or(self.difficulty)
+	}
+
+	/// Picks the difficulty after the current one, it replaces the one of the command line
+	pub fn cycle_difficulty(&mut self, step_right: bool) {
+		self.difficulty = self.difficulty().cycle(step_right);
+		self.difficulty_arg = None;
+	}
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config::new()
 	}
 }
 
 #[derive(Clone, Debug)]
 pub struct GameInfo {
-	_game_begin: Instant,
+	game_begin: Instant,
 	level_begin: Option<Instant>,
+	/// Id of the level being played
+	pub level_id: Option<u32>,
 	frame_count: u64,
 	pub fps: u32,
 	fps_cooldown: Cooldown,
 	pub dt: Duration,
 	pub t: Instant,
+	/// Time not simulated yet, less than a tick once the ticks of the frame ran
+	pub accumulator: Duration,
+	/// Spent in the ticks of the level during the last frame
+	pub update_time: Duration,
+	/// Spent in the rest of the last frame, up to the frame handed to the GPU
+	pub render_time: Duration,
+	/// Sampled with the fps, shown by the frame time overlay
+	pub frame_graph: FrameGraph,
 }
 
 impl GameInfo {
-	fn new() -> GameInfo {
+	pub fn new() -> GameInfo {
 		GameInfo {
-			_game_begin: Instant::now(),
+			game_begin: Instant::now(),
 			level_begin: None,
+			level_id: None,
 			frame_count: 0,
 			fps: 0,
 			fps_cooldown: Cooldown::with_secs(0.1),
 			dt: Duration::from_secs(1),
 			t: Instant::now(),
+			accumulator: Duration::ZERO,
+			update_time: Duration::ZERO,
+			render_time: Duration::ZERO,
+			frame_graph: FrameGraph::default(),
 		}
 	}
 
-	fn start_level(&mut self) {
+	fn start_level(&mut self, id: u32) {
 		self.level_begin = Some(Instant::now());
+		self.level_id = Some(id);
+		self.accumulator = Duration::ZERO;
+	}
+
+	/// How far the display is from the last tick to the next one, from 0 to 1
+	pub fn interpolation(&self) -> f32 {
+		(self.accumulator.as_secs_f32() / TICK.as_secs_f32()).min(1.)
 	}
 
 	pub fn update(&mut self) {
 		self.frame_count += 1;
 	}
 
-	pub fn _since_game_begin(&self) -> Duration {
-		Instant::elapsed(&self._game_begin)
+	pub fn sincegame_begin(&self) -> Duration {
+		Instant::elapsed(&self.game_begin)
 	}
 
-	pub fn _since_level_begin(&self) -> Duration {
-		Instant::elapsed(&self.level_begin.unwrap())
+	/// Zero when the world was loaded from a dump instead of a level start
+	pub fn since_level_begin(&self) -> Duration {
+		self
+			.level_begin
+			.map_or(Duration::ZERO, |begin| Instant::elapsed(&begin))
 	}
 }
 
@@ -190,45 +1082,177 @@ pub struct Game {
 	pub inputs: Inputs,
 	pub window: Window,
 	pub frame_buffer: FrameBuffer,
+	pub render_state: RenderState,
 	pub sheets: Sheets,
 	pub audio: Audio,
 	pub levels: Vec<Level>,
+	/// Last stage of the base game, clearing it finishes the game. Mods and the other modes
+	/// don't have an ending
+	pub final_level: Option<u32>,
+	/// Problems found while loading the mods, shown on the main menu
+	pub mod_errors: Vec<String>,
 	pub config: Config,
+	pub rules: Rules,
 	pub infos: GameInfo,
+	pub session_log: SessionLog,
+	pub replay: ReplayMode,
+	pub rewind: RewindBuffer,
+	pub quick_saves: QuickSaves,
+	pub section: Section,
+	/// Day of the daily challenge being played, none for the other runs
+	pub daily: Option<u64>,
+	/// Shown on the error screen
+	pub error: Option<GameError>,
+	pub progress: Progress,
+	/// Run shown on the victory screen
+	pub summary: Option<RunSummary>,
+	/// How far the credits went up, in base resolution pixels
+	pub credits_scroll: f32,
+	/// Animated background of the menus
+	pub title_scene: TitleScene,
+	/// Action waiting for its new key in the controls menu
+	pub rebinding: Option<Action>,
+	/// The options menu was opened from the pause menu and goes back to it
+	pub options_from_pause: bool,
+	/// Shown by the input display
+	pub input_history: InputHistory,
+	pub presence: Presence,
+	pub gamepads: Gamepads,
 }
 
 impl Game {
 	pub fn launch(event_loop: &ActiveEventLoop) -> Game {
 		env_logger::init();
-		let window = create_window(event_loop);
-		Game {
+		// Shown once the game exists, the window is created with the defaults meanwhile
+		let (config, settings_error) = match Config::load() {
+			Ok(config) => (config, None),
+			Err(err) => (Config::new(), Some(err)),
+		};
+		let window = create_window(event_loop, &config);
+		let mut game = Game {
 			state: RunState::Menu(MenuChoice::Play),
 			world: None,
 			inputs: Inputs::new(),
-			frame_buffer: FrameBuffer::new(&window),
+			frame_buffer: FrameBuffer::new(&window, config.vsync),
+			render_state: RenderState::new(),
 			window,
-			sheets: Sheets::load(),
+			// Replaced by the ones of the assets directory in `load`
+			sheets: Sheets::embedded(),
 			audio: Audio::new(),
 			levels: vec![],
-			config: Config::new(),
+			final_level: None,
+			mod_errors: vec![],
+			config,
+			rules: Rules::default(),
 			infos: GameInfo::new(),
+			session_log: SessionLog::default(),
+			replay: ReplayMode::Off,
+			rewind: RewindBuffer::default(),
+			quick_saves: QuickSaves::new(),
+			section: Section::default(),
+			daily: None,
+			error: None,
+			progress: Progress::default(),
+			summary: None,
+			credits_scroll: 0.,
+			title_scene: TitleScene::new(),
+			rebinding: None,
+			options_from_pause: false,
+			input_history: InputHistory::default(),
+			presence: Presence::default(),
+			gamepads: Gamepads::new(),
+		};
+		game.apply_volume();
+		// The window may not be the size asked for, fullscreen takes the size of the monitor
+		let size = game.window.inner_size();
+		game.resize(&size);
+		if let Some(err) = settings_error {
+			game.fail(err);
 		}
+		game
 	}
 
-	pub fn load_levels(&mut self) {
-		let level_dir: &Path = Path::new("./levels");
-		if !level_dir.exists() {
-			panic!("Levels directory doesn't exist");
-		}
-		for level in fs::read_dir(level_dir).unwrap() {
-			let path = level.unwrap().path();
-			if path.is_file() && path.extension().is_some_and(|ext| ext == "hbh") {
-				Level::level_parser(self, path.to_str().unwrap());
-			}
+	/// Reads the rules and the assets, the game can't be played if this fails
+	pub fn load(&mut self) -> Result<(), GameError> {
+		self.rules = Rules::load()?;
+		self.sheets = Sheets::load()?;
+		self.audio.load_sounds()?;
+		enemies::load_drops()?;
+		self.load_levels()
+	}
+
+	fn load_levels(&mut self) -> Result<(), GameError> {
+		let mut names = assets::list("levels", "hbh");
+		names.extend(assets::list("levels", "toml"));
+		for name in names {
+			let file = assets::read(&name).ok_or(GameError::level(&name, "missing file"))?;
+			let raw = String::from_utf8(file.into_owned())
+				.map_err(|_| GameError::level(&name, "not valid UTF-8"))?;
+			let level = Level::parse_file(self.levels.len() as u32, &name, &raw)
+				.map_err(|err| GameError::level(&name, err))?;
+			self.levels.push(level);
 		}
 		// Sort inversely by id
 		// TODO: Have better sorting function?
 		self.levels.sort_by_key(|x| u32::MAX - x.id);
+		// The stages come first in the inverse order, mods are added after them
+		self.final_level = self
+			.levels
+			.iter()
+			.position(|level| level.mode == LevelMode::Stage)
+			.map(|i| i as u32);
+		Ok(())
+	}
+
+	/// Starts a level file given on the command line, added after the other levels. Lets a level
+	/// be tried while it is written, without putting it in the assets
+	pub fn play_level_file(&mut self, path: &Path) -> Result<(), GameError> {
+		let raw = fs::read_to_string(path).map_err(|err| GameError::user_file(path, err))?;
+		let name = path.display().to_string();
+		let level = Level::parse_file(self.levels.len() as u32, &name, &raw)
+			.map_err(|err| GameError::level(&name, err))?;
+		let id = level.id;
+		self.levels.push(level);
+		self.start_level(id);
+		self.state = RunState::Playing;
+		Ok(())
+	}
+
+	/// Writes what is still in memory to disk before the game closes, it can be called more than
+	/// once
+	pub fn shutdown(&mut self) {
+		// The settings, high scores and progress are saved as they change. A run still in
+		// progress isn't exported to the stats, like a run left from the pause menu
+		// Too late for the error screen
+		if let Err(err) = self.session_log.flush() {
+			println!("Error: {err}");
+		}
+		if let Err(err) = self.finish_replay() {
+			println!("Error: {err}");
+		}
+		self.audio.stop_all();
+	}
+
+	/// Shows the error screen, the error is also printed to keep a trace of it
+	pub fn fail(&mut self, error: GameError) {
+		println!("Error: {error}");
+		self.error = Some(error);
+		self.state = RunState::Error;
+	}
+
+	/// Leaves the error screen, quitting if the game can't go on
+	fn error_key_handling(&mut self, key_state: &ElementState, key: &Key) {
+		use winit::keyboard::NamedKey::*;
+		if key_state == &ElementState::Released || key != &Key::Named(Escape) {
+			return;
+		}
+		let fatal = self.error.take().is_none_or(|error| error.is_fatal());
+		self.state = if fatal {
+			RunState::Quitting
+		} else {
+			self.world = None;
+			RunState::Menu(MenuChoice::Play)
+		};
 	}
 
 	fn menu_key_handling(&mut self, key_state: &ElementState, key: &Key) {
@@ -240,12 +1264,51 @@ impl Game {
 			RunState::Menu(choice) => choice,
 			_ => unreachable!("Not in menu state"),
 		};
+		if let Some(action) = self.rebinding {
+			self.rebind_key_handling(action, key_state, key);
+			return;
+		}
 		match key {
 			Key::Named(Escape) => {
 				self.audio.play_sound(SoundBase::MenuBack);
 				self.state = RunState::Menu(match menu_choice {
-					MenuChoice::Play | MenuChoice::Options | MenuChoice::Quit => MenuChoice::Quit,
-					MenuChoice::Resolution => MenuChoice::Options,
+					MenuChoice::Play
+					| MenuChoice::Practice
+					| MenuChoice::Daily
+					| MenuChoice::Modifiers
+					| MenuChoice::Options
+					| MenuChoice::Quit => MenuChoice::Quit,
+					MenuChoice::Resolution
+					| MenuChoice::DisplayMode
+					| MenuChoice::Monitor
+					| MenuChoice::Vsync
+					| MenuChoice::Difficulty
+					| MenuChoice::TitleStatus
+					| MenuChoice::MasterVolume
+					| MenuChoice::EffectsVolume
+					| MenuChoice::BulletOutline
+					| MenuChoice::ScreenShake
+					| MenuChoice::InputDisplay
+					| MenuChoice::DiscordPresence
+					| MenuChoice::Assist
+					| MenuChoice::StatsExport
+					| MenuChoice::Controls => MenuChoice::Options,
+					MenuChoice::AssistEnabled
+					| MenuChoice::AssistHp
+					| MenuChoice::AssistImmunity
+					| MenuChoice::AssistBulletSpeed => MenuChoice::Assist,
+					MenuChoice::Preset | MenuChoice::Binding(_) | MenuChoice::Controller => {
+						MenuChoice::Controls
+					},
+					MenuChoice::BulletSpeed
+					| MenuChoice::DoubleDamage
+					| MenuChoice::InfiniteBombs
+					| MenuChoice::Mirrored
+					| MenuChoice::SecondLoop => MenuChoice::Modifiers,
+					// Back to the entry the level selection was opened from
+					MenuChoice::Level(_) if self.config.practice && !self.config.practice_arg => {
+						MenuChoice::Practice
+					},
 					MenuChoice::Level(_) => MenuChoice::Play,
 					// Allow for future proofing
 					#[allow(unreachable_patterns)]
@@ -255,24 +1318,85 @@ impl Game {
 			Key::Named(ArrowDown) => {
 				self.audio.play_sound(SoundBase::MenuMove);
 				self.state = match menu_choice {
-					MenuChoice::Play | MenuChoice::Options | MenuChoice::Quit => {
-						RunState::Menu(match menu_choice {
-							MenuChoice::Play => MenuChoice::Options,
-							MenuChoice::Options => MenuChoice::Quit,
-							MenuChoice::Quit => MenuChoice::Play,
-							_ => panic!("Invalid main menu choice"),
-						})
-					},
+					MenuChoice::Play
+					| MenuChoice::Practice
+					| MenuChoice::Daily
+					| MenuChoice::Modifiers
+					| MenuChoice::Options
+					| MenuChoice::Quit => RunState::Menu(match menu_choice {
+						MenuChoice::Play => MenuChoice::Practice,
+						MenuChoice::Practice => MenuChoice::Daily,
+						MenuChoice::Daily => MenuChoice::Modifiers,
+						MenuChoice::Modifiers => MenuChoice::Options,
+						MenuChoice::Options => MenuChoice::Quit,
+						MenuChoice::Quit => MenuChoice::Play,
+						_ => panic!("Invalid main menu choice"),
+					}),
 					MenuChoice::Level(id) => {
 						let new_id = (id + 1) % self.levels.len() as u16;
 						RunState::Menu(MenuChoice::Level(new_id))
 					},
-					MenuChoice::Resolution => {
-						let res_choice = &mut self.config.resolution_choice;
-						*res_choice = (*res_choice + 1) % DRAW_CONSTANTS.sizes.len() as u8;
-						self.window.request_window_resize(*res_choice);
-						self.state
-					},
+					MenuChoice::Resolution
+					| MenuChoice::DisplayMode
+					| MenuChoice::Monitor
+					| MenuChoice::Vsync
+					| MenuChoice::Difficulty
+					| MenuChoice::TitleStatus
+					| MenuChoice::MasterVolume
+					| MenuChoice::EffectsVolume
+					| MenuChoice::BulletOutline
+					| MenuChoice::ScreenShake
+					| MenuChoice::InputDisplay
+					| MenuChoice::DiscordPresence
+					| MenuChoice::Assist
+					| MenuChoice::StatsExport
+					| MenuChoice::Controls => RunState::Menu(match menu_choice {
+						MenuChoice::Resolution => MenuChoice::DisplayMode,
+						MenuChoice::DisplayMode => MenuChoice::Monitor,
+						MenuChoice::Monitor => MenuChoice::Vsync,
+						MenuChoice::Vsync => MenuChoice::Difficulty,
+						MenuChoice::Difficulty => MenuChoice::TitleStatus,
+						MenuChoice::TitleStatus => MenuChoice::MasterVolume,
+						MenuChoice::MasterVolume => MenuChoice::EffectsVolume,
+						MenuChoice::EffectsVolume => MenuChoice::BulletOutline,
+						MenuChoice::BulletOutline => MenuChoice::ScreenShake,
+						MenuChoice::ScreenShake => MenuChoice::InputDisplay,
+						MenuChoice::InputDisplay => MenuChoice::DiscordPresence,
+						MenuChoice::DiscordPresence => MenuChoice::Assist,
+						MenuChoice::Assist => MenuChoice::StatsExport,
+						MenuChoice::StatsExport => MenuChoice::Controls,
+						MenuChoice::Controls => MenuChoice::Resolution,
+						_ => panic!("Invalid options menu choice"),
+					}),
+					MenuChoice::AssistEnabled
+					| MenuChoice::AssistHp
+					| MenuChoice::AssistImmunity
+					| MenuChoice::AssistBulletSpeed => RunState::Menu(match menu_choice {
+						MenuChoice::AssistEnabled => MenuChoice::AssistHp,
+						MenuChoice::AssistHp => MenuChoice::AssistImmunity,
+						MenuChoice::AssistImmunity => MenuChoice::AssistBulletSpeed,
+						MenuChoice::AssistBulletSpeed => MenuChoice::AssistEnabled,
+						_ => panic!("Invalid assist menu choice"),
+					}),
+					// The preset comes before the actions and the controller after them
+					MenuChoice::Preset => RunState::Menu(MenuChoice::Binding(Action::Up)),
+					MenuChoice::Binding(action) => RunState::Menu(match action.next() {
+						Some(next) => MenuChoice::Binding(next),
+						None => MenuChoice::Controller,
+					}),
+					MenuChoice::Controller => RunState::Menu(MenuChoice::Preset),
+					MenuChoice::BulletSpeed
+					| MenuChoice::DoubleDamage
+					| MenuChoice::InfiniteBombs
+					| MenuChoice::Mirrored
+					| MenuChoice::SecondLoop => RunState::Menu(match menu_choice {
+						MenuChoice::BulletSpeed => MenuChoice::DoubleDamage,
+						MenuChoice::DoubleDamage => MenuChoice::InfiniteBombs,
+						MenuChoice::InfiniteBombs => MenuChoice::Mirrored,
+						MenuChoice::Mirrored => MenuChoice::SecondLoop,
+						MenuChoice::SecondLoop => MenuChoice::BulletSpeed,
+						_ => panic!("Invalid modifiers menu choice"),
+					}),
 					// Allow for future proofing
 					#[allow(unreachable_patterns)]
 					_ => unimplemented!("Menu State '{:?}' not implemented for ↓", menu_choice),
@@ -281,40 +1405,360 @@ impl Game {
 			Key::Named(ArrowUp) => {
 				self.audio.play_sound(SoundBase::MenuMove);
 				self.state = match menu_choice {
-					MenuChoice::Play | MenuChoice::Options | MenuChoice::Quit => {
-						RunState::Menu(match menu_choice {
-							MenuChoice::Play => MenuChoice::Quit,
-							MenuChoice::Options => MenuChoice::Play,
-							MenuChoice::Quit => MenuChoice::Options,
-							_ => panic!("Invalid main menu choice"),
-						})
-					},
+					MenuChoice::Play
+					| MenuChoice::Practice
+					| MenuChoice::Daily
+					| MenuChoice::Modifiers
+					| MenuChoice::Options
+					| MenuChoice::Quit => RunState::Menu(match menu_choice {
+						MenuChoice::Play => MenuChoice::Quit,
+						MenuChoice::Practice => MenuChoice::Play,
+						MenuChoice::Daily => MenuChoice::Practice,
+						MenuChoice::Modifiers => MenuChoice::Daily,
+						MenuChoice::Options => MenuChoice::Modifiers,
+						MenuChoice::Quit => MenuChoice::Options,
+						_ => panic!("Invalid main menu choice"),
+					}),
 					MenuChoice::Level(id) => {
 						let new_id = (id - 1) % self.levels.len() as u16;
 						RunState::Menu(MenuChoice::Level(new_id))
 					},
-					MenuChoice::Resolution => {
-						let res_choice = &mut self.config.resolution_choice;
-						*res_choice = (*res_choice - 1) % DRAW_CONSTANTS.sizes.len() as u8;
-						self.window.request_window_resize(*res_choice);
-						self.state
+					MenuChoice::Resolution
+					| MenuChoice::DisplayMode
+					| MenuChoice::Monitor
+					| MenuChoice::Vsync
+					| MenuChoice::Difficulty
+					| MenuChoice::TitleStatus
+					| MenuChoice::MasterVolume
+					| MenuChoice::EffectsVolume
+					| MenuChoice::BulletOutline
+					| MenuChoice::ScreenShake
+					| MenuChoice::InputDisplay
+					| MenuChoice::DiscordPresence
+					| MenuChoice::Assist
+					| MenuChoice::StatsExport
+					| MenuChoice::Controls => RunState::Menu(match menu_choice {
+						MenuChoice::Resolution => MenuChoice::Controls,
+						MenuChoice::DisplayMode => MenuChoice::Resolution,
+						MenuChoice::Monitor => MenuChoice::DisplayMode,
+						MenuChoice::Vsync => MenuChoice::Monitor,
+						MenuChoice::Difficulty => MenuChoice::Vsync,
+						MenuChoice::TitleStatus => MenuChoice::Difficulty,
+						MenuChoice::MasterVolume => MenuChoice::TitleStatus,
+						MenuChoice::EffectsVolume => MenuChoice::MasterVolume,
+						MenuChoice::BulletOutline => MenuChoice::EffectsVolume,
+						MenuChoice::ScreenShake => MenuChoice::BulletOutline,
+						MenuChoice::InputDisplay => MenuChoice::ScreenShake,
+						MenuChoice::DiscordPresence => MenuChoice::InputDisplay,
+						MenuChoice::Assist => MenuChoice::DiscordPresence,
+						MenuChoice::StatsExport => MenuChoice::Assist,
+						MenuChoice::Controls => MenuChoice::StatsExport,
+						_ => panic!("Invalid options menu choice"),
+					}),
+					MenuChoice::AssistEnabled
+					| MenuChoice::AssistHp
+					| MenuChoice::AssistImmunity
+					| MenuChoice::AssistBulletSpeed => RunState::Menu(match menu_choice {
+						MenuChoice::AssistEnabled => MenuChoice::AssistBulletSpeed,
+						MenuChoice::AssistHp => MenuChoice::AssistEnabled,
+						MenuChoice::AssistImmunity => MenuChoice::AssistHp,
+						MenuChoice::AssistBulletSpeed => MenuChoice::AssistImmunity,
+						_ => panic!("Invalid assist menu choice"),
+					}),
+					MenuChoice::Preset => RunState::Menu(MenuChoice::Controller),
+					MenuChoice::Binding(action) => RunState::Menu(match action.prev() {
+						Some(prev) => MenuChoice::Binding(prev),
+						None => MenuChoice::Preset,
+					}),
+					MenuChoice::Controller => {
+						RunState::Menu(MenuChoice::Binding(Action::ALL[Action::ALL.len() - 1]))
 					},
+					MenuChoice::BulletSpeed
+					| MenuChoice::DoubleDamage
+					| MenuChoice::InfiniteBombs
+					| MenuChoice::Mirrored
+					| MenuChoice::SecondLoop => RunState::Menu(match menu_choice {
+						MenuChoice::BulletSpeed => MenuChoice::SecondLoop,
+						MenuChoice::DoubleDamage => MenuChoice::BulletSpeed,
+						MenuChoice::InfiniteBombs => MenuChoice::DoubleDamage,
+						MenuChoice::Mirrored => MenuChoice::InfiniteBombs,
+						MenuChoice::SecondLoop => MenuChoice::Mirrored,
+						_ => panic!("Invalid modifiers menu choice"),
+					}),
 					// Allow for future proofing
 					#[allow(unreachable_patterns)]
 					_ => unimplemented!("Menu State '{:?}' not implemented for ↑", menu_choice),
 				};
 			},
+			Key::Named(ArrowLeft) | Key::Named(ArrowRight) => {
+				// Only the options, modifiers and character have values to change
+				let step_right = key == &Key::Named(ArrowRight);
+				let window_changed = match menu_choice {
+					MenuChoice::Resolution => {
+						let res_choice = &mut self.config.resolution_choice;
+						*res_choice = if step_right {
+							(*res_choice + 1) % N_SIZES
+						} else {
+							(*res_choice + N_SIZES - 1) % N_SIZES
+						};
+						true
+					},
+					MenuChoice::DisplayMode => {
+						self.config.fullscreen_mode = self.config.fullscreen_mode.toggle();
+						true
+					},
+					MenuChoice::Monitor => {
+						let n_monitors = self.window.available_monitors().count().max(1);
+						let monitor = &mut self.config.monitor;
+						*monitor = if step_right {
+							(*monitor + 1) % n_monitors
+						} else {
+							(*monitor + n_monitors - 1) % n_monitors
+						};
+						true
+					},
+					MenuChoice::Vsync => {
+						self.config.vsync = !self.config.vsync;
+						self.frame_buffer.set_vsync(&self.window, self.config.vsync);
+						self.resize(&self.window.inner_size());
+						false
+					},
+					MenuChoice::Difficulty => {
+						self.config.cycle_difficulty(step_right);
+						false
+					},
+					MenuChoice::TitleStatus => {
+						self.config.title_status = !self.config.title_status;
+						if !self.config.title_status {
+							self.window.set_title(WINDOW_TITLE);
+						}
+						false
+					},
+					MenuChoice::MasterVolume | MenuChoice::EffectsVolume => {
+						let volume = if menu_choice == MenuChoice::MasterVolume {
+							&mut self.config.master_volume
+						} else {
+							&mut self.config.effects_volume
+						};
+						*volume = if step_right {
+							(*volume + VOLUME_STEP).min(100)
+						} else {
+							volume.saturating_sub(VOLUME_STEP)
+						};
+						self.apply_volume();
+						false
+					},
+					MenuChoice::BulletOutline => {
+						self.config.bullet_outline = !self.config.bullet_outline;
+						false
+					},
+					MenuChoice::ScreenShake => {
+						self.config.screen_shake = !self.config.screen_shake;
+						false
+					},
+					MenuChoice::InputDisplay => {
+						self.config.input_display = !self.config.input_display;
+						false
+					},
+					MenuChoice::DiscordPresence => {
+						self.config.discord_presence = !self.config.discord_presence;
+						if self.config.discord_presence {
+							self.update_presence();
+						} else {
+							self.clear_presence();
+						}
+						false
+					},
+					MenuChoice::BulletSpeed => {
+						let (min, max) = Modifiers::BULLET_SPEED_RANGE;
+						let speed = &mut self.rules.modifiers.bullet_speed;
+						*speed = if step_right {
+							(*speed + Modifiers::BULLET_SPEED_STEP).min(max)
+						} else {
+							(*speed - Modifiers::BULLET_SPEED_STEP).max(min)
+						};
+						false
+					},
+					MenuChoice::DoubleDamage => {
+						let modifiers = &mut self.rules.modifiers;
+						modifiers.double_damage = !modifiers.double_damage;
+						false
+					},
+					MenuChoice::InfiniteBombs => {
+						let modifiers = &mut self.rules.modifiers;
+						modifiers.infinite_bombs = !modifiers.infinite_bombs;
+						false
+					},
+					MenuChoice::Mirrored => {
+						let modifiers = &mut self.rules.modifiers;
+						modifiers.mirrored = !modifiers.mirrored;
+						false
+					},
+					MenuChoice::SecondLoop => {
+						if !self.progress.second_loop_unlocked(&self.levels) {
+							return;
+						}
+						let modifiers = &mut self.rules.modifiers;
+						modifiers.second_loop = !modifiers.second_loop;
+						false
+					},
+					MenuChoice::Level(_) => {
+						self.rules.character = self.rules.character.toggle();
+						false
+					},
+					MenuChoice::StatsExport => {
+						self.config.stats_export = self.config.stats_export.cycle(step_right);
+						false
+					},
+					MenuChoice::AssistEnabled => {
+						self.config.assist.enabled = !self.config.assist.enabled;
+						false
+					},
+					MenuChoice::AssistHp => {
+						let (min, max) = Assist::EXTRA_HP_RANGE;
+						let extra_hp = &mut self.config.assist.extra_hp;
+						*extra_hp = if step_right {
+							(*extra_hp + 1).min(max)
+						} else {
+							extra_hp.saturating_sub(1).max(min)
+						};
+						false
+					},
+					MenuChoice::AssistImmunity => {
+						let (min, max) = Assist::IMMUNITY_RANGE;
+						let immunity = &mut self.config.assist.immunity;
+						*immunity = if step_right {
+							(*immunity + Assist::IMMUNITY_STEP).min(max)
+						} else {
+							(*immunity - Assist::IMMUNITY_STEP).max(min)
+						};
+						false
+					},
+					MenuChoice::AssistBulletSpeed => {
+						let (min, max) = Assist::BULLET_SPEED_RANGE;
+						let speed = &mut self.config.assist.bullet_speed;
+						*speed = if step_right {
+							(*speed + Assist::BULLET_SPEED_STEP).min(max)
+						} else {
+							(*speed - Assist::BULLET_SPEED_STEP).max(min)
+						};
+						false
+					},
+					MenuChoice::Preset => {
+						let preset = ControlPreset::cycle(&self.config.bindings, step_right);
+						self.config.bindings = preset.bindings();
+						false
+					},
+					MenuChoice::Controller => {
+						self.gamepads.cycle_active(step_right);
+						false
+					},
+					_ => return,
+				};
+				self.audio.play_sound(SoundBase::MenuMove);
+				if window_changed {
+					self.window.request_window_resize(&self.config);
+				}
+				// The modifiers, the character and the weapon only last until the game closes
+				if matches!(
+					menu_choice,
+					MenuChoice::Resolution
+						| MenuChoice::DisplayMode
+						| MenuChoice::Monitor
+						| MenuChoice::Vsync
+						| MenuChoice::Difficulty
+						| MenuChoice::TitleStatus
+						| MenuChoice::MasterVolume
+						| MenuChoice::EffectsVolume
+						| MenuChoice::BulletOutline
+						| MenuChoice::ScreenShake
+						| MenuChoice::InputDisplay
+						| MenuChoice::DiscordPresence
+						| MenuChoice::StatsExport
+						| MenuChoice::AssistEnabled
+						| MenuChoice::AssistHp
+						| MenuChoice::AssistImmunity
+						| MenuChoice::AssistBulletSpeed
+						| MenuChoice::Preset
+				) {
+					self.save_settings();
+				}
+			},
+			// Left and right already pick the character on the level selection
+			Key::Named(Tab) if matches!(menu_choice, MenuChoice::Level(_)) => {
+				self.audio.play_sound(SoundBase::MenuMove);
+				self.rules.weapon = self.rules.weapon.toggle();
+			},
+			Key::Character(c) if c.as_str() == "s" && self.config.practice => {
+				if let MenuChoice::Level(id) = menu_choice {
+					self.audio.play_sound(SoundBase::MenuMove);
+					let events = &self.levels[id as usize].event_list;
+					self.section.next_start(id as u32, events);
+				}
+			},
+			Key::Character(c) if c.as_str() == "d" && matches!(menu_choice, MenuChoice::Level(_)) => {
+				self.audio.play_sound(SoundBase::MenuMove);
+				self.config.cycle_difficulty(true);
+				self.save_settings();
+			},
 			Key::Named(Enter) => {
 				self.audio.play_sound(SoundBase::MenuSelect);
 				self.state = match menu_choice {
-					MenuChoice::Play => RunState::Menu(MenuChoice::Level(0)),
+					MenuChoice::Play => {
+						self.config.practice = self.config.practice_arg;
+						RunState::Menu(MenuChoice::Level(0))
+					},
+					MenuChoice::Practice => {
+						self.config.practice = true;
+						RunState::Menu(MenuChoice::Level(0))
+					},
+					// Needs an endless level to build the waves in
+					MenuChoice::Daily => match self.endless_level() {
+						Some(id) => {
+							self.config.practice = false;
+							self.daily = Some(daily::today());
+							self.start_level(id);
+							RunState::Playing
+						},
+						None => RunState::Menu(MenuChoice::Daily),
+					},
+					MenuChoice::Modifiers => RunState::Menu(MenuChoice::BulletSpeed),
 					MenuChoice::Options => RunState::Menu(MenuChoice::Resolution),
 					MenuChoice::Quit => RunState::Quitting,
 					MenuChoice::Level(id) => {
+						self.daily = None;
 						self.start_level(id as u32);
 						RunState::Playing
 					},
-					MenuChoice::Resolution => RunState::Menu(MenuChoice::Options),
+					MenuChoice::Resolution
+					| MenuChoice::DisplayMode
+					| MenuChoice::Monitor
+					| MenuChoice::Vsync
+					| MenuChoice::Difficulty
+					| MenuChoice::TitleStatus
+					| MenuChoice::MasterVolume
+					| MenuChoice::EffectsVolume
+					| MenuChoice::BulletOutline
+					| MenuChoice::ScreenShake
+					| MenuChoice::InputDisplay
+					| MenuChoice::DiscordPresence
+					| MenuChoice::StatsExport => RunState::Menu(MenuChoice::Options),
+					MenuChoice::Assist => RunState::Menu(MenuChoice::AssistEnabled),
+					MenuChoice::AssistEnabled
+					| MenuChoice::AssistHp
+					| MenuChoice::AssistImmunity
+					| MenuChoice::AssistBulletSpeed => RunState::Menu(MenuChoice::Assist),
+					MenuChoice::Controls => RunState::Menu(MenuChoice::Preset),
+					MenuChoice::Binding(action) => {
+						self.rebinding = Some(action);
+						RunState::Menu(menu_choice)
+					},
+					MenuChoice::Preset | MenuChoice::Controller => RunState::Menu(menu_choice),
+					MenuChoice::BulletSpeed
+					| MenuChoice::DoubleDamage
+					| MenuChoice::InfiniteBombs
+					| MenuChoice::Mirrored
+					| MenuChoice::SecondLoop => RunState::Menu(MenuChoice::Modifiers),
 					// Allow for future proofing
 					#[allow(unreachable_patterns)]
 					_ => unimplemented!("Menu State '{:?}' not implemented for Enter", menu_choice),
@@ -328,54 +1772,447 @@ impl Game {
 		use winit::keyboard::NamedKey::*;
 		// TODO: Some day, use data structures for keys
 
+		// Before the menus, which can lead back to the pause menu with the same key press
+		if matches!(self.state, RunState::Paused(_)) {
+			self.pause_key_handling(key_state, key);
+			return;
+		}
 		if matches!(self.state, RunState::Menu(_)) {
 			self.menu_key_handling(key_state, key);
+			self.back_to_pause();
 		}
-		match key {
-			Key::Named(ArrowUp) => self.inputs.up = matches!(key_state, ElementState::Pressed),
-			Key::Named(ArrowDown) => self.inputs.down = matches!(key_state, ElementState::Pressed),
-			Key::Named(ArrowLeft) => self.inputs.left = matches!(key_state, ElementState::Pressed),
-			Key::Named(ArrowRight) => self.inputs.right = matches!(key_state, ElementState::Pressed),
-			Key::Character(key) if key == &SmolStr::new("x") => {
-				self.inputs.shoot = matches!(key_state, ElementState::Pressed)
-			},
-			_ => {},
+		if self.state == RunState::Error {
+			self.error_key_handling(key_state, key);
+			return;
+		}
+		if matches!(self.state, RunState::Victory(_) | RunState::Credits) {
+			self.ending_key_handling(key_state, key);
+			return;
+		}
+		if matches!(self.state, RunState::GameOver(_)) {
+			self.game_over_key_handling(key_state, key);
+			return;
+		}
+		// Debug keys
+		if key_state == &ElementState::Pressed {
+			let result = match key {
+				Key::Named(F3) => {
+					self.render_state.show_hitboxes = !self.render_state.show_hitboxes;
+					Ok(())
+				},
+				Key::Named(F4) => {
+					self.render_state.show_frame_graph = !self.render_state.show_frame_graph;
+					Ok(())
+				},
+				Key::Named(F9) if self.dump_keys() => self.dump_world(),
+				Key::Named(F10) if self.dump_keys() => self.load_latest_dump(),
+				_ => Ok(()),
+			};
+			if let Err(err) = result {
+				self.fail(err);
+			}
+		}
+		if self.state == RunState::Playing
+			&& key_state == &ElementState::Pressed
+			&& key == &Key::Named(Escape)
+		{
+			self.pause();
+			return;
+		}
+		let pressed = matches!(key_state, ElementState::Pressed);
+		match self.config.bindings.action(key) {
+			Some(Action::Up) => self.inputs.up = pressed,
+			Some(Action::Down) => self.inputs.down = pressed,
+			Some(Action::Left) => self.inputs.left = pressed,
+			Some(Action::Right) => self.inputs.right = pressed,
+			Some(Action::Shoot) => self.inputs.shoot = pressed,
+			Some(Action::Dash) => self.inputs.dash = pressed,
+			Some(Action::Hyper) => self.inputs.hyper = pressed,
+			Some(Action::Focus) => self.inputs.focus = pressed,
+			Some(Action::Missile) => self.inputs.missile = pressed,
+			Some(Action::Bomb) => self.inputs.bomb = pressed,
+			None => {},
 		}
 	}
 
 	pub fn start_level(&mut self, id: u32) {
-		self.infos.start_level();
-		// The wolrd size is fixed as the lowest resolution and the graphics are scaled up
-		let new_world = World::start(
-			WORLD_SIZE,
-			self.levels.get(id as usize).unwrap().event_list.clone(),
-		);
+		self.infos.start_level(id);
+		// The world size is given in the lowest resolution and the graphics are scaled up
+		let level = self.levels.get(id as usize).unwrap();
+		// A replay rolls the same drops as the recorded run. With the same seed and inputs, two runs
+		// play out the same
+		let seed = match &self.replay {
+			ReplayMode::Playback(playback) => playback.replay.seed,
+			_ => self
+				.daily
+				.map(daily::seed)
+				.or(self.config.seed_arg)
+				.or(level.seed)
+				.unwrap_or_else(Rng::seed_from_time),
+		};
+		self.rules.assist = self.config.assist.clone();
+		self.rules.difficulty = self.config.difficulty();
+		// A replay is simulated with the cheats and assists it was recorded with, and everyone plays
+		// the daily challenge under the same rules. They are locked in the world and the replay
+		// while the chosen ones are kept for the next runs
+		let rules = match (&self.replay, self.daily) {
+			(ReplayMode::Playback(playback), _) => Rules {
+				modifiers: playback.replay.modifiers.clone(),
+				assist: playback.replay.assist.clone(),
+				character: playback.replay.character,
+				weapon: playback.replay.weapon,
+				difficulty: playback.replay.difficulty,
+				..self.rules.clone()
+			},
+			(_, Some(_)) => Rules {
+				modifiers: Modifiers::default(),
+				assist: Assist::default(),
+				difficulty: Difficulty::Normal,
+				..self.rules.clone()
+			},
+			_ => self.rules.clone(),
+		};
+		let events = match level.mode {
+			LevelMode::BossRush => self.boss_rush_events(),
+			_ => level.event_list.clone(),
+		};
+		let mut new_world = World::start(level.playfield, events, &rules, seed);
+		if level.mode == LevelMode::Endless {
+			new_world.endless = Some(Endless::default());
+		}
+		// The same level always has the same background, the seed of the file when it has one
+		let background_seed = level.seed.unwrap_or_else(|| name_seed(&level.name));
+		new_world.background = Background::new(background_seed);
+		if self.config.practice {
+			self.section.attempt(id);
+			if let Some(event) = self.section.start_of(id) {
+				if !new_world.skip_to_event(event) {
+					println!(
+						"{} has no event {event}, started from its beginning",
+						level.name
+					);
+				}
+			}
+			new_world.invincible = self.section.invincible;
+		}
+		self.rewind.reset(&new_world);
 		self.world = Some(new_world);
+		self.input_history.clear();
+		self.update_presence();
+		// A replay being played restarts the level when seeking, it mustn't be replaced.
+		// Rewinding would desync the recording, practice runs aren't recorded
+		if !self.config.practice && !matches!(self.replay, ReplayMode::Playback(_)) {
+			self.replay = ReplayMode::Recording(Replay {
+				level_id: id,
+				modifiers: rules.modifiers,
+				assist: rules.assist,
+				character: rules.character,
+				weapon: rules.weapon,
+				difficulty: rules.difficulty,
+				seed,
+				frames: vec![],
+				rta: Duration::ZERO,
+				igt: Duration::ZERO,
+			});
+		}
 	}
 
-	pub fn tick(&mut self, event_loop: &ActiveEventLoop) {
-		// TODO: Maybe better assignment of world?
-		// Applying events
-		{
-			let world = self.world.as_mut().unwrap();
-			world.process_events();
+	pub fn tick(&mut self, dt: Duration) {
+		self.input_history.record(&self.inputs);
+		let world = self.world.as_mut().unwrap();
+		let boss_was_active = world.boss_active;
+		world.step(&self.inputs, dt);
+		if world.boss_active != boss_was_active {
+			self.update_presence();
+		}
+		let log = std::mem::take(&mut self.world.as_mut().unwrap().log);
+		if let Err(err) = self.session_log.write(log.into_iter()) {
+			self.fail(err);
+			return;
 		}
-		// Projectiles physics
-		self.update_projectiles();
-		// Main physics calculations
-		self.update_entities();
 		// Checks end condition
 		{
 			let world = self.world.as_mut().unwrap();
-			world.check_end(event_loop);
+			// Many bullets can hit on the same tick, each sound is only played once
+			let mut played = vec![];
+			for sound in world.sounds.drain(..) {
+				if !played.contains(&sound) {
+					self.audio.play_sound(sound);
+					played.push(sound);
+				}
+			}
+			for shake in world.shakes.drain(..) {
+				if self.config.screen_shake {
+					self.render_state.shake(shake);
+				}
+			}
+			if self.config.practice {
+				self.rewind.record(world);
+			}
+			// The practiced section starts over instead of the level ending
+			if world.player.hp == 0 && self.config.practice {
+				if let Some(id) = self.infos.level_id {
+					self.start_level(id);
+					return;
+				}
+			}
+			// Endless runs have no checkpoints, they would never end
+			if world.player.hp == 0 && world.difficulty == Difficulty::Easy && world.endless.is_none()
+			{
+				world.restore_checkpoint();
+			}
+			if world.check_end() {
+				self.end_level();
+			}
+		}
+	}
+
+	/// Chains the bosses of the stages, mods included, in the order of the levels. The lives are
+	/// given back once the field is clear after each fight, the next boss comes right after
+	fn boss_rush_events(&self) -> Vec<Event> {
+		let bosses = self
+			.levels
+			.iter()
+			.filter(|level| level.mode == LevelMode::Stage)
+			.flat_map(|level| level.event_list.iter())
+			.filter(|evt| matches!(evt.variant, EventType::SpawnBoss(..)));
+		let mut events = vec![];
+		for boss in bosses {
+			let id = events.len() as u32;
+			if id > 0 {
+				events.push(Event {
+					id,
+					time: None,
+					variant: EventType::RestoreLives,
+					ref_evt: Some((LEVEL_REF, Duration::from_secs(1))),
+					repeat: None,
+					condition: EventCondition::FieldClear,
+				});
+			}
+			// The first one waits on the start of the level, the others on the lives
+			let (t, condition) = match id {
+				0 => (BOSS_RUSH_FIRST_WAIT, EventCondition::Triggered),
+				_ => (BOSS_RUSH_WAIT, EventCondition::FieldClear),
+			};
+			events.push(Event {
+				id: events.len() as u32,
+				time: None,
+				variant: boss.variant.clone(),
+				ref_evt: Some((LEVEL_REF, t)),
+				repeat: None,
+				condition,
+			});
 		}
+		events
+	}
+
+	/// Counts the clear of the level toward the second loop, if it was played on normal
+	/// difficulty, without cheats or assist mode and in the first loop
+	pub fn record_clear(&mut self) {
+		let Some(world) = &self.world else {
+			return;
+		};
+		if world.difficulty != Difficulty::Normal
+			|| self.config.practice
+			|| matches!(self.replay, ReplayMode::Playback(_))
+			|| !world.is_scoring()
+			|| world.assist.enabled
+			|| world.modifiers.second_loop
+		{
+			return;
+		}
+		let Some(level) = self
+			.infos
+			.level_id
+			.and_then(|id| self.levels.get(id as usize))
+		else {
+			return;
+		};
+		if let Err(err) = self.progress.clear(level) {
+			self.fail(err);
+		}
+	}
+
+	/// Adds the endless run that just ended to the leaderboard, or to the daily records, unless it used cheats or assists,
+	/// or was a practice run or a replay
+	pub fn record_endless(&mut self) {
+		let Some(world) = &self.world else {
+			return;
+		};
+		let Some(endless) = &world.endless else {
+			return;
+		};
+		if self.config.practice
+			|| matches!(self.replay, ReplayMode::Playback(_))
+			|| !world.is_scoring()
+			|| world.assist.enabled
+		{
+			return;
+		}
+		let record = EndlessRecord {
+			score: world.score,
+			waves: endless.wave,
+			time: world.clock.now().as_secs_f32(),
+			difficulty: world.difficulty,
+		};
+		// The daily challenge doesn't roll the usual waves, it has its own records
+		let result = match self.daily {
+			Some(day) => self.progress.record_daily(day, record),
+			None => self.progress.record_endless(record),
+		};
+		if let Err(err) = result {
+			self.fail(err);
+		}
+	}
+
+	/// Index of the first endless level, the one the daily challenge is played on
+	fn endless_level(&self) -> Option<u32> {
+		self
+			.levels
+			.iter()
+			.position(|level| level.mode == LevelMode::Endless)
+			.map(|index| index as u32)
 	}
 
 	pub fn update_fps(&mut self) {
 		// Limit fps refresh for it to be readable
-		if self.infos.fps_cooldown.is_over() {
+		// Real time, the fps are shown in the menus too
+		let now = self.infos.sincegame_begin();
+		if self.infos.fps_cooldown.is_over(now) {
 			self.infos.fps = (1. / self.infos.dt.as_secs_f64()).round() as u32;
-			self.infos.fps_cooldown.reset();
+			self.infos.fps_cooldown.reset(now);
+			// `dt` is the length of the last frame, the one the split was measured on
+			let sample = FrameTime {
+				total: self.infos.dt,
+				update: self.infos.update_time,
+				render: self.infos.render_time,
+			};
+			self.infos.frame_graph.push(sample);
+			if self.config.title_status {
+				self.window.set_title(&self.title_status());
+			}
 		}
 	}
+
+	fn title_status(&self) -> String {
+		let fps = self.infos.fps;
+		match &self.world {
+			Some(world) => {
+				let entities = world.enemies.len() + world.projectiles.len();
+				format!(
+					"{WINDOW_TITLE} - Score: {} - FPS: {fps} - Entities: {entities}",
+					world.score
+				)
+			},
+			None => format!("{WINDOW_TITLE} - FPS: {fps}"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_events(raw: &str) -> Vec<Event> {
+		Level::parse(0, raw).unwrap().event_list
+	}
+
+	#[test]
+	fn repeat_ends_the_event_line() {
+		let events = parse_events(
+			"@spawn-enemy basic 1 400 -25 - repeat(3,0.5)\n\
+			 @checkpoint 2 - repeat(1,4)\n\
+			 @checkpoint 3 -",
+		);
+		assert_eq!(events[0].repeat, Some((3, Duration::from_millis(500))));
+		assert_eq!(events[1].repeat, Some((1, Duration::from_secs(4))));
+		assert_eq!(events[2].repeat, None);
+		// The reference before it is still read
+		assert_eq!(events[0].ref_evt, Some((LEVEL_REF, Duration::from_secs(1))));
+	}
+
+	#[test]
+	fn invalid_repeats_are_reported() {
+		for line in [
+			"@checkpoint 1 - repeat(3)",
+			"@checkpoint 1 - repeat(x,1)",
+			"@checkpoint 1 - repeat(3,0)",
+		] {
+			assert!(Level::parse(0, line).is_err(), "{line}");
+		}
+	}
+
+	#[test]
+	fn references_wait_on_their_condition() {
+		let events = parse_events(
+			"@checkpoint 1 -\n\
+			 @checkpoint 2 0\n\
+			 @checkpoint 3 cleared(1)\n\
+			 @checkpoint 4 clear",
+		);
+		let refs: Vec<_> = events
+			.iter()
+			.map(|event| (event.ref_evt.unwrap().0, event.condition))
+			.collect();
+		assert_eq!(
+			refs,
+			[
+				(LEVEL_REF, EventCondition::Triggered),
+				(0, EventCondition::Triggered),
+				(1, EventCondition::WaveCleared),
+				(LEVEL_REF, EventCondition::FieldClear),
+			]
+		);
+		// Numbered in the order of the file
+		let ids: Vec<_> = events.iter().map(|event| event.id).collect();
+		assert_eq!(ids, [0, 1, 2, 3]);
+		assert!(Level::parse(0, "@checkpoint 1 cleared(x)").is_err());
+	}
+
+	#[test]
+	fn toml_levels_read_like_hbh_ones() {
+		let hbh = Level::parse(
+			0,
+			"$title Twin\n\
+			 $path swoop line(3,100,0)\n\
+			 @spawn-enemy basic 1 top(0.5) - swoop repeat(2,0.5)\n\
+			 @checkpoint 2 cleared(0)\n\
+			 @hazard slow(0.5) 0.5 3 0 100 200 50 clear",
+		)
+		.unwrap();
+		let toml = Level::parse_toml(
+			0,
+			r#"
+			title = "Twin"
+			path = { swoop = ["line(3,100,0)"] }
+
+			[[event]]
+			type = "spawn-enemy"
+			enemy = "basic"
+			time = 1
+			pos = "top(0.5)"
+			path = "swoop"
+			repeat = { count = 2, interval = 0.5 }
+
+			[[event]]
+			type = "checkpoint"
+			time = 2
+			after = "cleared(0)"
+
+			[[event]]
+			type = "hazard"
+			kind = "slow(0.5)"
+			time = 0.5
+			duration = 3
+			zone = [0, 100, 200, 50]
+			after = "clear"
+			"#,
+		)
+		.unwrap();
+		assert_eq!(toml.name, hbh.name);
+		assert_eq!(
+			format!("{:?}", toml.event_list),
+			format!("{:?}", hbh.event_list)
+		);
+	}
 }
@@ -0,0 +1,94 @@
+use winit::{event::ElementState, keyboard::Key};
+
+use crate::{
+	game::{Game, Inputs, MenuChoice, RunState},
+	replay::ReplayMode,
+	sound::SoundBase,
+};
+
+/// Entries of the game over screen
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameOverChoice {
+	/// Starts the same level over
+	Retry,
+	/// Back to the title screen
+	Quit,
+}
+
+impl Game {
+	/// Called once the level ended, the player cleared it or ran out of lives
+	pub fn end_level(&mut self) {
+		let Some(world) = &self.world else {
+			return;
+		};
+		let cleared = world.player.hp > 0;
+		self.export_stats();
+		if cleared {
+			self.record_clear();
+		}
+		self.record_endless();
+		if matches!(self.replay, ReplayMode::Playback(_)) {
+			// Nothing to retry or celebrate, the replay is over
+			self.replay = ReplayMode::Off;
+			self.world = None;
+			self.state = RunState::Menu(MenuChoice::Play);
+			self.update_presence();
+		} else if cleared {
+			self.start_victory();
+		} else {
+			self.start_game_over();
+		}
+	}
+
+	/// The level stays frozen behind the game over screen until it is left
+	fn start_game_over(&mut self) {
+		self.audio.play_sound(SoundBase::MenuBack);
+		self.inputs = Inputs::default();
+		self.state = RunState::GameOver(GameOverChoice::Retry);
+		self.update_presence();
+		if let Err(err) = self.finish_replay() {
+			self.fail(err);
+		}
+	}
+
+	pub fn game_over_key_handling(&mut self, key_state: &ElementState, key: &Key) {
+		use winit::keyboard::NamedKey::*;
+		if key_state == &ElementState::Released {
+			return;
+		}
+		let RunState::GameOver(choice) = self.state else {
+			unreachable!("Not in game over state")
+		};
+		match key {
+			Key::Named(ArrowDown) | Key::Named(ArrowUp) => {
+				self.audio.play_sound(SoundBase::MenuMove);
+				self.state = RunState::GameOver(match choice {
+					GameOverChoice::Retry => GameOverChoice::Quit,
+					GameOverChoice::Quit => GameOverChoice::Retry,
+				});
+			},
+			Key::Named(Enter) => {
+				self.audio.play_sound(SoundBase::MenuSelect);
+				match choice {
+					GameOverChoice::Retry => {
+						let level_id = self.infos.level_id.unwrap_or_default();
+						self.start_level(level_id);
+						self.state = RunState::Playing;
+					},
+					GameOverChoice::Quit => self.leave_game_over(),
+				}
+			},
+			Key::Named(Escape) => {
+				self.audio.play_sound(SoundBase::MenuBack);
+				self.leave_game_over();
+			},
+			_ => {},
+		}
+	}
+
+	fn leave_game_over(&mut self) {
+		self.world = None;
+		self.state = RunState::Menu(MenuChoice::Play);
+		self.update_presence();
+	}
+}
@@ -1,30 +1,71 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use winit::{
 	application::ApplicationHandler,
 	error::EventLoopError,
-	event::{ElementState, KeyEvent, WindowEvent},
+	event::{KeyEvent, Modifiers, WindowEvent},
 	event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-	keyboard::Key,
 };
 
-use crate::game::{Game, MenuChoice, RunState};
+use crate::{
+	cli::Args,
+	game::{Game, RunState},
+	progress::Progress,
+	session_log::SessionLog,
+};
 
 struct EventLoopState {
 	game_opt: Option<Game>,
+	args: Args,
+	/// Held modifier keys, for Alt+Enter
+	modifiers: Modifiers,
 }
 
 impl ApplicationHandler for EventLoopState {
 	fn resumed(&mut self, event_loop: &ActiveEventLoop) {
 		if self.game_opt.is_none() {
 			let mut game = Game::launch(event_loop);
-			game.load_levels();
+			game.config.practice = self.args.practice;
+			game.config.practice_arg = self.args.practice;
+			game.section.start_event = self.args.start_event;
+			game.config.stats_export_arg = self.args.stats;
+			game.config.seed_arg = self.args.seed;
+			game.config.difficulty_arg = self.args.difficulty;
+			if let Err(err) = game.load() {
+				game.fail(err);
+				self.game_opt = Some(game);
+				return;
+			}
+			game.load_mods();
+			match Progress::load() {
+				Ok(progress) => game.progress = progress,
+				Err(err) => game.fail(err),
+			}
+			if let Some(path) = &self.args.event_log {
+				match SessionLog::to_file(path) {
+					Ok(log) => game.session_log = log,
+					Err(err) => game.fail(err),
+				}
+			}
+			let mut result = Ok(());
+			if let Some(path) = &self.args.world {
+				result = game.load_world(path);
+			}
+			if let Some(path) = &self.args.replay {
+				result = result.and_then(|_| game.start_playback(path));
+			}
+			if let Some(path) = &self.args.level {
+				result = result.and_then(|_| game.play_level_file(path));
+			}
+			if let Err(err) = result {
+				game.fail(err);
+			}
 			self.game_opt = Some(game);
 		}
 	}
 
 	fn window_event(
 		&mut self,
-		event_loop: &ActiveEventLoop,
+		_event_loop: &ActiveEventLoop,
 		window_id: winit::window::WindowId,
 		event: WindowEvent,
 	) {
@@ -33,22 +74,24 @@ impl ApplicationHandler for EventLoopState {
 			return;
 		}
 		match event {
+			// Closes at the end of the frame, like the quit menu entry
 			WindowEvent::CloseRequested => {
-				event_loop.exit();
+				game.state = RunState::Quitting;
 			},
 			WindowEvent::Resized(size) => {
 				game.resize(&size);
 			},
 
+			WindowEvent::ModifiersChanged(modifiers) => {
+				self.modifiers = modifiers;
+			},
 			WindowEvent::KeyboardInput { event: KeyEvent { ref logical_key, state, .. }, .. } => {
-				use winit::keyboard::NamedKey::*;
-				if matches!(state, ElementState::Pressed) {
-					// TODO: Move these into a function ???
-					if logical_key == &Key::Named(Escape) && game.state == RunState::Playing {
-						game.world = None;
-						game.state = RunState::Menu(MenuChoice::Play);
-					}
+				let alt = self.modifiers.state().alt_key();
+				if game.fullscreen_key_handling(&state, logical_key, alt) {
+					return;
 				}
+				game.replay_key_handling(&state, logical_key);
+				game.practice_key_handling(&state, logical_key);
 				game.process_input(&state, logical_key);
 			},
 			_ => {},
@@ -61,10 +104,15 @@ impl ApplicationHandler for EventLoopState {
 		game.infos.dt = Instant::elapsed(&game.infos.t);
 		game.infos.t = Instant::now();
 		game.update_fps();
+		game.infos.update_time = Duration::ZERO;
+		game.poll_gamepads();
 		game.audio.delete_ended_sounds();
+		game.update_music();
 		match game.state {
 			RunState::Playing => {
-				game.tick(event_loop);
+				let update_begin = Instant::now();
+				game.update();
+				game.infos.update_time = update_begin.elapsed();
 
 				// Drawing
 				game.draw_in_game();
@@ -74,30 +122,68 @@ impl ApplicationHandler for EventLoopState {
 				game.render();
 			},
 			RunState::Menu(choice) => {
+				game.title_scene.update(game.infos.dt);
 				game.draw_menu(choice);
 
 				game.infos.update();
 				game.redraw();
 				game.render();
 			},
+			RunState::Paused(choice) => {
+				// The level stays in the background, frozen
+				game.draw_in_game();
+				game.draw_pause(choice);
+
+				game.infos.update();
+				game.redraw();
+				game.render();
+			},
+			RunState::GameOver(choice) => {
+				// The level stays in the background, frozen
+				game.draw_in_game();
+				game.draw_game_over(choice);
+
+				game.infos.update();
+				game.redraw();
+				game.render();
+			},
+			RunState::Victory(choice) => {
+				game.draw_victory(choice);
+
+				game.infos.update();
+				game.redraw();
+				game.render();
+			},
+			RunState::Credits => {
+				game.update_credits(game.infos.dt);
+				game.draw_credits();
+
+				game.infos.update();
+				game.redraw();
+				game.render();
+			},
+			RunState::Error => {
+				game.draw_error();
+
+				game.infos.update();
+				game.redraw();
+				game.render();
+			},
 			RunState::Quitting => {
 				event_loop.exit();
 			},
-			_ => {},
 		}
 	}
 
 	fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
-		let _game = self.game_opt.as_mut().unwrap();
-		// TODO: Implement game save???
-		// game.save();
-		// game.window.close();
+		let game = self.game_opt.as_mut().unwrap();
+		game.shutdown();
 	}
 }
 
-pub fn game_run() -> Result<(), EventLoopError> {
+pub fn game_run(args: Args) -> Result<(), EventLoopError> {
 	let event_loop = EventLoop::new()?;
 	event_loop.set_control_flow(ControlFlow::Poll);
-	let mut loop_state = EventLoopState { game_opt: None };
+	let mut loop_state = EventLoopState { game_opt: None, args, modifiers: Modifiers::default() };
 	event_loop.run_app(&mut loop_state)
 }
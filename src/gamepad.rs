@@ -0,0 +1,176 @@
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use std::iter;
+use winit::{
+	event::ElementState,
+	keyboard::{Key, NamedKey},
+};
+
+use crate::game::{Game, RunState};
+
+/// Tilt of the left stick past which a direction is held
+const STICK_DEADZONE: f32 = 0.5;
+
+/// Gamepads plugged in, only the active one drives the game
+pub struct Gamepads {
+	/// None when the platform has no gamepad support, the keyboard still works then
+	gilrs: Option<Gilrs>,
+	/// Picked in the controls menu when several are connected, the first one connected otherwise
+	pub active: Option<GamepadId>,
+	/// Active gamepad that was disconnected, it becomes active again once reconnected
+	pub lost: Option<GamepadId>,
+	/// Directions the left stick is tilted to, -1, 0 or 1 on each axis, up being positive
+	stick: (i8, i8),
+}
+
+impl Gamepads {
+	pub fn new() -> Gamepads {
+		let gilrs = Gilrs::new().ok();
+		let active = gilrs
+			.as_ref()
+			.and_then(|gilrs| gilrs.gamepads().next())
+			.map(|(id, _)| id);
+		Gamepads { gilrs, active, lost: None, stick: (0, 0) }
+	}
+
+	fn connected(&self) -> Vec<GamepadId> {
+		self
+			.gilrs
+			.as_ref()
+			.map_or(vec![], |gilrs| gilrs.gamepads().map(|(id, _)| id).collect())
+	}
+
+	/// Name of the active gamepad, None when only the keyboard is used
+	pub fn active_name(&self) -> Option<String> {
+		let gilrs = self.gilrs.as_ref()?;
+		let gamepad = gilrs.connected_gamepad(self.active?)?;
+		Some(gamepad.name().to_string())
+	}
+
+	/// Goes through the connected gamepads and no gamepad at all, in that order
+	pub fn cycle_active(&mut self, step_right: bool) {
+		let mut choices: Vec<Option<GamepadId>> = self.connected().into_iter().map(Some).collect();
+		choices.push(None);
+		let n = choices.len();
+		let index = choices
+			.iter()
+			.position(|id| *id == self.active)
+			.unwrap_or(n - 1);
+		self.active = if step_right {
+			choices[(index + 1) % n]
+		} else {
+			choices[(index + n - 1) % n]
+		};
+		// Picking one by hand stops waiting for the lost one
+		self.lost = None;
+	}
+}
+
+impl Game {
+	/// Handles the gamepad events of the frame. The d-pad and the start button act like the arrows
+	/// and Escape, the face buttons are the actions while playing and confirm or go back in the
+	/// menus
+	pub fn poll_gamepads(&mut self) {
+		let Some(gilrs) = self.gamepads.gilrs.as_mut() else {
+			return;
+		};
+		let events: Vec<_> = iter::from_fn(|| gilrs.next_event()).collect();
+		for event in events {
+			match event.event {
+				EventType::Connected
+					if self.gamepads.active.is_none()
+						&& self.gamepads.lost.is_none_or(|lost| lost == event.id) =>
+				{
+					self.gamepads.active = Some(event.id);
+					self.gamepads.lost = None;
+				},
+				EventType::Disconnected if self.gamepads.active == Some(event.id) => {
+					self.gamepads.active = None;
+					self.gamepads.lost = Some(event.id);
+					if self.state == RunState::Playing {
+						self.pause();
+					}
+				},
+				_ if self.gamepads.active != Some(event.id) => {},
+				EventType::ButtonPressed(button, _) => self.gamepad_button(button, true),
+				EventType::ButtonReleased(button, _) => self.gamepad_button(button, false),
+				EventType::AxisChanged(axis, value, _) => self.gamepad_axis(axis, value),
+				_ => {},
+			}
+		}
+	}
+
+	fn gamepad_button(&mut self, button: Button, pressed: bool) {
+		if self.state == RunState::Playing {
+			match button {
+				Button::South => self.inputs.shoot = pressed,
+				Button::East => self.inputs.dash = pressed,
+				Button::North => self.inputs.hyper = pressed,
+				Button::West => self.inputs.bomb = pressed,
+				Button::LeftTrigger | Button::RightTrigger => self.inputs.focus = pressed,
+				Button::LeftTrigger2 | Button::RightTrigger2 => self.inputs.missile = pressed,
+				// Not sent as arrows, they may not be bound to the movement
+				Button::DPadUp => self.inputs.up = pressed,
+				Button::DPadDown => self.inputs.down = pressed,
+				Button::DPadLeft => self.inputs.left = pressed,
+				Button::DPadRight => self.inputs.right = pressed,
+				_ => {},
+			}
+		}
+		let key = match button {
+			Button::Start => Key::Named(NamedKey::Escape),
+			// The face buttons and the d-pad were handled above while playing
+			_ if self.state == RunState::Playing => return,
+			Button::DPadUp => Key::Named(NamedKey::ArrowUp),
+			Button::DPadDown => Key::Named(NamedKey::ArrowDown),
+			Button::DPadLeft => Key::Named(NamedKey::ArrowLeft),
+			Button::DPadRight => Key::Named(NamedKey::ArrowRight),
+			Button::South => Key::Named(NamedKey::Enter),
+			Button::East => Key::Named(NamedKey::Escape),
+			// The weapon and the difficulty on the level selection
+			Button::North => Key::Named(NamedKey::Tab),
+			Button::West => Key::Character("d".into()),
+			_ => return,
+		};
+		let state = if pressed {
+			ElementState::Pressed
+		} else {
+			ElementState::Released
+		};
+		self.process_input(&state, &key);
+	}
+
+	/// The left stick moves the player, the menus are only browsed with the d-pad. The inputs are
+	/// only written when the stick crosses the deadzone, so a stick resting near the center doesn't
+	/// release the arrows held on the keyboard: the last device used wins
+	fn gamepad_axis(&mut self, axis: Axis, value: f32) {
+		let direction = if value > STICK_DEADZONE {
+			1
+		} else if value < -STICK_DEADZONE {
+			-1
+		} else {
+			0
+		};
+		let held = match axis {
+			Axis::LeftStickX => &mut self.gamepads.stick.0,
+			Axis::LeftStickY => &mut self.gamepads.stick.1,
+			_ => return,
+		};
+		if *held == direction {
+			return;
+		}
+		*held = direction;
+		if self.state != RunState::Playing {
+			return;
+		}
+		match axis {
+			Axis::LeftStickX => {
+				self.inputs.left = direction < 0;
+				self.inputs.right = direction > 0;
+			},
+			_ => {
+				self.inputs.up = direction > 0;
+				self.inputs.down = direction < 0;
+			},
+		}
+	}
+}
@@ -1,20 +1,75 @@
-use cgmath::{InnerSpace, Point2, Vector2, Zero};
+use cgmath::{InnerSpace, MetricSpace, Point2, Vector2, Zero};
+use serde::{Deserialize, Serialize};
 use std::{
-	collections::HashMap,
-	time::{Duration, Instant},
+	collections::{HashMap, VecDeque},
+	f32::consts::{FRAC_1_SQRT_2, PI, TAU},
+	iter,
+	time::Duration,
 };
-use winit::event_loop::ActiveEventLoop;
 
 use crate::{
-	coords::{collide_rectangle, CenteredBox, Dimensions, RectF},
-	game::{Game, Inputs},
+	background::Background,
+	body::{Body, Collider, Mobile},
+	coords::{collide_rectangle, segment_distance, Dimensions, Rect, RectF},
+	endless::{Endless, WAVE_SPAWN_DELAY},
+	enemies::{EnemyType, ItemDrop},
+	flight_path::{FlightPath, Segment},
+	formation::Formation,
+	game::{Difficulty, Inputs},
+	hazard::{ActiveHazard, ActiveWell, GravityWell, Hazard, HazardKind},
+	particles::{self, Particles},
+	pool::Pool,
+	rng::Rng,
+	rules::{Assist, Modifiers, Rules, SecondLoop},
+	session_log::{LogEntry, LogKind, PLAYER_ID},
 	sound::SoundBase,
+	sprites::{self, PlayerPose},
+	stats::RunStats,
+	status::{StatusEffects, StatusKind},
+	systems::{self, Aim, Shooter, Volley},
 };
 
 pub const DT_60: f32 = 1. / 60.;
-#[derive(Clone, Debug)]
+/// Length of a simulation step, the world is always advanced by this much at a time
+pub const TICK: Duration = Duration::from_nanos(16_666_667);
+/// Longest step of the game clock, a stalled frame (like while the window is dragged) counts as
+/// this much
+const MAX_CLOCK_STEP: Duration = Duration::from_millis(100);
+/// Projectiles the world has room for from the start, the dense patterns don't have to grow it
+/// while they are fired
+const PROJECTILE_CAPACITY: usize = 4096;
+
+/// Time of the level, only advanced by the updates of the simulation so it stops while paused, in
+/// the menus and during stalled frames. The cooldowns and the events are timed with it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GameClock {
+	#[serde(with = "crate::snapshot::ticks")]
+	now: Duration,
+}
+
+impl GameClock {
+	/// Game time of a frame that took `dt`
+	pub fn step(dt: Duration) -> Duration {
+		dt.min(MAX_CLOCK_STEP)
+	}
+
+	pub fn advance(&mut self, dt: Duration) {
+		self.now += GameClock::step(dt);
+	}
+
+	/// Game time since the start of the level, also the in-game time of the run
+	pub fn now(self) -> Duration {
+		self.now
+	}
+}
+
+/// Wait between two uses of something. The times given are from the game clock for the gameplay
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Cooldown {
-	last_emit: Option<Instant>,
+	#[serde(with = "crate::snapshot::opt_ticks")]
+	last_emit: Option<Duration>,
+	#[serde(with = "crate::snapshot::ticks")]
 	cooldown: Duration,
 }
 
@@ -29,404 +84,2701 @@ impl Cooldown {
 		Cooldown { last_emit: None, cooldown: value }
 	}
 
-	pub fn is_over(&self) -> bool {
+	pub fn is_over(&self, now: Duration) -> bool {
 		if let Some(last) = self.last_emit {
-			return Instant::elapsed(&last) >= self.cooldown;
+			return now.saturating_sub(last) >= self.cooldown;
 		}
 		true
 	}
 
-	pub fn reset(&mut self) {
-		self.last_emit = Some(Instant::now());
+	pub fn reset(&mut self, now: Duration) {
+		self.last_emit = Some(now);
+	}
+
+	/// Fraction of the cooldown still to go, 0 once it is over
+	pub fn fraction_left(&self, now: Duration) -> f32 {
+		let Some(last) = self.last_emit else {
+			return 0.;
+		};
+		if self.cooldown.is_zero() {
+			return 0.;
+		}
+		let elapsed = now.saturating_sub(last).as_secs_f32() / self.cooldown.as_secs_f32();
+		(1. - elapsed).max(0.)
+	}
+
+	/// Like `is_over`, with the cooldown multiplied by `factor`
+	pub fn is_over_scaled(&self, now: Duration, factor: f32) -> bool {
+		if let Some(last) = self.last_emit {
+			return now.saturating_sub(last) >= self.cooldown.mul_f32(factor);
+		}
+		true
 	}
 }
 
-#[derive(Clone, Debug)]
+/// Power items needed for the next damage tier
+const POWER_PER_TIER: u32 = 5;
+const MAX_DAMAGE_TIER: u32 = 4;
+/// Every this many shots, the player fires a shot applying a status effect
+const SPECIAL_SHOT_EVERY: u32 = 4;
+/// Distance around the hitbox in which enemy bullets are grazed
+const GRAZE_MARGIN: f32 = 16.;
+/// Score of each graze, before the score factor
+const GRAZE_SCORE: u64 = 10;
+/// Rank at the start of a level, between 0 and 1
+const RANK_START: f32 = 0.5;
+/// Rank gained every tick the player survives, a full rank in about 4 minutes
+const RANK_PER_TICK: f32 = 0.00007;
+/// Rank gained with each kill
+const RANK_PER_KILL: f32 = 0.004;
+/// Rank lost with each life
+const RANK_PER_DEATH: f32 = 0.2;
+/// Change of the enemy bullet speed and fire rate from the start rank to the highest one
+const RANK_EFFECT: f32 = 0.4;
+/// Grazes filling the meter, hyper can be triggered once it is full
+pub const GRAZE_METER_MAX: u32 = 40;
+/// Ticks a hyper lasts
+pub const HYPER_TICKS: u32 = 300;
+/// Distance between the center of the player and each of the two side by side shots
+const SHOT_SPREAD: f32 = 10.;
+/// Angle of the diagonal streams from straight up, in radians
+const DIAGONAL_SHOT_ANGLE: f32 = 0.3;
+/// Angle between two neighbouring streams of the spread fan, in radians
+const FAN_ANGLE: f32 = 0.2;
+/// Ticks a reflect barrier lasts
+const BARRIER_TICKS: u32 = 360;
+/// Multiplies the score of kills and point items during hyper
+const HYPER_SCORE_FACTOR: u64 = 2;
+/// Where the player appears when the level starts, and again after each life lost
+const PLAYER_START: Point2<f32> = Point2::new(75., 200.);
+/// Size of the box the enemy bullets and bodies have to reach to hit the player
+pub const PLAYER_HITBOX: Dimensions<f32> = Dimensions { w: 12., h: 12. };
+/// Speed of the homing missiles, in pixels per 60 Hz tick
+const MISSILE_SPEED: f32 = 5.;
+/// Largest turn of a homing missile toward its target, in radians per 60 Hz tick
+const MISSILE_TURN_RATE: f32 = 0.08;
+/// Missiles given by a missile item
+const MISSILES_PER_ITEM: u32 = 3;
+/// Missiles the player can carry
+const MAX_MISSILES: u32 = 9;
+/// Length of a bomb, the enemy bullets vanish and the player can't be hit until it is over
+const BOMB_TICKS: u32 = 120;
+/// Dealt by a bomb to every enemy on screen
+const BOMB_DAMAGE: f32 = 20.;
+/// Option satellites the player can have
+const MAX_OPTIONS: u32 = 2;
+/// Player positions between the player and its first option, and between two options
+const OPTION_SPACING: usize = 12;
+/// Multiplies the damage of the shots fired by the options
+const OPTION_DAMAGE_FACTOR: f32 = 0.5;
+/// Multiplies the player speed while the focus is held
+const FOCUS_SPEED_FACTOR: f32 = 0.5;
+
+/// Playable character, picked on the level selection
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Character {
+	/// Gains the most from power items
+	#[default]
+	Balanced,
+	/// Fires slower piercing shots, hitting harder from the start but gaining less from power
+	/// items
+	Striker,
+}
+
+impl Character {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Character::Balanced => "Balanced",
+			Character::Striker => "Striker",
+		}
+	}
+
+	pub fn toggle(self) -> Character {
+		match self {
+			Character::Balanced => Character::Striker,
+			Character::Striker => Character::Balanced,
+		}
+	}
+
+	/// Damage of a shot at tier 0, and what each tier adds to it
+	fn damage(&self) -> (f32, f32) {
+		match self {
+			Character::Balanced => (2., 0.5),
+			Character::Striker => (3., 0.25),
+		}
+	}
+
+	fn damage_type(&self) -> DamageType {
+		match self {
+			Character::Balanced => DamageType::Normal,
+			Character::Striker => DamageType::Pierce,
+		}
+	}
+
+	fn fire_cooldown_factor(&self) -> f32 {
+		match self {
+			Character::Balanced => 1.,
+			Character::Striker => 1.5,
+		}
+	}
+}
+
+/// Player weapon, picked on the level selection
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weapon {
+	/// Focused shots straight ahead
+	#[default]
+	Straight,
+	/// Fan of weaker shots, covering more of the playfield
+	Spread,
+}
+
+/// Balance of a weapon
+#[derive(Debug)]
+pub struct WeaponDef {
+	pub name: &'static str,
+	/// Multiplies the fire cooldown of the rules and the character
+	cooldown_factor: f32,
+	/// Multiplies the damage of each shot
+	damage_factor: f32,
+	/// Streams fired with each shot, as the offset from the center and the angle from straight up.
+	/// Indexed by the damage tier, the last one is used past it
+	patterns: [&'static [(f32, f32)]; 3],
+}
+
+/// Indexed by `Weapon`
+const WEAPONS: [WeaponDef; 2] = [
+	WeaponDef {
+		name: "Straight",
+		cooldown_factor: 1.,
+		damage_factor: 1.,
+		// A single shot, then two side by side, then the diagonals are added
+		patterns: [
+			&[(0., 0.)],
+			&[(-SHOT_SPREAD, 0.), (SHOT_SPREAD, 0.)],
+			&[
+				(-SHOT_SPREAD, 0.),
+				(SHOT_SPREAD, 0.),
+				(0., -DIAGONAL_SHOT_ANGLE),
+				(0., DIAGONAL_SHOT_ANGLE),
+			],
+		],
+	},
+	WeaponDef {
+		name: "Spread",
+		cooldown_factor: 1.2,
+		damage_factor: 0.6,
+		// A 3-way fan, then a 5-way one, then two shots side by side are added in the middle
+		patterns: [
+			&[(0., -FAN_ANGLE), (0., 0.), (0., FAN_ANGLE)],
+			&[
+				(0., -2. * FAN_ANGLE),
+				(0., -FAN_ANGLE),
+				(0., 0.),
+				(0., FAN_ANGLE),
+				(0., 2. * FAN_ANGLE),
+			],
+			&[
+				(0., -2. * FAN_ANGLE),
+				(0., -FAN_ANGLE),
+				(-SHOT_SPREAD, 0.),
+				(SHOT_SPREAD, 0.),
+				(0., FAN_ANGLE),
+				(0., 2. * FAN_ANGLE),
+			],
+		],
+	},
+];
+
+impl Weapon {
+	pub fn def(self) -> &'static WeaponDef {
+		&WEAPONS[self as usize]
+	}
+
+	pub fn toggle(self) -> Weapon {
+		match self {
+			Weapon::Straight => Weapon::Spread,
+			Weapon::Spread => Weapon::Straight,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Player {
-	pub pos: Point2<f32>,
-	vel: Vector2<f32>,
+	#[serde(flatten)]
+	pub body: Body,
 	pub size: Dimensions<f32>,
-	pub hitbox: CenteredBox,
 	pub hp: u32,
+	/// Lives at the start of the level, life items don't give more
+	max_hp: u32,
+	/// Power items collected
+	pub power: u32,
+	pub character: Character,
+	pub weapon: Weapon,
+	/// Counts the shots to know which ones are special
+	shots_fired: u32,
 	immunity: Cooldown,
 	new_shoot: Cooldown,
+	dash_cd: Cooldown,
+	/// Reset with each dash, the player can't be hit until it is over
+	dash_immunity: Cooldown,
+	dash_distance: f32,
+	missile_cd: Cooldown,
+	/// Homing missiles left, up to `MAX_MISSILES`
+	pub missiles: u32,
+	pub bombs: u32,
+	/// Ticks left of the current bomb, 0 when none is going off
+	pub bomb: u32,
+	/// Option satellites collected, up to `MAX_OPTIONS`
+	pub options: u32,
+	/// Last positions of the player, newest first, followed by the options
+	#[serde(skip)]
+	trail: VecDeque<Point2<f32>>,
+	/// Enemy bullets grazed since the last hyper, up to `GRAZE_METER_MAX`
+	pub graze: u32,
+	/// Ticks left of the current hyper, 0 when not in hyper
+	pub hyper: u32,
+	/// Ticks left of the reflect barrier, enemy bullets touching the player sprite are sent back
+	pub barrier: u32,
+	/// Focus held during the last tick, the hitbox is highlighted
+	pub focused: bool,
 }
 
 impl Player {
-	fn new() -> Self {
+	fn new(rules: &Rules) -> Self {
 		Self {
-			pos: (75., 200.).into(),
-			hitbox: CenteredBox { center: (75., 200.).into(), dims: (12., 12.).into() },
-			vel: (0., 0.).into(),
+			body: Body::new(PLAYER_START, Vector2::zero()),
 			size: Dimensions { w: 48., h: 48. },
-			hp: 5,
-			immunity: Cooldown::with_secs(2.),
-			new_shoot: Cooldown::with_secs(15. * DT_60),
+			hp: rules.player_hp + rules.assist.extra_hp(),
+			max_hp: rules.player_hp + rules.assist.extra_hp(),
+			power: 0,
+			character: rules.character,
+			weapon: rules.weapon,
+			shots_fired: 0,
+			immunity: Cooldown::with_secs(rules.immunity * rules.assist.immunity_factor()),
+			new_shoot: Cooldown::with_secs(
+				rules.fire_cooldown
+					* rules.character.fire_cooldown_factor()
+					* rules.weapon.def().cooldown_factor,
+			),
+			dash_cd: Cooldown::with_secs(rules.dash_cooldown),
+			dash_immunity: Cooldown::with_secs(rules.dash_immunity),
+			dash_distance: rules.dash_distance,
+			missile_cd: Cooldown::with_secs(rules.missile_cooldown),
+			missiles: rules.missiles.min(MAX_MISSILES),
+			bombs: rules.bombs,
+			bomb: 0,
+			options: 0,
+			trail: VecDeque::with_capacity(MAX_OPTIONS as usize * OPTION_SPACING),
+			graze: 0,
+			hyper: 0,
+			barrier: 0,
+			focused: false,
+		}
+	}
+
+	/// Grows with the power items collected, up to `MAX_DAMAGE_TIER`
+	pub fn damage_tier(&self) -> u32 {
+		(self.power / POWER_PER_TIER).min(MAX_DAMAGE_TIER)
+	}
+
+	fn shot_damage(&self) -> f32 {
+		let (base, per_tier) = self.character.damage();
+		(base + per_tier * self.damage_tier() as f32) * self.weapon.def().damage_factor
+	}
+
+	/// Streams of the shot pattern, from the weapon and the damage tier. Hyper fires the pattern
+	/// of the next tier
+	fn shot_pattern(&self) -> &'static [(f32, f32)] {
+		let patterns = &self.weapon.def().patterns;
+		let level = self.damage_tier() + u32::from(self.hyper > 0);
+		patterns[(level as usize).min(patterns.len() - 1)]
+	}
+
+	/// Effect of the next shot, the special shots cycle through the status effects
+	fn next_shot_status(&self) -> Option<StatusKind> {
+		let special = self.shots_fired / SPECIAL_SHOT_EVERY;
+		(self.shots_fired % SPECIAL_SHOT_EVERY == SPECIAL_SHOT_EVERY - 1)
+			.then(|| StatusKind::CYCLE[special as usize % StatusKind::CYCLE.len()])
+	}
+
+	/// Fraction of the invulnerability after a hit still to go, 0 once it is over
+	pub fn immunity_left(&self, now: Duration) -> f32 {
+		self.immunity.fraction_left(now)
+	}
+
+	/// Lives at the start of the level
+	pub fn max_hp(&self) -> u32 {
+		self.max_hp
+	}
+
+	/// Banks toward the side it moves to
+	pub fn pose(&self) -> PlayerPose {
+		if self.body.vel.x < 0. {
+			PlayerPose::BankLeft
+		} else if self.body.vel.x > 0. {
+			PlayerPose::BankRight
+		} else {
+			PlayerPose::Idle
+		}
+	}
+
+	/// Grazes don't fill the meter during hyper
+	fn add_graze(&mut self) {
+		if self.hyper == 0 {
+			self.graze = (self.graze + 1).min(GRAZE_METER_MAX);
+		}
+	}
+
+	/// Empties the full meter to start a hyper, doubling the shots and the score. Tells if it
+	/// started
+	fn trigger_hyper(&mut self) -> bool {
+		if self.graze < GRAZE_METER_MAX || self.hyper > 0 {
+			return false;
+		}
+		self.graze = 0;
+		self.hyper = HYPER_TICKS;
+		true
+	}
+
+	fn score_factor(&self) -> u64 {
+		if self.hyper > 0 {
+			HYPER_SCORE_FACTOR
+		} else {
+			1
 		}
 	}
 
-	pub fn immunity_over(&self) -> bool {
-		self.immunity.is_over()
+	/// Back at the start position after losing a life, invulnerable for a moment
+	fn respawn(&mut self, now: Duration) {
+		// Not drawn sliding across the playfield from where it was hit, the options gather back
+		self.body.teleport(PLAYER_START);
+		self.trail.clear();
+		self.immunity.reset(now);
+		// Half the power is lost with the life
+		self.power /= 2;
+	}
+
+	/// Where the options are, along the path the player took. They wait on the player until it
+	/// has moved far enough
+	pub fn option_positions(&self) -> impl Iterator<Item = Point2<f32>> + '_ {
+		(1..=self.options as usize).map(|i| {
+			self
+				.trail
+				.get(i * OPTION_SPACING - 1)
+				.or(self.trail.back())
+				.copied()
+				.unwrap_or(self.body.pos)
+		})
 	}
 
-	fn update_pos(&mut self, inputs: &Inputs, bounds: RectF, dt: f32) {
+	/// Hits are ignored during the dash and after being hit
+	fn can_be_hit(&self, now: Duration) -> bool {
+		self.immunity.is_over(now) && self.dash_immunity.is_over(now) && self.bomb == 0
+	}
+
+	/// Jumps `dash_distance` in the direction held, up when none is, stopping at the edges
+	fn dash(&mut self, bounds: RectF, now: Duration) {
+		let direction = if self.body.vel == Vector2::zero() {
+			-Vector2::unit_y()
+		} else {
+			self.body.vel.normalize()
+		};
+		let new_pos = self.body.pos + direction * self.dash_distance;
+		self.body.pos = (
+			new_pos.x.clamp(0., bounds.dims.w),
+			new_pos.y.clamp(0., bounds.dims.h),
+		)
+			.into();
+		self.dash_cd.reset(now);
+		self.dash_immunity.reset(now);
+	}
+
+	/// Velocity from the inputs, the movement system moves the player
+	fn steer(&mut self, inputs: &Inputs) {
 		// Inputs
-		self.vel = Vector2::zero();
+		const SPEED: f32 = 5.;
+		let vel = &mut self.body.vel;
+		*vel = Vector2::zero();
 		if inputs.left {
-			self.vel -= Vector2::unit_x();
+			*vel -= Vector2::unit_x();
 		}
 		if inputs.right {
-			self.vel += Vector2::unit_x();
+			*vel += Vector2::unit_x();
 		}
 		if inputs.up {
-			self.vel -= Vector2::unit_y();
+			*vel -= Vector2::unit_y();
 		}
 		if inputs.down {
-			self.vel += Vector2::unit_y();
+			*vel += Vector2::unit_y();
 		}
+		*vel *= SPEED;
+		self.focused = inputs.focus;
+	}
 
-		// Update pos
-		if self.vel != Vector2::zero() {
-			let new_pos = self.pos + 5. * self.vel * dt / DT_60;
-			// Separate x and y checks to allow movement while on an edge
-			if 0. <= new_pos.x && new_pos.x <= bounds.dims.w {
-				self.pos.x = new_pos.x;
-			}
-			if 0. <= new_pos.y && new_pos.y <= bounds.dims.h {
-				self.pos.y = new_pos.y;
-			}
-			self.hitbox.center = self.pos;
+	/// Back on the edges it went past, the options following the move
+	fn confine(&mut self, bounds: RectF) {
+		// Each axis on its own to allow movement along an edge
+		let body = &mut self.body;
+		body.pos.x = body.pos.x.clamp(0., bounds.dims.w);
+		body.pos.y = body.pos.y.clamp(0., bounds.dims.h);
+		// The options only follow while the player moves
+		if self.trail.front() != Some(&body.pos) {
+			self.trail.push_front(body.pos);
+			self.trail.truncate(MAX_OPTIONS as usize * OPTION_SPACING);
 		}
 	}
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum EnemyType {
-	Basic,
-	Sniper,
+impl Collider for Player {
+	fn body(&self) -> &Body {
+		&self.body
+	}
+
+	fn hit_dims(&self) -> Dimensions<f32> {
+		PLAYER_HITBOX
+	}
 }
 
-#[derive(Clone, Debug)]
+impl Mobile for Player {
+	fn body_mut(&mut self) -> &mut Body {
+		&mut self.body
+	}
+
+	fn speed_factor(&self) -> Option<f32> {
+		Some(if self.focused { FOCUS_SPEED_FACTOR } else { 1. })
+	}
+
+	fn drifts(&self) -> bool {
+		true
+	}
+}
+
+impl Shooter for Player {
+	/// The pattern of the power and the options while shoot is held, and a missile from the stock
+	fn shoot(&mut self, aim: &Aim, volley: &mut Volley) {
+		let (inputs, now) = (aim.inputs, aim.now);
+		if inputs.shoot & self.new_shoot.is_over(now) {
+			// Template of the streams of the pattern
+			let proj = Projectile {
+				body: Body::new(
+					self.body.pos - self.size.h / 2. * Vector2::unit_y(),
+					Vector2::zero(),
+				),
+				variant: ProjType::PlayerShoot,
+				damage: self.shot_damage(),
+				damage_type: self.character.damage_type(),
+				status: self.next_shot_status(),
+				grazed: false,
+			};
+			let pattern = self.shot_pattern();
+			for &(offset, angle) in pattern {
+				let pos = proj.body.pos + offset * Vector2::unit_x();
+				let vel = Vector2::new(angle.sin(), -angle.cos()) * 10.;
+				volley
+					.shots
+					.push(Projectile { body: Body::new(pos, vel), ..proj.clone() });
+			}
+			volley.player_shots += pattern.len() as u32;
+			// Each option fires a single weaker shot straight up
+			for pos in self.option_positions() {
+				volley.shots.push(Projectile {
+					body: Body::new(pos, Vector2::unit_y() * -10.),
+					damage: proj.damage * OPTION_DAMAGE_FACTOR,
+					..proj.clone()
+				});
+				volley.player_shots += 1;
+			}
+			self.shots_fired += 1;
+			self.new_shoot.reset(now);
+		}
+		if inputs.missile && self.missiles > 0 && self.missile_cd.is_over(now) {
+			volley.shots.push(Projectile::new(
+				self.body.pos - self.size.h / 2. * Vector2::unit_y(),
+				-MISSILE_SPEED * Vector2::unit_y(),
+				ProjType::Homing,
+			));
+			volley.player_shots += 1;
+			self.missiles -= 1;
+			self.missile_cd.reset(now);
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 enum EnemyState {
 	NotSpawned,
-	OnScreen(fn(&mut Enemy, RectF)),
+	/// The behavior is given by the movement of its `EnemyDef`
+	OnScreen,
 	OffScreen,
 	Dead,
+	/// Blew up on the player, it is gone without being killed
+	Exploded,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Enemy {
-	pub pos: Point2<f32>,
-	vel: Vector2<f32>,
+	/// Unique in the world, used to follow the enemy in the logs
+	pub id: u32,
+	#[serde(flatten)]
+	pub body: Body,
 	pub size: Dimensions<f32>,
 	pub hp: f32,
 	proj_cd: Cooldown,
 	pub variant: EnemyType,
 	state: EnemyState,
+	pub status: StatusEffects,
+	/// Set for the enemies spawned as bosses
+	pub boss: Option<Boss>,
+	/// Unit vector the enemy aims along, for the enemy types turning toward the player
+	pub aim: Vector2<f32>,
+	/// Path given by the level, followed instead of the movement of the type until it is over
+	pub path: Option<FlightPath>,
+	/// Id of the level event that spawned it, none for the enemies let out by others
+	pub wave: Option<u32>,
+	/// Multiplies the max HP of its type, from the difficulty
+	hp_factor: f32,
+}
+
+/// Hit points of a boss phase, relative to the max HP of its enemy type
+const BOSS_HP_FACTOR: f32 = 10.;
+/// Size of a boss, relative to its enemy type
+const BOSS_SIZE_FACTOR: f32 = 2.;
+/// Ticks a boss and the other enemies hold their fire after a boss phase ends
+const PHASE_SAFETY_TICKS: u64 = 90;
+/// Angle between the bullets of a boss spread, in radians
+const BOSS_SPREAD: f32 = 0.26;
+/// Bullets of the ring fired by a boss from its third phase on
+const BOSS_RING_BULLETS: u32 = 12;
+
+/// Phases of a boss, it refills its HP for the next one when one ends
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Boss {
+	/// From 0 to `phases - 1`
+	pub phase: u32,
+	pub phases: u32,
+	/// Set when a phase ends, until the world reacts to it
+	phase_ended: bool,
+}
+
+impl Boss {
+	/// Attack of the current phase, built on the shot of the enemy type: the first phase fires it
+	/// as is, the second one as a spread of three and the next ones add a ring around the boss
+	fn pattern(&self, shot: Vec<Projectile>, pos: Point2<f32>, speed: f32) -> Vec<Projectile> {
+		if self.phase == 0 {
+			return shot;
+		}
+		let mut bullets: Vec<Projectile> = shot
+			.into_iter()
+			.flat_map(|proj| {
+				[-BOSS_SPREAD, 0., BOSS_SPREAD].map(|angle| Projectile {
+					body: Body::new(proj.body.pos, rotate(proj.body.vel, angle)),
+					..proj.clone()
+				})
+			})
+			.collect();
+		if self.phase >= 2 {
+			// Turned a bit more every phase, the gaps aren't where the player learned them
+			let step = TAU / BOSS_RING_BULLETS as f32;
+			let offset = self.phase as f32 * step / 3.;
+			bullets.extend((0..BOSS_RING_BULLETS).map(|i| {
+				let vel = rotate(Vector2::unit_y() * speed, offset + i as f32 * step);
+				Projectile::new(pos, vel, ProjType::Basic)
+			}));
+		}
+		bullets
+	}
+}
+
+/// Unit vector of `dir` turned toward `wanted`, by at most `max_turn` radians
+fn turn_toward(dir: Vector2<f32>, wanted: Vector2<f32>, max_turn: f32) -> Vector2<f32> {
+	let heading = dir.y.atan2(dir.x);
+	// Shortest way around, between -PI and PI
+	let turn = (wanted.y.atan2(wanted.x) - heading + PI).rem_euclid(TAU) - PI;
+	let heading = heading + turn.clamp(-max_turn, max_turn);
+	Vector2::new(heading.cos(), heading.sin())
+}
+
+fn rotate(v: Vector2<f32>, angle: f32) -> Vector2<f32> {
+	let (sin, cos) = angle.sin_cos();
+	Vector2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
 }
 
 impl Enemy {
-	fn spawn(pos: Point2<f32>, variant: EnemyType) -> Enemy {
-		let (size, proj_cd) = match variant {
-			EnemyType::Basic => ((48., 48.).into(), Cooldown::with_secs(25. * DT_60)),
-			EnemyType::Sniper => ((32., 48.).into(), Cooldown::with_secs(40. * DT_60)),
-		};
+	pub fn spawn(id: u32, pos: Point2<f32>, variant: EnemyType) -> Enemy {
+		let def = variant.def();
 		Self {
-			pos,
-			vel: Vector2::zero(),
-			size,
-			hp: Self::max_hp(variant),
-			proj_cd,
+			id,
+			body: Body::new(pos, Vector2::zero()),
+			size: def.size,
+			hp: def.max_hp,
+			proj_cd: Cooldown::with_secs(def.fire_cooldown),
 			variant,
 			state: EnemyState::NotSpawned,
+			status: StatusEffects::default(),
+			boss: None,
+			aim: Vector2::unit_y(),
+			path: None,
+			wave: None,
+			hp_factor: 1.,
 		}
 	}
 
-	pub fn max_hp(variant: EnemyType) -> f32 {
-		match variant {
-			EnemyType::Basic => 15.,
-			EnemyType::Sniper => 8.,
+	/// A sturdier and bigger enemy, holding its position once on screen
+	pub fn spawn_boss(id: u32, pos: Point2<f32>, variant: EnemyType, phases: u32) -> Enemy {
+		let enemy = Enemy::spawn(id, pos, variant);
+		Enemy {
+			size: enemy.size * BOSS_SIZE_FACTOR,
+			hp: enemy.hp * BOSS_HP_FACTOR,
+			boss: Some(Boss { phase: 0, phases, phase_ended: false }),
+			..enemy
 		}
 	}
 
-	fn enemy_func(&mut self) -> fn(&mut Enemy, RectF) {
-		const SPEED: f32 = 0.5;
-		match self.variant {
-			EnemyType::Basic => |enemy, bounds| {
-				enemy.vel = Vector2::unit_y() * SPEED;
-				if enemy.pos.x <= bounds.dims.w / 2. {
-					enemy.vel -= Vector2::unit_x() * SPEED;
-				} else if enemy.pos.x > bounds.dims.w / 2. {
-					enemy.vel += Vector2::unit_x() * SPEED;
-				}
-			},
-			EnemyType::Sniper => |enemy, bounds| {
-				let mid_up: Point2<f32> = (bounds.dims.w / 2., 0.).into();
-				let to_mid = (mid_up - enemy.pos).normalize();
-				// Orthogonal, needs better solution because only one direction works
-				enemy.vel = Vector2::new(to_mid.y, -to_mid.x) * SPEED * 5.;
-			},
+	pub fn max_hp(&self) -> f32 {
+		let max_hp = self.variant.def().max_hp * self.hp_factor;
+		match self.boss {
+			Some(_) => max_hp * BOSS_HP_FACTOR,
+			None => max_hp,
 		}
 	}
 
-	fn update_pos(&mut self, bounds: RectF, dt: f32) {
+	/// Velocity along the movement of its type, `target` being the position of the player. A
+	/// path places the enemy itself
+	fn steer(&mut self, bounds: RectF, target: Point2<f32>, transform: StageTransform, dt: f32) {
 		// Enemies behavior
-		const SPEED: f32 = 0.5;
+		const SPEED: f32 = 1.;
+		let speed_factor = self.status.speed_factor();
+		if let (Some(path), EnemyState::NotSpawned | EnemyState::OnScreen) =
+			(&mut self.path, &self.state)
+		{
+			let ticks = speed_factor * dt / DT_60;
+			match path.advance(ticks, |offset| transform.vector(offset)) {
+				Some(pos) => {
+					if ticks > 0. {
+						self.body.vel = (pos - self.body.pos) / ticks;
+					}
+					self.body.pos = pos;
+					// The path may start and end out of the playfield, for the entries and exits
+					if bounds.contains(pos) {
+						self.state = EnemyState::OnScreen;
+					} else if matches!(self.state, EnemyState::OnScreen) {
+						self.state = EnemyState::OffScreen;
+					}
+					return;
+				},
+				None => self.path = None,
+			}
+		}
 		match self.state {
 			EnemyState::NotSpawned => {
-				self.vel = Vector2::unit_y() * SPEED;
-				self.pos += self.vel * dt / DT_60;
-				if bounds.contains(self.pos) {
-					self.state = EnemyState::OnScreen(self.enemy_func());
+				// Enters the playfield from the side it spawned on
+				let direction = if self.body.pos.x < bounds.top_left.x {
+					Vector2::unit_x()
+				} else if self.body.pos.x >= bounds.top_left.x + bounds.dims.w {
+					-Vector2::unit_x()
+				} else if self.body.pos.y >= bounds.top_left.y + bounds.dims.h {
+					-Vector2::unit_y()
+				} else {
+					Vector2::unit_y()
 				};
+				self.body.vel = direction * SPEED;
 			},
-			EnemyState::OnScreen(f) => {
-				f(self, bounds);
-				if !bounds.contains(self.pos) {
-					self.state = EnemyState::OffScreen;
-				}
+			EnemyState::OnScreen if self.boss.is_some() => self.body.vel = Vector2::zero(),
+			EnemyState::OnScreen => {
+				// Paths are written for the stage as the level describes it
+				transform.enemy(self);
+				(self.variant.def().movement)(self, bounds, transform.point(target));
+				transform.enemy(self);
 			},
 			_ => {},
 		}
-		// Update pos
-		if self.vel != Vector2::zero() {
-			self.pos += self.vel * dt / DT_60;
+	}
+
+	/// Enters the playfield or leaves it once moved, the paths follow it as they go
+	fn confine(&mut self, bounds: RectF) {
+		if self.path.is_some() {
+			return;
+		}
+		match self.state {
+			EnemyState::NotSpawned if bounds.contains(self.body.pos) => {
+				self.state = EnemyState::OnScreen
+			},
+			// Bosses hold their position
+			EnemyState::OnScreen if self.boss.is_none() && !bounds.contains(self.body.pos) => {
+				self.state = EnemyState::OffScreen
+			},
+			_ => {},
 		}
 	}
 
+	/// Center, size and damage multiplier of the weak point, flipped with the stage
+	fn weak_point(&self, transform: StageTransform) -> Option<(Point2<f32>, Dimensions<f32>, f32)> {
+		let weak_point = self.variant.def().weak_point?;
+		let offset = transform.vector(weak_point.offset.into());
+		Some((
+			self.body.pos + offset,
+			weak_point.size.into(),
+			weak_point.multiplier,
+		))
+	}
+
 	fn get_shot(&mut self, damage: f32) {
 		self.hp -= damage;
-		if self.hp <= 0. {
-			self.state = EnemyState::Dead;
+		if self.hp > 0. {
+			return;
+		}
+		let max_hp = self.max_hp();
+		match &mut self.boss {
+			Some(boss) if boss.phase + 1 < boss.phases => {
+				boss.phase += 1;
+				boss.phase_ended = true;
+				self.hp = max_hp;
+			},
+			_ => self.state = EnemyState::Dead,
 		}
 	}
 }
 
-#[derive(Clone, Debug)]
+impl Collider for Enemy {
+	fn body(&self) -> &Body {
+		&self.body
+	}
+
+	fn hit_dims(&self) -> Dimensions<f32> {
+		self.size
+	}
+}
+
+impl Mobile for Enemy {
+	fn body_mut(&mut self) -> &mut Body {
+		&mut self.body
+	}
+
+	fn speed_factor(&self) -> Option<f32> {
+		self.path.is_none().then(|| self.status.speed_factor())
+	}
+
+	fn drifts(&self) -> bool {
+		false
+	}
+}
+
+impl Shooter for Enemy {
+	/// Turns toward the player, and fires the shot of its type or lets out its minion once its
+	/// cooldown is over
+	fn shoot(&mut self, aim: &Aim, volley: &mut Volley) {
+		// Where the shots come from and go to. The enemies turning toward the player fire from
+		// the end of their barrel where they aim, the others below them at the player
+		let def = self.variant.def();
+		let (pos, target) = match def.turn_rate {
+			Some(turn_rate) => {
+				let max_turn = turn_rate * aim.dt / DT_60;
+				self.aim = turn_toward(self.aim, aim.target - self.body.pos, max_turn);
+				let muzzle = self.body.pos + self.aim * self.size.h * 0.6;
+				(muzzle, muzzle + self.aim)
+			},
+			None => (
+				self.body.pos + self.size.h * 0.6 * Vector2::unit_y(),
+				aim.target,
+			),
+		};
+		if !self.proj_cd.is_over_scaled(aim.now, 1. / aim.rank_factor)
+			|| aim.hold_fire
+			|| self.status.is_stunned()
+			|| !aim.bounds.contains(self.body.pos)
+		{
+			return;
+		}
+		self.proj_cd.reset(aim.now);
+		if let Some(minion) = def.releases {
+			volley.released.push((pos, minion));
+			return;
+		}
+		// Patterns are emitted in the stage as the level describes it
+		let transform = aim.transform;
+		let shot = (def.shot)(
+			transform.point(pos),
+			transform.point(target),
+			aim.bullet_speed,
+		);
+		let shot = match &self.boss {
+			Some(boss) => boss.pattern(shot, transform.point(pos), aim.bullet_speed),
+			None => shot,
+		};
+		volley
+			.shots
+			.extend(shot.into_iter().map(|proj| transform.projectile(proj)));
+	}
+}
+
+/// How a projectile hurts, enemies resist some types better than others
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageType {
+	#[default]
+	Normal,
+	Pierce,
+	Explosive,
+	Beam,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ProjType {
 	Basic,
 	Aimed,
 	PlayerShoot,
+	/// Player missile turning toward the nearest enemy
+	Homing,
+	/// Enemy beam along the velocity of the projectile, which stays in place. A warning line is
+	/// shown before it fires
+	Laser {
+		/// Ticks left before it fires
+		warning: u32,
+		/// Ticks left once it fires
+		active: u32,
+	},
 }
 
-const PROJ_SIZE: Dimensions<f32> = Dimensions { w: 10., h: 10. };
-#[derive(Clone, Debug)]
-pub struct Projectile {
-	pub pos: Point2<f32>,
-	vel: Vector2<f32>,
-	pub variant: ProjType,
-}
+impl ProjType {
+	/// Laser starting with its warning line
+	pub fn laser() -> ProjType {
+		ProjType::Laser { warning: LASER_WARNING_TICKS, active: LASER_ACTIVE_TICKS }
+	}
 
-impl Projectile {
-	fn damage(&self) -> f32 {
-		match self.variant {
-			ProjType::Basic => 1.,
-			ProjType::Aimed => 1.,
-			ProjType::PlayerShoot => 2.,
-		}
+	/// Hits the enemies instead of the player
+	pub fn is_player_shot(&self) -> bool {
+		matches!(self, ProjType::PlayerShoot | ProjType::Homing)
 	}
 }
 
-#[derive(Clone, Debug)]
-pub enum EventType {
-	_SpawnEnemy(Point2<f32>, EnemyType),
-	_SpawnBoss(Point2<f32>),
+/// What a picked up item gives
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+	/// Adds `POINT_ITEM_SCORE` to the score
+	Point,
+	/// Raises the player power
+	Power,
+	/// Builds a chain, see `Chain`
+	Chain,
+	/// Rare, reflects the enemy bullets for `BARRIER_TICKS`
+	Barrier,
+	/// Very rare, gives back a life lost
+	Life,
+	/// Gives `MISSILES_PER_ITEM` homing missiles
+	Missile,
+	/// Rare, adds an option satellite, up to `MAX_OPTIONS`
+	Option,
 }
 
-#[derive(Clone, Debug)]
-pub struct Event {
-	pub id: u32,
-	pub time: Option<Instant>,
-	/// (`id`, `offset`), id of the trigger event, and the duration of the wait after said event is triggered
-	pub ref_evt: Option<(u32, Duration)>,
-	pub variant: EventType,
-}
+pub const ITEM_SIZE: Dimensions<f32> = Dimensions { w: 12., h: 12. };
+/// Falling speed of the items, in pixels per 60 Hz tick
+const ITEM_SPEED: f32 = 1.5;
+const POINT_ITEM_SCORE: u64 = 50;
+/// Ticks allowed between two chain items for the chain to go on, 1.5 seconds at 60 Hz
+const CHAIN_WINDOW_TICKS: u64 = 90;
+/// Multiplied by the square of the chain length when it is banked
+const CHAIN_BONUS: u64 = 20;
 
-#[derive(Clone, Debug)]
-pub struct EventSystem {
-	list: Vec<Event>,
-	history: HashMap<u32, Instant>,
-	_latest_id: u32,
+/// Chain items picked up in a row, each one less than `CHAIN_WINDOW_TICKS` after the previous.
+/// The chain is banked when the window runs out, a single item gives nothing
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Chain {
+	pub count: u64,
+	/// Tick of the last pickup
+	last_pickup: u64,
 }
 
-impl EventSystem {
-	fn new(evt_list: Vec<Event>) -> Self {
-		use crate::game::LEVEL_REF;
-		let mut list = vec![];
-		for evt in evt_list {
-			let mut evt = evt.clone();
-			if evt.ref_evt.is_some_and(|(x, _)| x == LEVEL_REF) {
-				evt.time = Some(Instant::now() + evt.ref_evt.unwrap().1);
-				evt.ref_evt = None;
-			}
-			list.push(evt);
-		}
-		Self { list, history: HashMap::new(), _latest_id: 0 }
+impl Chain {
+	fn pick(&mut self, tick: u64) {
+		self.count += 1;
+		self.last_pickup = tick;
 	}
 
-	fn events_clear(&self) -> bool {
-		self.list.is_empty()
+	/// Fraction of the window left before the chain is banked
+	pub fn time_left(&self, tick: u64) -> f32 {
+		let elapsed = tick
+			.saturating_sub(self.last_pickup)
+			.min(CHAIN_WINDOW_TICKS);
+		1. - elapsed as f32 / CHAIN_WINDOW_TICKS as f32
+	}
+
+	/// Ends the chain once its window is over, returns the score it pays
+	fn bank(&mut self, tick: u64) -> u64 {
+		if self.count == 0 || tick.saturating_sub(self.last_pickup) < CHAIN_WINDOW_TICKS {
+			return 0;
+		}
+		let count = std::mem::take(&mut self.count);
+		if count < 2 {
+			return 0;
+		}
+		CHAIN_BONUS * count * count
 	}
 }
 
-#[derive(Clone, Debug)]
-pub struct World {
-	pub player: Player,
-	pub projectiles: Vec<Projectile>,
-	pub enemies: Vec<Enemy>,
-	boundaries: RectF,
-	pub score: u64,
-	event_syst: EventSystem,
+/// Ticks allowed between two kills for the combo to rise, 1 second at 60 Hz. Past it, the
+/// multiplier goes down by one every window without a kill
+const COMBO_WINDOW_TICKS: u64 = 60;
+const MAX_COMBO: u64 = 16;
+
+/// Multiplier of the kill score, one more with each kill close enough to the previous one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Combo {
+	pub multiplier: u64,
+	/// Tick of the last kill, or of the last decay since
+	last_change: u64,
 }
 
-impl World {
-	/// Create a new `World` instance that can draw a moving box.
-	pub fn start(dims: Dimensions<f32>, evt_list: Vec<Event>) -> Self {
-		Self {
-			player: Player::new(),
-			projectiles: Vec::new(),
-			enemies: vec![],
-			boundaries: dims.into_rect(),
-			score: 0,
-			event_syst: EventSystem::new(evt_list),
-		}
+impl Default for Combo {
+	fn default() -> Combo {
+		Combo { multiplier: 1, last_change: 0 }
 	}
+}
 
-	pub fn check_end(&self, event_loop: &ActiveEventLoop) {
-		if self.player.hp == 0 {
-			// Goofiest dead message
-			println!("Ur so dead 💀, RIP BOZO 🔫🔫😂😂😂😂");
-			event_loop.exit();
-		}
-		if self.enemies.is_empty() && self.event_syst.events_clear() {
-			println!("You won! Score: {score}", score = self.score);
-			event_loop.exit();
-		}
+impl Combo {
+	/// Counts a kill, returns the multiplier it scores with
+	fn kill(&mut self, tick: u64) -> u64 {
+		let multiplier = self.multiplier;
+		self.multiplier = (self.multiplier + 1).min(MAX_COMBO);
+		self.last_change = tick;
+		multiplier
 	}
 
-	pub fn process_events(&mut self) {
-		let evt_list = &mut self.event_syst.list;
-		let map = &mut self.event_syst.history;
-		// Checks if absolute events are triggered
-		evt_list.retain(|e| {
-			if !e.time.is_some_and(|t| Instant::now() >= t) {
-				return true;
-			}
-			match &e.variant {
-				EventType::_SpawnEnemy(pos, variant) => {
-					self.enemies.push(Enemy::spawn(*pos, *variant));
-				},
-				var => {
-					unimplemented!("Event variant '{var:?}' not implemented")
-				},
-			}
-			map.insert(e.id, Instant::now());
-			false
-		});
-		// Updates relative events to be transformed into absolute events
-		for e in evt_list.iter_mut() {
-			if let Some((id, t)) = e.ref_evt {
-				if map.contains_key(&id) {
-					e.ref_evt = None;
-					e.time = Some(map[&id] + t);
-				}
-			}
+	/// Fraction of the window left before the multiplier goes down
+	pub fn time_left(&self, tick: u64) -> f32 {
+		let elapsed = tick
+			.saturating_sub(self.last_change)
+			.min(COMBO_WINDOW_TICKS);
+		1. - elapsed as f32 / COMBO_WINDOW_TICKS as f32
+	}
+
+	fn decay(&mut self, tick: u64) {
+		if self.multiplier > 1 && tick.saturating_sub(self.last_change) >= COMBO_WINDOW_TICKS {
+			self.multiplier -= 1;
+			self.last_change = tick;
 		}
 	}
 }
 
-impl Game {
-	pub fn update_entities(&mut self) {
-		let world = &mut self.world.as_mut().unwrap();
-		let dt = self.infos.dt;
-		let inputs = &self.inputs;
-		// Player
-		let player = &mut world.player;
-		player.update_pos(inputs, world.boundaries, dt.as_secs_f32());
-		// Player shoot
-		if inputs.shoot & player.new_shoot.is_over() {
-			let proj = Projectile {
-				pos: player.pos - player.size.h / 2. * Vector2::unit_y(),
-				vel: Vector2::unit_y() * -10.,
-				variant: ProjType::PlayerShoot,
-			};
-			world.projectiles.push(proj);
-			self.audio.play_sound(SoundBase::PlayerShoot);
-			player.new_shoot.reset();
-		}
+/// Score threshold escalating the look of the stage
+pub struct Milestone {
+	pub score: u64,
+	/// Background color once reached
+	pub bg: [u8; 4],
+	/// Cutoff of the low-pass filter on the music once reached, in hertz. The higher, the more
+	/// intense the music sounds
+	pub music_cutoff: f64,
+}
 
-		// Enemies physics
-		// Updates position
-		world.enemies.retain_mut(|enemy| {
-			enemy.update_pos(world.boundaries, dt.as_secs_f32());
-			// If the enemy is dead, add points
-			if matches!(enemy.state, EnemyState::Dead) {
-				world.score += 100;
-				return false;
+/// Reached in order, the last one reached sets the background and the music intensity
+pub const MILESTONES: [Milestone; 4] = [
+	Milestone { score: 10_000, bg: [0x12, 0x0b, 0x2a, 0xff], music_cutoff: 4_000. },
+	Milestone { score: 50_000, bg: [0x1e, 0x0a, 0x2c, 0xff], music_cutoff: 7_000. },
+	Milestone {
+		score: 150_000,
+		bg: [0x2a, 0x08, 0x22, 0xff],
+		music_cutoff: 12_000.,
+	},
+	Milestone {
+		score: 400_000,
+		bg: [0x34, 0x06, 0x10, 0xff],
+		music_cutoff: 20_000.,
+	},
+];
+/// Ticks the celebration of a reached milestone lasts
+pub const CELEBRATION_TICKS: u64 = 90;
+
+/// Afterimages left along a dash
+const DASH_GHOSTS: u32 = 4;
+/// Ticks the afterimage closest to the end of the dash stays on screen
+const GHOST_TICKS: u32 = 16;
+
+/// Afterimage of the player left by a dash
+#[derive(Clone, Debug)]
+pub struct Ghost {
+	pub pos: Point2<f32>,
+	ticks_left: u32,
+}
+
+impl Ghost {
+	/// From 1 when it appears to 0 when it disappears
+	pub fn opacity(&self) -> f32 {
+		self.ticks_left as f32 / GHOST_TICKS as f32
+	}
+}
+
+/// Ticks a hit effect stays on screen
+const HIT_SPARK_TICKS: u32 = 8;
+
+/// How a player shot hit an enemy, shown by the spark it leaves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitKind {
+	Normal,
+	/// On a weak point, drawn bigger and in another color
+	Critical,
+	/// On an armored front, drawn smaller and dimmer
+	Armored,
+}
+
+/// Face of an enemy a shot went through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitSide {
+	Front,
+	Side,
+	Rear,
+}
+
+impl HitSide {
+	/// Face reached by a shot moving at `vel`, the enemies face down the stage
+	fn of_shot(vel: Vector2<f32>) -> HitSide {
+		// The shot comes from the opposite of where it goes
+		let from = -vel.normalize();
+		if from.y > FRAC_1_SQRT_2 {
+			HitSide::Front
+		} else if from.y < -FRAC_1_SQRT_2 {
+			HitSide::Rear
+		} else {
+			HitSide::Side
+		}
+	}
+}
+
+/// Flash drawn where a player shot hit an enemy
+#[derive(Clone, Debug)]
+pub struct HitSpark {
+	pub pos: Point2<f32>,
+	pub kind: HitKind,
+	ticks_left: u32,
+}
+
+/// Ticks a popup stays on screen
+const POPUP_TICKS: u32 = 40;
+/// Speed of the popups going up, in pixels per 60 Hz tick
+const POPUP_RISE: f32 = 0.6;
+
+/// What a rectangle tested by the collisions belongs to, for the hitbox overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitArea {
+	Player,
+	Enemy,
+	/// Part of an enemy taking more damage, inside its body
+	WeakPoint,
+	PlayerShot,
+	EnemyBullet,
+}
+
+/// What a popup shows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PopupKind {
+	/// Damage dealt by a shot, colored like the spark of the hit
+	Damage(HitKind),
+	/// Score earned by a kill
+	Score,
+}
+
+/// Number rising from where an enemy was hit or killed, fading out as it goes
+#[derive(Clone, Debug)]
+pub struct Popup {
+	pub pos: Point2<f32>,
+	pub text: String,
+	pub kind: PopupKind,
+	vel: Vector2<f32>,
+	ticks_left: u32,
+}
+
+impl Popup {
+	fn new(pos: Point2<f32>, text: String, kind: PopupKind) -> Popup {
+		Popup {
+			pos,
+			text,
+			kind,
+			vel: -POPUP_RISE * Vector2::unit_y(),
+			ticks_left: POPUP_TICKS,
+		}
+	}
+
+	/// From 1 when it appears to 0 when it disappears
+	pub fn opacity(&self) -> f32 {
+		self.ticks_left as f32 / POPUP_TICKS as f32
+	}
+}
+
+/// Shaking of the playfield, the offset fades over the duration
+#[derive(Clone, Copy, Debug)]
+pub struct Shake {
+	/// Largest offset, in base resolution pixels
+	pub amplitude: f32,
+	/// In seconds
+	pub duration: f32,
+}
+
+const PLAYER_HIT_SHAKE: Shake = Shake { amplitude: 6., duration: 0.4 };
+const HYPER_SHAKE: Shake = Shake { amplitude: 3., duration: 0.3 };
+const BOMB_SHAKE: Shake = Shake { amplitude: 6., duration: 0.5 };
+const BOSS_DEATH_SHAKE: Shake = Shake { amplitude: 10., duration: 1. };
+
+/// Animation of an enemy dying, where it was killed
+#[derive(Clone, Debug)]
+pub struct Explosion {
+	pub pos: Point2<f32>,
+	pub size: Dimensions<f32>,
+	/// Since it started
+	pub ticks: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Item {
+	pub pos: Point2<f32>,
+	pub kind: ItemKind,
+}
+
+/// Rolls each drop of a killed enemy, `drop_rate` multiplies their chance
+fn roll_drops(
+	drops: &[ItemDrop],
+	pos: Point2<f32>,
+	drop_rate: f32,
+	rng: &mut Rng,
+	items: &mut Vec<Item>,
+) {
+	for drop in drops {
+		if rng.next_f32() >= drop.chance * drop_rate {
+			continue;
+		}
+		for _ in 0..drop.count {
+			// Spread so the items don't stack on each other
+			let offset = Vector2::new(rng.next_f32() - 0.5, rng.next_f32() - 0.5) * 32.;
+			items.push(Item { pos: pos + offset, kind: drop.kind });
+		}
+	}
+}
+
+const PROJ_SIZE: Dimensions<f32> = Dimensions { w: 10., h: 10. };
+/// Ticks the warning line of a laser is shown before it fires
+const LASER_WARNING_TICKS: u32 = 45;
+/// Ticks a laser fires for
+const LASER_ACTIVE_TICKS: u32 = 40;
+/// Length of a laser, enough to cross the playfield
+pub const LASER_LENGTH: f32 = 1500.;
+pub const LASER_WIDTH: f32 = 14.;
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Projectile {
+	#[serde(flatten)]
+	pub body: Body,
+	pub variant: ProjType,
+	damage: f32,
+	damage_type: DamageType,
+	/// Applied to the enemy hit
+	pub status: Option<StatusKind>,
+	/// Enemy bullets are grazed once
+	grazed: bool,
+}
+
+impl Projectile {
+	pub fn new(pos: Point2<f32>, vel: Vector2<f32>, variant: ProjType) -> Projectile {
+		let damage = match variant {
+			ProjType::Basic => 1.,
+			ProjType::Aimed => 1.,
+			ProjType::PlayerShoot => 2.,
+			ProjType::Homing => 6.,
+			ProjType::Laser { .. } => 1.,
+		};
+		Projectile {
+			body: Body::new(pos, vel),
+			variant,
+			damage,
+			damage_type: DamageType::Normal,
+			status: None,
+			grazed: false,
+		}
+	}
+
+	/// Other end of the beam of a laser, None for the other projectiles
+	pub fn laser_end(&self) -> Option<Point2<f32>> {
+		matches!(self.variant, ProjType::Laser { .. })
+			.then(|| self.body.pos + self.body.vel.normalize() * LASER_LENGTH)
+	}
+
+	/// Whether the projectile overlaps the box centered on `pos`. A laser only touches while it
+	/// fires, along its whole beam
+	fn touches(&self, pos: Point2<f32>, dims: Dimensions<f32>) -> bool {
+		match (&self.variant, self.laser_end()) {
+			(ProjType::Laser { warning, .. }, Some(end)) => {
+				*warning == 0
+					&& segment_distance(pos, self.body.pos, end)
+						< (LASER_WIDTH + dims.w.min(dims.h)) / 2.
+			},
+			_ => collide_rectangle(pos, dims, self.body.pos, self.hit_dims()),
+		}
+	}
+
+	/// Turns toward the nearest enemy on screen, by at most `MISSILE_TURN_RATE` per tick
+	fn home(&mut self, enemies: &[Enemy], dt_ticks: f32) {
+		let Some(target) = enemies
+			.iter()
+			.filter(|enemy| matches!(enemy.state, EnemyState::OnScreen))
+			.map(|enemy| enemy.body.pos)
+			.min_by(|a, b| {
+				self
+					.body
+					.pos
+					.distance2(*a)
+					.total_cmp(&self.body.pos.distance2(*b))
+			})
+		else {
+			return;
+		};
+		let heading = turn_toward(
+			self.body.vel,
+			target - self.body.pos,
+			MISSILE_TURN_RATE * dt_ticks,
+		);
+		self.body.vel = heading * self.body.vel.magnitude();
+	}
+
+	/// Turns an enemy bullet into a player shot going straight up at the same speed
+	fn reflect(&mut self, damage: f32) {
+		self.variant = ProjType::PlayerShoot;
+		self.body.vel = -self.body.vel.magnitude() * Vector2::unit_y();
+		self.damage = damage;
+		self.damage_type = DamageType::Normal;
+	}
+}
+
+impl Collider for Projectile {
+	fn body(&self) -> &Body {
+		&self.body
+	}
+
+	fn hit_dims(&self) -> Dimensions<f32> {
+		PROJ_SIZE
+	}
+}
+
+impl Mobile for Projectile {
+	fn body_mut(&mut self) -> &mut Body {
+		&mut self.body
+	}
+
+	/// Lasers stay where they were fired
+	fn speed_factor(&self) -> Option<f32> {
+		self.laser_end().is_none().then_some(1.)
+	}
+
+	fn drifts(&self) -> bool {
+		true
+	}
+}
+
+/// Distance outside the playfield of the enemies spawned on its edges
+const SPAWN_EDGE_OFFSET: f32 = 25.;
+
+/// Where an enemy appears, resolved against the playfield when it spawns
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum SpawnPos {
+	Absolute(Point2<f32>),
+	/// Above the playfield, at a fraction of its width
+	TopEdge(f32),
+	/// Left of the playfield, at a height in pixels
+	LeftEdge(f32),
+	/// Right of the playfield, at a height in pixels
+	RightEdge(f32),
+	/// Above the player by an offset in pixels
+	AbovePlayer(f32),
+}
+
+impl SpawnPos {
+	fn resolve(self, world: &World) -> Point2<f32> {
+		let Rect { top_left, dims } = world.boundaries;
+		let transform = world.transform();
+		let pos = match self {
+			SpawnPos::Absolute(pos) => pos,
+			SpawnPos::TopEdge(x) => (top_left.x + x * dims.w, top_left.y - SPAWN_EDGE_OFFSET).into(),
+			SpawnPos::LeftEdge(y) => (top_left.x - SPAWN_EDGE_OFFSET, top_left.y + y).into(),
+			SpawnPos::RightEdge(y) => (top_left.x + dims.w + SPAWN_EDGE_OFFSET, top_left.y + y).into(),
+			// Follows the player wherever the stage puts them
+			SpawnPos::AbovePlayer(offset) => {
+				return world.player.body.pos - Vector2::unit_y() * offset
+			},
+		};
+		transform.point(pos)
+	}
+}
+
+/// Reflection across the vertical axis of the playfield for mirrored stages, does nothing
+/// otherwise. Applying it twice gives back the original
+#[derive(Clone, Copy, Debug)]
+pub struct StageTransform {
+	axis_x: Option<f32>,
+}
+
+impl StageTransform {
+	fn point(self, pos: Point2<f32>) -> Point2<f32> {
+		match self.axis_x {
+			Some(axis_x) => (2. * axis_x - pos.x, pos.y).into(),
+			None => pos,
+		}
+	}
+
+	fn vector(self, vec: Vector2<f32>) -> Vector2<f32> {
+		match self.axis_x {
+			Some(_) => Vector2::new(-vec.x, vec.y),
+			None => vec,
+		}
+	}
+
+	fn enemy(self, enemy: &mut Enemy) {
+		enemy.body.pos = self.point(enemy.body.pos);
+		enemy.body.vel = self.vector(enemy.body.vel);
+	}
+
+	fn hazard(self, hazard: Hazard) -> Hazard {
+		let HazardKind::Wind(force) = hazard.kind else {
+			return Hazard { zone: self.rect(hazard.zone), ..hazard };
+		};
+		let kind = HazardKind::Wind(self.vector(force * Vector2::unit_x()).x);
+		Hazard { kind, zone: self.rect(hazard.zone), ..hazard }
+	}
+
+	fn rect(self, rect: RectF) -> RectF {
+		// The right edge becomes the left one
+		let right = rect.top_left + rect.dims.w * Vector2::unit_x();
+		Rect { top_left: self.point(right), ..rect }
+	}
+
+	fn projectile(self, proj: Projectile) -> Projectile {
+		Projectile {
+			body: Body::new(self.point(proj.body.pos), self.vector(proj.body.vel)),
+			..proj
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum EventType {
+	/// Path followed from the spawn position, none for the movement of the type
+	_SpawnEnemy(SpawnPos, EnemyType, Option<Vec<Segment>>),
+	/// Enemy type and number of phases
+	SpawnBoss(SpawnPos, EnemyType, u32),
+	Checkpoint,
+	Hazard(Hazard),
+	GravityWell(GravityWell),
+	/// Removes the enemies of this type still in the playfield, bosses aside
+	Despawn(EnemyType),
+	/// Group of enemies laid out around the position, spawned one after the other
+	SpawnFormation(SpawnPos, Formation),
+	/// Gives back every life lost, between the fights of a boss rush
+	RestoreLives,
+}
+
+/// Member of a formation or of an endless wave waiting for its turn to spawn
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingSpawn {
+	tick: u64,
+	/// Id of the formation event, none in the endless waves
+	wave: Option<u32>,
+	pos: Point2<f32>,
+	variant: EnemyType,
+	path: Option<Vec<Segment>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Event {
+	pub id: u32,
+	/// Game time at which the event is triggered, None while it waits on `ref_evt`
+	#[serde(with = "crate::snapshot::opt_ticks")]
+	pub time: Option<Duration>,
+	/// (`id`, `offset`), id of the trigger event, and the duration of the wait after said event is triggered
+	pub ref_evt: Option<(u32, Duration)>,
+	pub variant: EventType,
+	pub repeat: Option<Repeat>,
+	/// What the wait of `ref_evt` starts from
+	pub condition: EventCondition,
+}
+
+/// Moment the offset of a relative event is counted from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventCondition {
+	/// Trigger of the `ref_evt` event
+	#[default]
+	Triggered,
+	/// Every enemy spawned by the `ref_evt` event being gone
+	WaveCleared,
+	/// Every earlier event of the level being triggered and no enemy being left on the field,
+	/// `ref_evt` is ignored
+	FieldClear,
+}
+
+/// (`count`, `interval`), times an event is triggered again after the first one, and the wait
+/// between two
+pub type Repeat = (u32, Duration);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventSystem {
+	list: Vec<Event>,
+	/// Game time at which each event was triggered
+	#[serde(with = "crate::snapshot::ticks_map")]
+	history: HashMap<u32, Duration>,
+	/// Game time at which the last enemy spawned by each event was gone
+	#[serde(with = "crate::snapshot::ticks_map")]
+	cleared: HashMap<u32, Duration>,
+	_latest_id: u32,
+}
+
+impl EventSystem {
+	fn new(evt_list: Vec<Event>) -> Self {
+		use crate::game::LEVEL_REF;
+		let mut list = vec![];
+		for evt in evt_list {
+			let mut evt = evt.clone();
+			// The level starts at game time zero
+			if let (Some((LEVEL_REF, t)), EventCondition::Triggered) = (evt.ref_evt, evt.condition) {
+				evt.time = Some(t);
+				evt.ref_evt = None;
 			}
-			// Removes if offscreen
-			!matches!(enemy.state, EnemyState::OffScreen)
+			list.push(evt);
+		}
+		Self {
+			list,
+			history: HashMap::new(),
+			cleared: HashMap::new(),
+			_latest_id: 0,
+		}
+	}
+
+	fn events_clear(&self) -> bool {
+		self.list.is_empty()
+	}
+
+	/// The event is waiting to be triggered, or to be triggered again
+	fn is_pending(&self, id: u32) -> bool {
+		self.list.iter().any(|evt| evt.id == id)
+	}
+
+	/// A boss is still to be spawned
+	fn boss_pending(&self) -> bool {
+		self
+			.list
+			.iter()
+			.any(|evt| matches!(evt.variant, EventType::SpawnBoss(..)))
+	}
+
+	/// Pushes back every timer by `by`, as if the time had stopped
+	fn delay(&mut self, by: Duration) {
+		for evt in self.list.iter_mut() {
+			evt.time = evt.time.map(|t| t + by);
+		}
+		for t in self.history.values_mut() {
+			*t += by;
+		}
+	}
+
+	/// Starts the events at `id`, as if the earlier ones had been triggered and cleared at the
+	/// start of the level. The absolute times are brought forward, for `id` to come right away.
+	/// False, and nothing skipped, when no event left has this id
+	fn skip_to(&mut self, id: u32) -> bool {
+		if !self.list.iter().any(|evt| evt.id == id) {
+			return false;
+		}
+		let (skipped, list) = std::mem::take(&mut self.list)
+			.into_iter()
+			.partition::<Vec<_>, _>(|evt| evt.id < id);
+		self.list = list;
+		for evt in skipped {
+			self.history.insert(evt.id, Duration::ZERO);
+			self.cleared.insert(evt.id, Duration::ZERO);
+		}
+		let start = self
+			.list
+			.iter()
+			.find(|evt| evt.id == id)
+			.and_then(|evt| evt.time)
+			.unwrap_or_default();
+		for evt in self.list.iter_mut() {
+			evt.time = evt.time.map(|t| t.saturating_sub(start));
+		}
+		true
+	}
+}
+
+/// Where the player restarts after dying in easy difficulty
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+	/// Events left when the checkpoint was reached
+	event_syst: EventSystem,
+	score: u64,
+	/// Game time at which it was reached
+	#[serde(with = "crate::snapshot::ticks")]
+	reached: Duration,
+	/// The player as the rules of the run set it up, it starts over from there
+	player: Player,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct World {
+	pub player: Player,
+	pub projectiles: Pool<Projectile>,
+	pub enemies: Vec<Enemy>,
+	pub items: Vec<Item>,
+	pub chain: Chain,
+	pub combo: Combo,
+	/// Zones placed by the level events, until they run out
+	pub hazards: Vec<ActiveHazard>,
+	pub wells: Vec<ActiveWell>,
+	boundaries: RectF,
+	/// Distance outside the playfield at which projectiles are removed
+	despawn_margin: f32,
+	pub score: u64,
+	/// Number of `MILESTONES` reached
+	pub milestones: usize,
+	/// Tick at which the last milestone was reached, for its celebration
+	pub milestone_tick: u64,
+	/// Set while a boss is fighting, the background gets dimmed to make bullets readable
+	pub boss_active: bool,
+	/// A boss was killed, the level ends with it when no other boss is left to come
+	boss_defeated: bool,
+	/// Enemies don't shoot before this tick, set when a boss phase ends
+	safe_until: u64,
+	event_syst: EventSystem,
+	/// Cheats of the run, it isn't scored if there are any
+	pub modifiers: Modifiers,
+	/// Set in endless runs
+	pub endless: Option<Endless>,
+	/// Assist mode of the run
+	pub assist: Assist,
+	/// Scales the enemies
+	pub difficulty: Difficulty,
+	/// Hidden, between 0 and 1. Rises while the player survives and kills, falls when it dies,
+	/// and makes the enemies fire faster and faster bullets with it
+	rank: f32,
+	/// Counted for the statistics export
+	pub stats: RunStats,
+	/// Practice toggle, the player can't be hit
+	pub invincible: bool,
+	/// Resumed from a dump outside practice, the run is not scored
+	pub tampered: bool,
+	/// Formation members spawned in the next ticks
+	pending_spawns: Vec<PendingSpawn>,
+	/// Rules overlay of the second loop, when it is played
+	second_loop: Option<SecondLoop>,
+	/// Last checkpoint reached, the start of the level until the first one
+	checkpoint: Checkpoint,
+	/// Number of updates since the start of the level
+	pub tick: u64,
+	/// Also the in-game time of the run
+	#[serde(rename = "igt")]
+	pub clock: GameClock,
+	/// Id given to the next spawned enemy
+	next_id: u32,
+	/// Drops are rolled with it, seeded at the start of the level
+	rng: Rng,
+	/// Scrolls behind the playfield
+	pub background: Background,
+	/// Multiplies the chance of every drop
+	drop_rate: f32,
+	/// Gameplay events of the current tick, for the session log
+	#[serde(skip)]
+	pub log: Vec<LogEntry>,
+	/// Sound effects of the current tick, played by the game once it is over
+	#[serde(skip)]
+	pub sounds: Vec<SoundBase>,
+	/// Screen shakes started on the current tick, passed to the renderer once it is over
+	#[serde(skip)]
+	pub shakes: Vec<Shake>,
+	/// Only drawn, they aren't part of the dumps
+	#[serde(skip)]
+	pub hit_sparks: Vec<HitSpark>,
+	#[serde(skip)]
+	pub popups: Vec<Popup>,
+	#[serde(skip)]
+	pub ghosts: Vec<Ghost>,
+	#[serde(skip)]
+	pub particles: Particles,
+	#[serde(skip)]
+	pub explosions: Vec<Explosion>,
+}
+
+impl World {
+	/// World of a level at its first tick: a playfield of `dims`, the player set up by the rules
+	/// and the events still to come. `seed` drives every random roll of the run
+	pub fn start(dims: Dimensions<f32>, evt_list: Vec<Event>, rules: &Rules, seed: u64) -> Self {
+		let event_syst = EventSystem::new(evt_list);
+		let player = Player::new(rules);
+		Self {
+			player: player.clone(),
+			projectiles: Pool::with_capacity(PROJECTILE_CAPACITY),
+			enemies: vec![],
+			items: vec![],
+			chain: Chain::default(),
+			combo: Combo::default(),
+			hazards: vec![],
+			wells: vec![],
+			boundaries: dims.into_rect(),
+			despawn_margin: rules.despawn_margin,
+			score: 0,
+			milestones: 0,
+			milestone_tick: 0,
+			boss_active: false,
+			boss_defeated: false,
+			safe_until: 0,
+			modifiers: rules.modifiers.clone(),
+			assist: rules.assist.clone(),
+			endless: None,
+			difficulty: rules.difficulty,
+			rank: RANK_START,
+			stats: RunStats::default(),
+			invincible: false,
+			tampered: false,
+			pending_spawns: vec![],
+			second_loop: rules
+				.modifiers
+				.second_loop
+				.then(|| rules.second_loop.clone()),
+			checkpoint: Checkpoint {
+				event_syst: event_syst.clone(),
+				score: 0,
+				reached: Duration::ZERO,
+				player,
+			},
+			event_syst,
+			tick: 0,
+			clock: GameClock::default(),
+			next_id: PLAYER_ID + 1,
+			rng: Rng::new(seed),
+			background: Background::new(seed),
+			drop_rate: rules.drop_rate,
+			log: vec![],
+			sounds: vec![],
+			shakes: vec![],
+			hit_sparks: vec![],
+			popups: vec![],
+			ghosts: vec![],
+			particles: Particles::default(),
+			explosions: vec![],
+		}
+	}
+
+	/// Whether the run counts for the scores: no cheat modifier and not resumed from a dump
+	pub fn is_scoring(&self) -> bool {
+		self.modifiers.is_scoring() && !self.tampered
+	}
+
+	pub fn spawn_enemy(&mut self, pos: Point2<f32>, variant: EnemyType) {
+		self.add_enemy(Enemy::spawn(self.next_id, pos, variant));
+	}
+
+	/// Spawns an enemy of the wave of event `wave`, following the path from `pos` or moving like
+	/// its type without one
+	fn spawn_on_path(
+		&mut self,
+		pos: Point2<f32>,
+		variant: EnemyType,
+		path: Option<Vec<Segment>>,
+		wave: Option<u32>,
+	) {
+		let mut enemy = Enemy::spawn(self.next_id, pos, variant);
+		enemy.path = path.map(|segments| FlightPath::new(pos, segments));
+		enemy.wave = wave;
+		self.add_enemy(enemy);
+	}
+
+	/// Records the time the wave of event `wave` was cleared, once none of its enemies is left or
+	/// still to come
+	fn check_cleared(&mut self, wave: u32) {
+		let remaining = self.enemies.iter().any(|enemy| enemy.wave == Some(wave))
+			|| self
+				.pending_spawns
+				.iter()
+				.any(|spawn| spawn.wave == Some(wave))
+			|| self.event_syst.is_pending(wave);
+		if !remaining {
+			let now = self.clock.now();
+			self.event_syst.cleared.entry(wave).or_insert(now);
+		}
+	}
+
+	/// Adds an enemy created with `next_id`, with the difficulty and the second loop applied
+	fn add_enemy(&mut self, mut enemy: Enemy) {
+		let difficulty = self.difficulty.def();
+		enemy.hp_factor = difficulty.enemy_hp;
+		enemy.hp *= difficulty.enemy_hp;
+		let loop_factor = self
+			.second_loop
+			.as_ref()
+			.map_or(1., |second_loop| second_loop.fire_cooldown_factor);
+		let cooldown = enemy.variant.def().fire_cooldown * difficulty.fire_cooldown * loop_factor;
+		enemy.proj_cd = Cooldown::with_secs(cooldown);
+		self.log.push(LogEntry::new(
+			self.tick,
+			LogKind::Spawn,
+			self.next_id,
+			enemy.body.pos,
+		));
+		self.enemies.push(enemy);
+		self.next_id += 1;
+	}
+
+	/// Reacts to the boss lifecycle: the end of a phase clears the enemy bullets, turned into
+	/// point items, and gives the player time before the next phase opens fire
+	pub fn update_bosses(&mut self) {
+		let mut phase_ended = false;
+		for boss in self
+			.enemies
+			.iter_mut()
+			.filter_map(|enemy| enemy.boss.as_mut())
+		{
+			phase_ended |= std::mem::take(&mut boss.phase_ended);
+		}
+		if phase_ended {
+			let items = &mut self.items;
+			self.projectiles.retain(|proj| {
+				if proj.variant.is_player_shot() {
+					return true;
+				}
+				items.push(Item { pos: proj.body.pos, kind: ItemKind::Point });
+				false
+			});
+			self.safe_until = self.tick + PHASE_SAFETY_TICKS;
+		}
+		self.boss_active = self.enemies.iter().any(|enemy| enemy.boss.is_some());
+	}
+
+	/// Multiplies the enemy bullet speed and divides their fire cooldown, 1 at the start rank
+	fn rank_factor(&self) -> f32 {
+		1. + RANK_EFFECT * (self.rank - RANK_START) / (1. - RANK_START)
+	}
+
+	/// Speed of the enemy bullets, with the difficulty, the modifiers, the assist mode and the
+	/// second loop
+	fn enemy_bullet_speed(&self) -> f32 {
+		let loop_factor = self
+			.second_loop
+			.as_ref()
+			.map_or(1., |second_loop| second_loop.bullet_speed_factor);
+		10. * self.difficulty.def().bullet_speed
+			* self.modifiers.bullet_speed_factor()
+			* self.assist.bullet_speed_factor()
+			* loop_factor
+	}
+
+	fn transform(&self) -> StageTransform {
+		let axis_x = self.boundaries.top_left.x + self.boundaries.dims.w / 2.;
+		StageTransform { axis_x: self.modifiers.mirrored.then_some(axis_x) }
+	}
+
+	/// The background is dimmed while a boss fights, and lightens in the pause between two phases
+	pub fn background_dimmed(&self) -> bool {
+		self.boss_active && self.tick >= self.safe_until
+	}
+
+	/// Playfield rectangle, in base resolution pixels
+	pub fn rect(&self) -> RectF {
+		self.boundaries
+	}
+
+	/// Center and size of every rectangle given to `collide_rectangle`, at the simulated
+	/// positions. The lasers hit along their beam and aren't listed
+	pub fn hit_areas(&self) -> Vec<(HitArea, Point2<f32>, Dimensions<f32>)> {
+		let mut areas = vec![(
+			HitArea::Player,
+			self.player.body.pos,
+			self.player.hit_dims(),
+		)];
+		for enemy in self.enemies.iter() {
+			areas.push((HitArea::Enemy, enemy.body.pos, enemy.hit_dims()));
+			if let Some((pos, size, _)) = enemy.weak_point(self.transform()) {
+				areas.push((HitArea::WeakPoint, pos, size));
+			}
+		}
+		for proj in self.projectiles.iter() {
+			if proj.laser_end().is_some() {
+				continue;
+			}
+			let kind = if proj.variant.is_player_shot() {
+				HitArea::PlayerShot
+			} else {
+				HitArea::EnemyBullet
+			};
+			areas.push((kind, proj.body.pos, proj.hit_dims()));
+		}
+		areas
+	}
+
+	/// Tells if the level is over, the player ran out of lives, the last boss was killed or every
+	/// enemy and event is gone. Endless runs only end with the lives
+	pub fn check_end(&self) -> bool {
+		self.player.hp == 0
+			|| (self.boss_defeated && !self.event_syst.boss_pending())
+			|| (self.enemies.is_empty()
+				&& self.pending_spawns.is_empty()
+				&& self.event_syst.events_clear()
+				&& self.endless.is_none())
+	}
+
+	pub fn process_events(&mut self) {
+		let tick = self.tick;
+		let now = self.clock.now();
+		self.hazards.retain(|active| active.until > tick);
+		self.wells.retain(|active| active.until > tick);
+		// Checks if absolute events are triggered
+		let mut triggered = vec![];
+		self.event_syst.list.retain(|e| {
+			if e.time.is_none_or(|t| now < t) {
+				return true;
+			}
+			triggered.push(e.clone());
+			false
 		});
-		for enemy in world.enemies.iter_mut() {
-			// Shooting
-			if enemy.proj_cd.is_over() && world.boundaries.contains(enemy.pos) {
-				let proj = {
-					let pos = enemy.pos + enemy.size.h * 0.6 * Vector2::unit_y();
-					match enemy.variant {
-						EnemyType::Basic => {
-							Projectile { pos, vel: Vector2::unit_y() * 10., variant: ProjType::Basic }
-						},
-						EnemyType::Sniper => {
-							let delta = player.pos - pos;
-							let mut to_player = Vector2::zero();
-							if delta != Vector2::zero() {
-								to_player = delta.normalize();
-							}
-							Projectile { pos, vel: 10. * to_player, variant: ProjType::Aimed }
-						},
+		// Re-armed from the time they were due, the intervals don't drift with the ticks
+		for e in triggered.iter() {
+			if let (Some((count, interval)), Some(time)) = (e.repeat, e.time) {
+				if count > 0 {
+					self.event_syst.list.push(Event {
+						time: Some(time + interval),
+						repeat: (count > 1).then_some((count - 1, interval)),
+						..e.clone()
+					});
+				}
+			}
+		}
+		let mut checkpoint_reached = false;
+		for e in triggered {
+			match &e.variant {
+				EventType::_SpawnEnemy(pos, variant, path) => {
+					let pos = pos.resolve(self);
+					self.spawn_on_path(pos, *variant, path.clone(), Some(e.id));
+				},
+				EventType::SpawnBoss(pos, variant, phases) => {
+					let pos = pos.resolve(self);
+					let mut boss = Enemy::spawn_boss(self.next_id, pos, *variant, *phases);
+					boss.wave = Some(e.id);
+					self.add_enemy(boss);
+				},
+				EventType::Checkpoint => checkpoint_reached = true,
+				EventType::RestoreLives => self.player.hp = self.player.max_hp(),
+				EventType::Hazard(hazard) => self.hazards.push(ActiveHazard {
+					hazard: self.transform().hazard(*hazard),
+					until: self.tick + hazard.duration,
+				}),
+				EventType::GravityWell(well) => self.wells.push(ActiveWell {
+					well: GravityWell { center: self.transform().point(well.center), ..*well },
+					until: self.tick + well.duration,
+				}),
+				EventType::Despawn(variant) => {
+					for enemy in self
+						.enemies
+						.iter_mut()
+						.filter(|enemy| enemy.variant == *variant && enemy.boss.is_none())
+					{
+						// Removed like the ones leaving the playfield, without a kill
+						enemy.state = EnemyState::OffScreen;
 					}
-				};
-				world.projectiles.push(proj);
-				enemy.proj_cd.reset();
+				},
+				EventType::SpawnFormation(pos, formation) => {
+					let pos = pos.resolve(self);
+					let transform = self.transform();
+					for i in 0..formation.count {
+						self.pending_spawns.push(PendingSpawn {
+							tick: tick + i as u64 * formation.delay,
+							wave: Some(e.id),
+							pos: pos + transform.vector(formation.offset(i)),
+							variant: formation.variant,
+							path: formation.path.clone(),
+						});
+					}
+				},
 			}
+			self.event_syst.history.insert(e.id, now);
 		}
+		// Endless runs send their waves instead of the level events
+		let field_clear = self.enemies.is_empty() && self.pending_spawns.is_empty();
+		let transform = self.transform();
+		if let Some(endless) = &mut self.endless {
+			if endless.wave_due(tick, field_clear) {
+				let Rect { top_left, dims } = self.boundaries;
+				let wave = endless.next_wave(tick, &mut self.rng);
+				for (i, (variant, x)) in wave.into_iter().enumerate() {
+					let pos = (top_left.x + x * dims.w, top_left.y - SPAWN_EDGE_OFFSET).into();
+					self.pending_spawns.push(PendingSpawn {
+						tick: tick + i as u64 * WAVE_SPAWN_DELAY,
+						wave: None,
+						pos: transform.point(pos),
+						variant,
+						path: None,
+					});
+				}
+			}
+		}
+		// Formation members and endless waves whose turn came, in the order they were queued
+		let (due, pending) = std::mem::take(&mut self.pending_spawns)
+			.into_iter()
+			.partition::<Vec<_>, _>(|spawn| spawn.tick <= tick);
+		self.pending_spawns = pending;
+		for spawn in due {
+			self.spawn_on_path(spawn.pos, spawn.variant, spawn.path, spawn.wave);
+		}
+		// The earliest event left blocks the ones waiting for the field to clear after it
+		let first_pending = self.event_syst.list.iter().map(|e| e.id).min();
+		let field_clear = self.enemies.is_empty() && self.pending_spawns.is_empty();
+		let evt_list = &mut self.event_syst.list;
+		// Updates relative events to be transformed into absolute events
+		for e in evt_list.iter_mut() {
+			let Some((id, t)) = e.ref_evt else {
+				continue;
+			};
+			let since = match e.condition {
+				EventCondition::Triggered => self.event_syst.history.get(&id).copied(),
+				EventCondition::WaveCleared => self.event_syst.cleared.get(&id).copied(),
+				EventCondition::FieldClear => {
+					(field_clear && first_pending == Some(e.id)).then_some(now)
+				},
+			};
+			if let Some(since) = since {
+				e.ref_evt = None;
+				e.time = Some(since + t);
+			}
+		}
+		// Taken once the events are updated, the ones waiting on the checkpoint are kept
+		if checkpoint_reached {
+			self.checkpoint.event_syst = self.event_syst.clone();
+			self.checkpoint.score = self.score;
+			self.checkpoint.reached = now;
+		}
+	}
+
+	/// Starts the level at the event `id` for practice, the checkpoint is moved there too.
+	/// False when the level has no such event
+	pub fn skip_to_event(&mut self, id: u32) -> bool {
+		if !self.event_syst.skip_to(id) {
+			return false;
+		}
+		self.checkpoint.event_syst = self.event_syst.clone();
+		true
 	}
 
-	pub fn update_projectiles(&mut self) {
-		let world = &mut self.world.as_mut().unwrap();
-		let player = &mut world.player;
+	/// Sends the player back to the last checkpoint, with the events and score it had
+	pub fn restore_checkpoint(&mut self) {
+		let now = self.clock.now();
+		let checkpoint = &mut self.checkpoint;
+		// The events resume where they were when the checkpoint was reached
+		checkpoint
+			.event_syst
+			.delay(now.saturating_sub(checkpoint.reached));
+		checkpoint.reached = now;
+		self.event_syst = checkpoint.event_syst.clone();
+		self.score = checkpoint.score;
+		self.player = checkpoint.player.clone();
+		self.enemies.clear();
+		self.pending_spawns.clear();
+		self.projectiles.clear();
+		self.items.clear();
+		self.hazards.clear();
+		self.wells.clear();
+		self.chain = Chain::default();
+		self.combo = Combo::default();
+	}
+
+	/// Costs the player `damage` lives, it starts again from the start position while some are
+	/// left
+	fn hit_player(&mut self, damage: u32) {
+		if self.invincible {
+			return;
+		}
+		self.combo = Combo::default();
+		self.shakes.push(PLAYER_HIT_SHAKE);
+		let player = &mut self.player;
+		// Avoids underflow if damage is more than 1
+		player.hp = player.hp.saturating_sub(damage);
+		self.stats.deaths += 1;
+		self.rank = (self.rank - RANK_PER_DEATH * damage as f32).max(0.);
+		self.sounds.push(SoundBase::PlayerHit);
+		self.particles.emit(player.body.pos, particles::PLAYER_HIT);
+		self.log.push(LogEntry::new(
+			self.tick,
+			LogKind::Hit,
+			PLAYER_ID,
+			player.body.pos,
+		));
+		if player.hp == 0 {
+			self.log.push(LogEntry::new(
+				self.tick,
+				LogKind::Death,
+				PLAYER_ID,
+				player.body.pos,
+			));
+			return;
+		}
+		player.respawn(self.clock.now());
+	}
 
-		world.projectiles.retain_mut(|proj| {
-			proj.pos += proj.vel * self.infos.dt.as_secs_f32() / DT_60;
-			if !world.boundaries.contains(proj.pos) {
+	/// Celebrates the milestones the score went past
+	pub fn check_milestones(&mut self) {
+		while MILESTONES
+			.get(self.milestones)
+			.is_some_and(|milestone| self.score >= milestone.score)
+		{
+			self.milestones += 1;
+			self.milestone_tick = self.tick;
+		}
+	}
+
+	/// Last milestone reached, if it is still being celebrated
+	pub fn celebrated_milestone(&self) -> Option<&'static Milestone> {
+		let last = MILESTONES.get(self.milestones.checked_sub(1)?)?;
+		(self.tick.saturating_sub(self.milestone_tick) < CELEBRATION_TICKS).then_some(last)
+	}
+
+	/// Keeps the positions before the tick, to draw the entities between two ticks
+	pub fn remember_positions(&mut self) {
+		for mobile in mobiles(&mut self.player, &mut self.enemies, &mut self.projectiles) {
+			mobile.body_mut().remember_pos();
+		}
+	}
+
+	/// Fades the hit effects
+	pub fn update_effects(&mut self) {
+		self.particles.update();
+		self.hit_sparks.retain_mut(|spark| {
+			spark.ticks_left -= 1;
+			spark.ticks_left > 0
+		});
+		self.popups.retain_mut(|popup| {
+			popup.pos += popup.vel;
+			popup.ticks_left -= 1;
+			popup.ticks_left > 0
+		});
+		self.ghosts.retain_mut(|ghost| {
+			ghost.ticks_left -= 1;
+			ghost.ticks_left > 0
+		});
+		self.explosions.retain_mut(|explosion| {
+			explosion.ticks += 1;
+			explosion.ticks < sprites::EXPLOSION.duration()
+		});
+	}
+
+	/// Items fall and are picked up when they touch the player
+	pub fn update_items(&mut self, dt: Duration) {
+		self.score += self.chain.bank(self.tick);
+		self.combo.decay(self.tick);
+		let tick = self.tick;
+		let despawn_bounds = self.boundaries.grow(self.despawn_margin);
+		let player = &mut self.player;
+		self.items.retain_mut(|item| {
+			item.pos.y += ITEM_SPEED * dt.as_secs_f32() / DT_60;
+			if collide_rectangle(player.body.pos, player.size, item.pos, ITEM_SIZE) {
+				match item.kind {
+					ItemKind::Point => self.score += POINT_ITEM_SCORE * player.score_factor(),
+					ItemKind::Power => player.power += 1,
+					ItemKind::Chain => self.chain.pick(tick),
+					ItemKind::Barrier => player.barrier = BARRIER_TICKS,
+					ItemKind::Life if player.hp < player.max_hp => player.hp += 1,
+					ItemKind::Life => {},
+					ItemKind::Missile => {
+						player.missiles = (player.missiles + MISSILES_PER_ITEM).min(MAX_MISSILES)
+					},
+					ItemKind::Option => player.options = (player.options + 1).min(MAX_OPTIONS),
+				}
+				self.sounds.push(SoundBase::Pickup);
 				return false;
 			}
+			despawn_bounds.contains(item.pos)
+		});
+	}
+}
+
+/// Contact found by the collision system, resolved once they are all known. The indices are
+/// those of the projectile pool and the enemy list
+enum Contact {
+	/// Player shot reaching an enemy
+	Shot { proj: usize, enemy: usize },
+	/// Enemy bullet caught by the barrier
+	Barrier(usize),
+	/// Enemy bullet reaching the hitbox
+	Bullet(usize),
+	/// Enemy bullet brushing past the hitbox
+	Graze(usize),
+	/// Enemy body reaching the hitbox
+	Ram(usize),
+}
+
+/// Every body of the world, for the systems shared by all of them
+fn mobiles<'a>(
+	player: &'a mut Player,
+	enemies: &'a mut [Enemy],
+	projectiles: &'a mut Pool<Projectile>,
+) -> impl Iterator<Item = &'a mut dyn Mobile> {
+	iter::once(player as &mut dyn Mobile)
+		.chain(enemies.iter_mut().map(|enemy| enemy as &mut dyn Mobile))
+		.chain(projectiles.iter_mut().map(|proj| proj as &mut dyn Mobile))
+}
+
+impl World {
+	/// One tick of the level: the events, then the entities and what follows from them. A replay
+	/// gives back the same world when its inputs and steps are played again
+	pub fn step(&mut self, inputs: &Inputs, dt: Duration) {
+		self.remember_positions();
+		self.tick += 1;
+		self.clock.advance(dt);
+		self.process_events();
+		self.stats.sample_score(self.tick, self.score);
+		self.update(inputs, dt);
+		self.update_bosses();
+		self.update_items(dt);
+		self.update_effects();
+		self.check_milestones();
+	}
+
+	/// Advances the entities by a tick. Each kind steers its bodies, then the systems shared by
+	/// all of them move the bodies, find and resolve the contacts, pay out the deaths and fire
+	pub fn update(&mut self, inputs: &Inputs, dt: Duration) {
+		let dt = dt.as_secs_f32();
+		// The rank speeds the bullets up and shortens the cooldowns
+		self.rank = (self.rank + RANK_PER_TICK).min(1.);
+		self.steer_player(inputs);
+		self.steer_enemies(dt);
+		self.steer_projectiles(dt);
+		systems::movement(
+			mobiles(&mut self.player, &mut self.enemies, &mut self.projectiles),
+			&self.hazards,
+			dt,
+		);
+		self.confine();
+		let contacts = self.contacts();
+		self.resolve(contacts);
+		let gone_waves = self.remove_gone_enemies();
+		self.fire(inputs, dt);
+		for wave in gone_waves {
+			self.check_cleared(wave);
+		}
+	}
+
+	/// Velocity from the inputs, and the dash, hyper and bomb they trigger
+	fn steer_player(&mut self, inputs: &Inputs) {
+		let now = self.clock.now();
+		let player = &mut self.player;
+		player.steer(inputs);
+		// Player dash
+		if inputs.dash && player.dash_cd.is_over(now) {
+			let start = player.body.pos;
+			player.dash(self.boundaries, now);
+			// The ghosts closer to the start fade first
+			self.ghosts.extend((0..DASH_GHOSTS).map(|i| Ghost {
+				pos: start + (player.body.pos - start) * i as f32 / DASH_GHOSTS as f32,
+				ticks_left: GHOST_TICKS * (i + 1) / DASH_GHOSTS,
+			}));
+		}
+		player.barrier = player.barrier.saturating_sub(1);
+		// Player hyper
+		player.hyper = player.hyper.saturating_sub(1);
+		if inputs.hyper && player.trigger_hyper() {
+			self.shakes.push(HYPER_SHAKE);
+		}
+		// Player bomb, from the stock
+		player.bomb = player.bomb.saturating_sub(1);
+		let has_bomb = player.bombs > 0 || self.modifiers.infinite_bombs;
+		if inputs.bomb && has_bomb && player.bomb == 0 {
+			if !self.modifiers.infinite_bombs {
+				player.bombs -= 1;
+			}
+			player.bomb = BOMB_TICKS;
+			// The kills are counted with the other deaths
+			for enemy in self
+				.enemies
+				.iter_mut()
+				.filter(|enemy| matches!(enemy.state, EnemyState::OnScreen))
+			{
+				enemy.get_shot(BOMB_DAMAGE);
+			}
+			self.shakes.push(BOMB_SHAKE);
+			self.sounds.push(SoundBase::Explosion);
+			self.particles.emit(player.body.pos, particles::EXPLOSION);
+		}
+		if player.bomb > 0 {
+			self
+				.projectiles
+				.retain(|proj| proj.variant.is_player_shot());
+		}
+	}
 
-			for enemy in world.enemies.iter_mut() {
-				if matches!(proj.variant, ProjType::PlayerShoot)
-					& collide_rectangle(enemy.pos, enemy.size, proj.pos, PROJ_SIZE)
-				{
-					enemy.get_shot(proj.damage());
-					return false;
+	/// Burns, then the path or the movement of their type
+	fn steer_enemies(&mut self, dt: f32) {
+		let transform = self.transform();
+		let target = self.player.body.pos;
+		for enemy in self.enemies.iter_mut() {
+			let burn = enemy.status.tick();
+			if burn > 0. {
+				enemy.get_shot(burn);
+			}
+			enemy.steer(self.boundaries, target, transform, dt);
+		}
+	}
+
+	/// Missiles homing, lasers counting down and wells bending the trajectories
+	fn steer_projectiles(&mut self, dt: f32) {
+		let dt_ticks = dt / DT_60;
+		let enemies = &self.enemies;
+		let wells = &self.wells;
+		self.projectiles.retain_mut(|proj| {
+			match &mut proj.variant {
+				ProjType::Homing => proj.home(enemies, dt_ticks),
+				// Lasers stay where they were fired, they only count down
+				ProjType::Laser { warning, active } => {
+					if *warning > 0 {
+						*warning -= 1;
+					} else if *active > 0 {
+						*active -= 1;
+					} else {
+						return false;
+					}
+					return true;
+				},
+				_ => {},
+			}
+			// Force pass, the wells curve the trajectory before it is followed
+			for active in wells.iter() {
+				proj.body.vel += active.well.pull(proj.body.pos, dt_ticks);
+			}
+			true
+		});
+	}
+
+	/// Keeps the player in the playfield, and follows the enemies and projectiles leaving it
+	fn confine(&mut self) {
+		self.player.confine(self.boundaries);
+		for enemy in self.enemies.iter_mut() {
+			enemy.confine(self.boundaries);
+		}
+		// Projectiles can come from outside the playfield and fly in
+		let despawn_bounds = self.boundaries.grow(self.despawn_margin);
+		self.projectiles.retain(|proj| {
+			matches!(proj.variant, ProjType::Laser { .. }) || despawn_bounds.contains(proj.body.pos)
+		});
+	}
+
+	/// Collision system of the world: the player shots against the enemies, the enemy bullets and
+	/// bodies against the player
+	fn contacts(&self) -> Vec<Contact> {
+		let player = &self.player;
+		let graze_dims = player.hit_dims() + 2. * GRAZE_MARGIN;
+		let mut contacts = vec![];
+		for (index, proj) in self.projectiles.indexed() {
+			if proj.variant.is_player_shot() {
+				if let Some(enemy) = systems::first_contact(proj, self.enemies.iter().enumerate()) {
+					contacts.push(Contact::Shot { proj: index, enemy });
 				}
+			// The barrier catches the bullets before they reach the hitbox
+			} else if player.barrier > 0 && proj.touches(player.body.pos, player.size) {
+				contacts.push(Contact::Barrier(index));
+			} else if proj.touches(player.body.pos, player.hit_dims()) {
+				contacts.push(Contact::Bullet(index));
+			} else if !proj.grazed && proj.touches(player.body.pos, graze_dims) {
+				contacts.push(Contact::Graze(index));
 			}
+		}
+		let on_screen = self
+			.enemies
+			.iter()
+			.enumerate()
+			.filter(|(_, enemy)| matches!(enemy.state, EnemyState::OnScreen));
+		if let Some(enemy) = systems::first_contact(player, on_screen) {
+			contacts.push(Contact::Ram(enemy));
+		}
+		contacts
+	}
 
-			if player.immunity.is_over()
-				& !matches!(proj.variant, ProjType::PlayerShoot)
-				& collide_rectangle(player.pos, player.hitbox.dims, proj.pos, PROJ_SIZE)
-			{
-				if player.hp > 0 {
-					// Avoids underflow if damage is more than 1
-					player.hp = player.hp.saturating_sub(proj.damage() as u32)
+	/// Damage, hits and grazes of the contacts, in the order they were found. The player is only
+	/// hit once per tick
+	fn resolve(&mut self, contacts: Vec<Contact>) {
+		let now = self.clock.now();
+		let mut player_hit = None;
+		for contact in contacts {
+			let can_be_hit = player_hit.is_none() && self.player.can_be_hit(now);
+			match contact {
+				Contact::Shot { proj, enemy } => self.shot_hit(proj, enemy),
+				Contact::Barrier(index) => {
+					// The lasers are only blocked as they can't be sent back
+					let damage = self.player.shot_damage();
+					if let Some(proj) = self
+						.projectiles
+						.get_mut(index)
+						.filter(|proj| proj.laser_end().is_none())
+					{
+						proj.reflect(damage);
+					}
+				},
+				Contact::Bullet(index) if can_be_hit => {
+					let Some(proj) = self.projectiles.get(index) else {
+						continue;
+					};
+					player_hit = Some(proj.damage as u32);
+					// The beam goes on, the immunity keeps it from hitting again
+					if proj.laser_end().is_none() {
+						self.projectiles.remove(index);
+					}
+				},
+				// Only grazed while the player can't be hit
+				Contact::Bullet(index) | Contact::Graze(index) => self.graze(index),
+				// Enemy bodies reaching the hitbox hurt too, the ones exploding on contact are
+				// used up
+				Contact::Ram(index) if can_be_hit => {
+					player_hit = Some(1);
+					let enemy = &mut self.enemies[index];
+					if enemy.variant.def().explodes_on_contact {
+						enemy.state = EnemyState::Exploded;
+					}
+				},
+				Contact::Ram(_) => {},
+			}
+		}
+		if let Some(damage) = player_hit {
+			self.hit_player(damage);
+		}
+	}
+
+	/// Damage of the player shot at `index` to the enemy at `enemy`, with its weak point, armor and
+	/// resistances. The shot is used up
+	fn shot_hit(&mut self, index: usize, enemy: usize) {
+		let Some(proj) = self.projectiles.remove(index) else {
+			return;
+		};
+		let transform = self.transform();
+		let enemy = &mut self.enemies[enemy];
+		let weak_hit = enemy
+			.weak_point(transform)
+			.filter(|&(pos, size, _)| collide_rectangle(pos, size, proj.body.pos, proj.hit_dims()));
+		let def = enemy.variant.def();
+		let front_armor = def
+			.front_armor
+			.filter(|_| HitSide::of_shot(proj.body.vel) == HitSide::Front);
+		let multiplier = weak_hit.map_or(1., |(_, _, multiplier)| multiplier)
+			* front_armor.unwrap_or(1.)
+			* def.resistances.factor(proj.damage_type);
+		let damage = proj.damage * multiplier * self.modifiers.damage_factor();
+		enemy.get_shot(damage);
+		if let Some(status) = proj.status {
+			enemy.status.apply(status);
+		}
+		let kind = match (weak_hit, front_armor) {
+			(Some(_), _) => HitKind::Critical,
+			(None, Some(_)) => HitKind::Armored,
+			(None, None) => HitKind::Normal,
+		};
+		self
+			.hit_sparks
+			.push(HitSpark { pos: proj.body.pos, kind, ticks_left: HIT_SPARK_TICKS });
+		// Whole numbers unless the multipliers left a fraction
+		let text = if damage.fract() == 0. {
+			format!("{damage:.0}")
+		} else {
+			format!("{damage:.1}")
+		};
+		self
+			.popups
+			.push(Popup::new(proj.body.pos, text, PopupKind::Damage(kind)));
+		self.log.push(LogEntry::new(
+			self.tick,
+			LogKind::Hit,
+			enemy.id,
+			proj.body.pos,
+		));
+		self.stats.hits += 1;
+		self.sounds.push(SoundBase::EnemyHit);
+		self.particles.emit(proj.body.pos, particles::IMPACT);
+	}
+
+	/// Enemy bullets brushing past the hitbox fill the graze meter, once each
+	fn graze(&mut self, index: usize) {
+		let Some(proj) = self.projectiles.get_mut(index).filter(|proj| !proj.grazed) else {
+			return;
+		};
+		proj.grazed = true;
+		self.player.add_graze();
+		self.stats.grazes += 1;
+		self.score += GRAZE_SCORE * self.player.score_factor();
+	}
+
+	/// Pays out the kills and removes the enemies killed, blown up or gone off screen, returning
+	/// the waves they were part of as they may be cleared
+	fn remove_gone_enemies(&mut self) -> Vec<u32> {
+		let bullet_speed = self.enemy_bullet_speed() * self.rank_factor();
+		let revenge_bullets = self
+			.second_loop
+			.as_ref()
+			.is_some_and(|second_loop| second_loop.revenge_bullets);
+		let player = &self.player;
+		let mut gone_waves = vec![];
+		self.enemies.retain_mut(|enemy| {
+			// If the enemy is dead, add points
+			if matches!(enemy.state, EnemyState::Dead) {
+				roll_drops(
+					&enemy.variant.def().drops,
+					enemy.body.pos,
+					self.drop_rate,
+					&mut self.rng,
+					&mut self.items,
+				);
+				let death_shot = (enemy.variant.def().death_shot)(enemy.body.pos, bullet_speed);
+				self.projectiles.extend(death_shot);
+				if revenge_bullets {
+					let to_player = player.body.pos - enemy.body.pos;
+					if to_player != Vector2::zero() {
+						let vel = to_player.normalize() * bullet_speed;
+						self
+							.projectiles
+							.push(Projectile::new(enemy.body.pos, vel, ProjType::Aimed));
+					}
 				}
-				if player.hp == 0 {
-					return false;
+				let score = 100 * self.combo.kill(self.tick) * player.score_factor();
+				self.score += score;
+				self.popups.push(Popup::new(
+					enemy.body.pos,
+					format!("+{score}"),
+					PopupKind::Score,
+				));
+				self.stats.kill(&enemy.variant.def().name);
+				self.rank = (self.rank + RANK_PER_KILL).min(1.);
+				self.boss_defeated |= enemy.boss.is_some();
+				if enemy.boss.is_some() {
+					self.shakes.push(BOSS_DEATH_SHAKE);
 				}
-
-				player.immunity.reset();
+				self.sounds.push(SoundBase::Explosion);
+				self.particles.emit(enemy.body.pos, particles::EXPLOSION);
+				self
+					.explosions
+					.push(Explosion { pos: enemy.body.pos, size: enemy.size, ticks: 0 });
+				self.log.push(LogEntry::new(
+					self.tick,
+					LogKind::Kill,
+					enemy.id,
+					enemy.body.pos,
+				));
+				gone_waves.extend(enemy.wave);
+				return false;
+			}
+			// Rammed the player, nothing is earned
+			if matches!(enemy.state, EnemyState::Exploded) {
+				self.sounds.push(SoundBase::Explosion);
+				self.particles.emit(enemy.body.pos, particles::EXPLOSION);
+				self
+					.explosions
+					.push(Explosion { pos: enemy.body.pos, size: enemy.size, ticks: 0 });
+				gone_waves.extend(enemy.wave);
+				return false;
+			}
+			// Removes if offscreen
+			if matches!(enemy.state, EnemyState::OffScreen) {
+				gone_waves.extend(enemy.wave);
 				return false;
 			}
 			true
 		});
+		gone_waves
+	}
+
+	/// Shots of the player and the enemies, and the enemies let out by the carriers
+	fn fire(&mut self, inputs: &Inputs, dt: f32) {
+		let rank_factor = self.rank_factor();
+		let aim = Aim {
+			inputs,
+			now: self.clock.now(),
+			dt,
+			target: self.player.body.pos,
+			bullet_speed: self.enemy_bullet_speed() * rank_factor,
+			rank_factor,
+			transform: self.transform(),
+			bounds: self.boundaries,
+			hold_fire: self.tick < self.safe_until,
+		};
+		let shooters = iter::once(&mut self.player as &mut dyn Shooter).chain(
+			self
+				.enemies
+				.iter_mut()
+				.map(|enemy| enemy as &mut dyn Shooter),
+		);
+		let volley = systems::shooting(shooters, &aim);
+		if volley.player_shots > 0 {
+			self.sounds.push(SoundBase::PlayerShoot);
+		}
+		self.stats.shots += volley.player_shots;
+		self.projectiles.extend(volley.shots);
+		for (pos, minion) in volley.released {
+			self.spawn_enemy(pos, minion);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		enemies,
+		game::{LEVEL_REF, WORLD_SIZE},
+	};
+
+	/// Turret, staying where it spawns, `secs` after its reference
+	fn spawn(id: u32, ref_evt: u32, secs: f32, condition: EventCondition) -> Event {
+		let turret = enemies::find("turret").unwrap();
+		Event {
+			id,
+			time: None,
+			ref_evt: Some((ref_evt, Duration::from_secs_f32(secs))),
+			variant: EventType::_SpawnEnemy(SpawnPos::Absolute((400., 300.).into()), turret, None),
+			repeat: None,
+			condition,
+		}
+	}
+
+	fn start(events: Vec<Event>) -> World {
+		let mut world = World::start(WORLD_SIZE, events, &Rules::default(), 0);
+		// The turrets fire at the player
+		world.invincible = true;
+		world
+	}
+
+	fn run(world: &mut World, ticks: u32) {
+		for _ in 0..ticks {
+			world.step(&Inputs::default(), TICK);
+		}
+	}
+
+	fn spawned(world: &World) -> u32 {
+		world.next_id - PLAYER_ID - 1
+	}
+
+	#[test]
+	fn repeated_events_come_back_after_their_interval() {
+		let event = Event {
+			repeat: Some((2, Duration::from_millis(500))),
+			..spawn(0, LEVEL_REF, 1., EventCondition::Triggered)
+		};
+		let mut world = start(vec![event]);
+		run(&mut world, 50);
+		assert_eq!(spawned(&world), 0);
+		run(&mut world, 20);
+		assert_eq!(spawned(&world), 1);
+		run(&mut world, 30);
+		assert_eq!(spawned(&world), 2);
+		run(&mut world, 30);
+		assert_eq!(spawned(&world), 3);
+		// Two more times, then it is done
+		run(&mut world, 120);
+		assert_eq!(spawned(&world), 3);
+		assert!(!world.event_syst.is_pending(0));
+	}
+
+	#[test]
+	fn field_clear_waits_for_no_enemy_left() {
+		let mut world = start(vec![
+			spawn(0, LEVEL_REF, 0., EventCondition::Triggered),
+			spawn(1, LEVEL_REF, 0.5, EventCondition::FieldClear),
+		]);
+		run(&mut world, 60);
+		assert_eq!(spawned(&world), 1);
+		assert!(world.event_syst.is_pending(1));
+		world.enemies.clear();
+		// Counted from the moment the field is clear
+		run(&mut world, 20);
+		assert_eq!(spawned(&world), 1);
+		run(&mut world, 20);
+		assert_eq!(spawned(&world), 2);
+	}
+
+	#[test]
+	fn wave_cleared_only_waits_for_its_wave() {
+		let mut world = start(vec![
+			spawn(0, LEVEL_REF, 0., EventCondition::Triggered),
+			spawn(1, LEVEL_REF, 0., EventCondition::Triggered),
+			spawn(2, 0, 0., EventCondition::WaveCleared),
+		]);
+		run(&mut world, 30);
+		assert_eq!(spawned(&world), 2);
+		assert!(world.event_syst.is_pending(2));
+		// Leaving the playfield clears the wave like a kill, the other wave is still there
+		for enemy in world
+			.enemies
+			.iter_mut()
+			.filter(|enemy| enemy.wave == Some(0))
+		{
+			enemy.state = EnemyState::OffScreen;
+		}
+		// Removed, then the event is timed from it and triggered
+		run(&mut world, 3);
+		assert_eq!(spawned(&world), 3);
+		assert!(world.enemies.iter().any(|enemy| enemy.wave == Some(1)));
+	}
+
+	#[test]
+	fn skip_to_starts_at_the_event() {
+		let events = vec![
+			spawn(0, LEVEL_REF, 1., EventCondition::Triggered),
+			spawn(1, LEVEL_REF, 5., EventCondition::Triggered),
+			spawn(2, 1, 1., EventCondition::Triggered),
+			spawn(3, 0, 0., EventCondition::WaveCleared),
+		];
+		let mut world = start(events);
+		assert!(!world.skip_to_event(9));
+		assert!(world.event_syst.is_pending(0));
+
+		assert!(world.skip_to_event(1));
+		assert!(!world.event_syst.is_pending(0));
+		// Right away, and the skipped wave counts as cleared
+		run(&mut world, 2);
+		assert!(!world.event_syst.is_pending(1));
+		assert!(!world.event_syst.is_pending(3));
+		assert!(world.event_syst.is_pending(2));
+		run(&mut world, 60);
+		assert!(!world.event_syst.is_pending(2));
+		assert_eq!(spawned(&world), 3);
+	}
+
+	#[test]
+	fn chain_is_banked_once_its_window_is_over() {
+		let mut chain = Chain::default();
+		for tick in [0, 30, 60] {
+			chain.pick(tick);
+		}
+		assert_eq!(chain.bank(60 + CHAIN_WINDOW_TICKS - 1), 0);
+		assert_eq!(chain.bank(60 + CHAIN_WINDOW_TICKS), CHAIN_BONUS * 9);
+		assert_eq!(chain.count, 0);
+		// A single item gives nothing
+		chain.pick(500);
+		assert_eq!(chain.bank(500 + CHAIN_WINDOW_TICKS), 0);
+		assert_eq!(chain.count, 0);
+	}
+
+	#[test]
+	fn combo_rises_with_kills_and_decays_without() {
+		let mut combo = Combo::default();
+		let multipliers: Vec<_> = [0, 10, 20]
+			.into_iter()
+			.map(|tick| combo.kill(tick))
+			.collect();
+		assert_eq!(multipliers, [1, 2, 3]);
+		assert_eq!(combo.multiplier, 4);
+		combo.decay(20 + COMBO_WINDOW_TICKS - 1);
+		assert_eq!(combo.multiplier, 4);
+		// One step per window without a kill
+		combo.decay(20 + COMBO_WINDOW_TICKS);
+		combo.decay(20 + COMBO_WINDOW_TICKS + 1);
+		assert_eq!(combo.multiplier, 3);
+		combo.decay(20 + 2 * COMBO_WINDOW_TICKS);
+		assert_eq!(combo.multiplier, 2);
+		for tick in 0..2 * MAX_COMBO {
+			combo.kill(1000 + tick);
+		}
+		assert_eq!(combo.multiplier, MAX_COMBO);
+	}
+
+	#[test]
+	fn kills_score_with_the_combo() {
+		let mut world = start(vec![]);
+		for (i, x) in [300., 500.].into_iter().enumerate() {
+			world.spawn_enemy((x, 300.).into(), EnemyType::BASIC);
+			world.enemies[i].state = EnemyState::Dead;
+		}
+		run(&mut world, 1);
+		assert_eq!(world.score, 100 + 2 * 100);
+		assert_eq!(world.combo.multiplier, 3);
 	}
 }
@@ -0,0 +1,168 @@
+use cgmath::Vector2;
+use image::RgbaImage;
+use std::{fs, path::Path};
+
+use crate::{
+	cli::GoldenMode,
+	draw::{draw_world, FrameBuffer, RenderState, Sheets, BG_DIM_FADE_SECS, DRAW_CONSTANTS},
+	enemies::EnemyType,
+	game::{Config, GameInfo, WORLD_SIZE},
+	gameplay::{ProjType, Projectile, World},
+	rules::Rules,
+};
+
+const GOLDEN_DIR: &str = "./tests/golden";
+/// Biggest difference on a color channel for two pixels to be considered the same
+const CHANNEL_TOLERANCE: u8 = 2;
+/// Fraction of pixels that can differ before a render is considered a regression
+const PIXEL_TOLERANCE: f32 = 0.001;
+
+/// A deterministic world state and its reference image name
+struct Fixture {
+	name: &'static str,
+	world: fn() -> World,
+}
+
+fn fixtures() -> Vec<Fixture> {
+	vec![
+		Fixture {
+			name: "empty",
+			world: || World::start(WORLD_SIZE, vec![], &Rules::default(), 0),
+		},
+		Fixture {
+			name: "enemies",
+			world: || {
+				let mut world = World::start(WORLD_SIZE, vec![], &Rules::default(), 0);
+				world.spawn_enemy((300., 200.).into(), EnemyType::BASIC);
+				world.spawn_enemy((600., 150.).into(), EnemyType::SNIPER);
+				// Half a life bar
+				world.enemies[1].hp /= 2.;
+				world
+			},
+		},
+		Fixture {
+			name: "projectiles",
+			world: || {
+				let mut world = World::start(WORLD_SIZE, vec![], &Rules::default(), 0);
+				for (i, variant) in [ProjType::Basic, ProjType::Aimed, ProjType::PlayerShoot]
+					.into_iter()
+					.enumerate()
+				{
+					let pos = (200. + 100. * i as f32, 400.).into();
+					world
+						.projectiles
+						.push(Projectile::new(pos, Vector2::unit_y(), variant));
+				}
+				world
+			},
+		},
+		Fixture {
+			name: "boss_dim",
+			world: || {
+				let mut world = World::start(WORLD_SIZE, vec![], &Rules::default(), 0);
+				world.boss_active = true;
+				world
+			},
+		},
+	]
+}
+
+/// Renders the fixture at the base resolution
+fn render(fixture: &Fixture, sheets: &Sheets) -> RgbaImage {
+	let world = (fixture.world)();
+	let dims = DRAW_CONSTANTS.sizes[0];
+	let mut frame_buffer = FrameBuffer::offscreen(dims);
+	let mut render_state = RenderState::new();
+	// Long enough for any fade to be over
+	render_state.update(&world, BG_DIM_FADE_SECS);
+	draw_world(
+		&mut frame_buffer,
+		sheets,
+		&world,
+		&render_state,
+		&Config::new(),
+		&GameInfo::new(),
+		fixture.name,
+	);
+	RgbaImage::from_raw(dims.w, dims.h, frame_buffer.frame().to_vec()).unwrap()
+}
+
+fn compare(render: &RgbaImage, reference: &RgbaImage) -> Result<(), String> {
+	if render.dimensions() != reference.dimensions() {
+		return Err(format!(
+			"size {:?} doesn't match the reference {:?}",
+			render.dimensions(),
+			reference.dimensions()
+		));
+	}
+	let differing = render
+		.as_raw()
+		.chunks_exact(4)
+		.zip(reference.as_raw().chunks_exact(4))
+		.filter(|(px, ref_px)| {
+			px.iter()
+				.zip(ref_px.iter())
+				.any(|(c, ref_c)| c.abs_diff(*ref_c) > CHANNEL_TOLERANCE)
+		})
+		.count();
+	let (w, h) = render.dimensions();
+	let ratio = differing as f32 / (w * h) as f32;
+	if ratio > PIXEL_TOLERANCE {
+		return Err(format!("{differing} pixels differ ({:.2}%)", 100. * ratio));
+	}
+	Ok(())
+}
+
+/// Runs the harness on all the fixtures, returns the exit code of the process
+pub fn run(mode: GoldenMode) -> i32 {
+	let sheets = match Sheets::load() {
+		Ok(sheets) => sheets,
+		Err(err) => {
+			println!("Error: {err}");
+			return 1;
+		},
+	};
+	let golden_dir = Path::new(GOLDEN_DIR);
+	let mut failures = 0;
+	for fixture in fixtures() {
+		let render = render(&fixture, &sheets);
+		let path = golden_dir.join(format!("{}.png", fixture.name));
+		match mode {
+			GoldenMode::Update => {
+				fs::create_dir_all(golden_dir).unwrap();
+				render.save(&path).unwrap();
+				println!("updated {}", path.display());
+			},
+			GoldenMode::Check => {
+				let result = match image::open(&path) {
+					Ok(reference) => compare(&render, &reference.to_rgba8()),
+					Err(err) => Err(format!("can't open reference: {err}")),
+				};
+				match result {
+					Ok(()) => println!("ok      {}", fixture.name),
+					Err(msg) => {
+						failures += 1;
+						println!("FAILED  {}: {msg}", fixture.name);
+						// Kept next to the reference to compare them by hand
+						render.save(path.with_extension("actual.png")).unwrap();
+					},
+				}
+			},
+		}
+	}
+	if failures > 0 {
+		println!("{failures} golden image(s) don't match");
+		return 1;
+	}
+	0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn renders_match_the_references() {
+		assert_eq!(run(GoldenMode::Check), 0);
+	}
+}
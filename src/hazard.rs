@@ -0,0 +1,78 @@
+use cgmath::{InnerSpace, Point2, Vector2, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::coords::RectF;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum HazardKind {
+	/// Pushes sideways by this many pixels per 60 Hz tick, to the right when positive
+	Wind(f32),
+	/// Multiplies the speed of what moves through it
+	Slow(f32),
+}
+
+/// Zone of the playfield changing how the player and the bullets move, placed by a level event
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Hazard {
+	pub kind: HazardKind,
+	pub zone: RectF,
+	/// Ticks it stays active after its event
+	pub duration: u64,
+}
+
+/// Hazard whose event was triggered
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActiveHazard {
+	pub hazard: Hazard,
+	/// Tick at which it stops
+	pub until: u64,
+}
+
+/// Speed factor and push of the hazards covering `pos`, they add up when zones overlap
+pub fn effect_at(hazards: &[ActiveHazard], pos: Point2<f32>) -> (f32, Vector2<f32>) {
+	let mut speed_factor = 1.;
+	let mut push = Vector2::zero();
+	for active in hazards
+		.iter()
+		.filter(|active| active.hazard.zone.contains(pos))
+	{
+		match active.hazard.kind {
+			HazardKind::Wind(force) => push += force * Vector2::unit_x(),
+			HazardKind::Slow(factor) => speed_factor *= factor,
+		}
+	}
+	(speed_factor, push)
+}
+
+/// Obstacle bending the trajectory of every projectile around it toward its center
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GravityWell {
+	pub center: Point2<f32>,
+	/// Distance from the center within which projectiles are pulled
+	pub radius: f32,
+	/// Speed added toward the center every 60 Hz tick
+	pub strength: f32,
+	/// Ticks it stays after its event
+	pub duration: u64,
+}
+
+impl GravityWell {
+	/// Velocity change of a projectile at `pos` over `dt_ticks` 60 Hz ticks
+	pub fn pull(&self, pos: Point2<f32>, dt_ticks: f32) -> Vector2<f32> {
+		let to_center = self.center - pos;
+		let distance = to_center.magnitude();
+		// Nothing to pull toward once on the center
+		if distance > self.radius || distance < f32::EPSILON {
+			return Vector2::zero();
+		}
+		to_center / distance * self.strength * dt_ticks
+	}
+}
+
+/// Gravity well whose event was triggered
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActiveWell {
+	pub well: GravityWell,
+	/// Tick at which it disappears
+	pub until: u64,
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use crate::game::Inputs;
+
+/// Lines of the history shown under the input display
+pub const HISTORY_LEN: usize = 4;
+/// Keeps the lines short enough for the panel
+const MAX_HELD_FRAMES: u32 = 999;
+
+/// Last combinations of inputs of the level, each with the number of frames it was held, the most
+/// recent at the front
+#[derive(Clone, Debug, Default)]
+pub struct InputHistory {
+	entries: VecDeque<(Inputs, u32)>,
+}
+
+impl InputHistory {
+	/// Called every frame the level is updated, with the inputs it used
+	pub fn record(&mut self, inputs: &Inputs) {
+		if let Some((last, held)) = self.entries.front_mut() {
+			if last == inputs {
+				*held = (*held + 1).min(MAX_HELD_FRAMES);
+				return;
+			}
+		}
+		self.entries.push_front((inputs.clone(), 1));
+		self.entries.truncate(HISTORY_LEN);
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.clear();
+	}
+
+	/// Lines like `12 UL SHOT`, the most recent first
+	pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+		self.entries.iter().map(|(inputs, held)| {
+			format!(
+				"{held:3} {:2} {}",
+				directions_label(inputs),
+				buttons_label(inputs)
+			)
+			.trim_end()
+			.to_string()
+		})
+	}
+}
+
+/// Held directions by their first letter, a dash when there is none
+fn directions_label(inputs: &Inputs) -> String {
+	let label: String = [
+		(inputs.up, 'U'),
+		(inputs.down, 'D'),
+		(inputs.left, 'L'),
+		(inputs.right, 'R'),
+	]
+	.into_iter()
+	.filter_map(|(held, c)| held.then_some(c))
+	.collect();
+	if label.is_empty() {
+		"-".into()
+	} else {
+		label
+	}
+}
+
+fn buttons_label(inputs: &Inputs) -> String {
+	[
+		(inputs.shoot, "SHOT"),
+		(inputs.dash, "DASH"),
+		(inputs.hyper, "HYPER"),
+		(inputs.focus, "FOCUS"),
+		(inputs.bomb, "BOMB"),
+	]
+	.into_iter()
+	.filter_map(|(held, name)| held.then_some(name))
+	.collect::<Vec<_>>()
+	.join(" ")
+}
+
+/// Cell of the 3x3 direction pad lit by the inputs, as (column, row). Opposite directions cancel
+/// out like they do for the movement
+pub fn pad_cell(inputs: &Inputs) -> (i32, i32) {
+	let axis = |negative: bool, positive: bool| 1 + positive as i32 - negative as i32;
+	(
+		axis(inputs.left, inputs.right),
+		axis(inputs.up, inputs.down),
+	)
+}
@@ -1,12 +1,70 @@
+mod assets;
+mod background;
+mod body;
+mod cli;
 mod coords;
+mod daily;
 mod draw;
+mod ending;
+mod endless;
+mod enemies;
+mod error;
+mod flight_path;
+mod formation;
+mod frame_graph;
 mod game;
+mod game_over;
 mod gameloop;
+mod gamepad;
 mod gameplay;
+mod golden;
+mod hazard;
+mod input_display;
+mod mods;
+mod particles;
+mod paths;
+mod pause;
+mod pool;
+mod practice;
+mod presence;
+mod progress;
+mod replay;
+mod rng;
+mod rules;
+mod session_log;
+mod settings;
+mod snapshot;
 mod sound;
+mod sprites;
+mod stats;
+mod status;
+mod systems;
+mod timing;
+mod title;
 
-use crate::gameloop::game_run;
+use crate::{
+	cli::{Args, USAGE},
+	gameloop::game_run,
+};
 
 fn main() {
-	game_run().unwrap();
+	let args = match Args::parse() {
+		Ok(args) => args,
+		Err(err) => {
+			println!("Error: {err}");
+			println!("{USAGE}");
+			std::process::exit(2);
+		},
+	};
+	if let Some(mode) = args.golden {
+		std::process::exit(golden::run(mode));
+	}
+	if let Err(err) = paths::create_all() {
+		println!("Error: {err}");
+		std::process::exit(1);
+	}
+	if let Err(err) = game_run(args) {
+		println!("Error: {err}");
+		std::process::exit(1);
+	}
 }
@@ -0,0 +1,198 @@
+use image::{DynamicImage, GenericImageView};
+use serde::Deserialize;
+use std::{fs, path::PathBuf};
+
+use crate::{
+	enemies::{self, EnemyDef, ItemDrop, Resistances, WeakPoint},
+	game::{Game, Level},
+};
+
+const MOD_DIR: &str = "./mods";
+const MANIFEST_FILE: &str = "mod.toml";
+
+/// `mod.toml`, a directory of the mods directory without one isn't a mod
+#[derive(Deserialize)]
+struct Manifest {
+	name: String,
+	/// Mods are loaded by increasing order, the last ones override the files of the first ones
+	#[serde(default)]
+	order: i32,
+}
+
+/// Enemy type of `enemies.toml`, behaving like an existing one with its own stats
+#[derive(Deserialize)]
+struct EnemyEntry {
+	name: String,
+	/// Name of the enemy type whose movement and shots are used
+	behavior: String,
+	hp: f32,
+	size: (f32, f32),
+	/// Seconds between two shots
+	fire_cooldown: f32,
+	/// Position in the sprite sheet, in 8 pixels tiles
+	sprite: (u32, u32),
+	#[serde(default)]
+	drop: Vec<ItemDrop>,
+	weak_point: Option<WeakPoint>,
+	front_armor: Option<f32>,
+	#[serde(default)]
+	resistances: Resistances,
+}
+
+#[derive(Deserialize)]
+struct EnemyFile {
+	enemy: Vec<EnemyEntry>,
+}
+
+/// Mod directory, containing a manifest and optionally:
+/// - `enemies.toml` registering new enemy types, usable in the levels
+/// - `levels/*.hbh` and `levels/*.toml` added to the level selection
+/// - `font.png` and `spritesheet.png` replacing the base ones
+struct ModPackage {
+	name: String,
+	order: i32,
+	dir: PathBuf,
+}
+
+impl ModPackage {
+	fn read(dir: PathBuf) -> Result<ModPackage, String> {
+		let raw = fs::read_to_string(dir.join(MANIFEST_FILE))
+			.map_err(|err| format!("Can't read '{MANIFEST_FILE}': {err}"))?;
+		let manifest: Manifest =
+			toml::from_str(&raw).map_err(|err| format!("Invalid '{MANIFEST_FILE}': {err}"))?;
+		Ok(ModPackage { name: manifest.name, order: manifest.order, dir })
+	}
+
+	/// Registers the enemy types of the mod, stops at the first invalid one
+	fn register_enemies(&self) -> Result<(), String> {
+		let path = self.dir.join("enemies.toml");
+		if !path.exists() {
+			return Ok(());
+		}
+		let raw = fs::read_to_string(path).map_err(|err| err.to_string())?;
+		let file: EnemyFile =
+			toml::from_str(&raw).map_err(|err| format!("Invalid 'enemies.toml': {err}"))?;
+		for entry in file.enemy {
+			let base = enemies::find(&entry.behavior)
+				.ok_or(format!(
+					"Unknown behavior '{}' for '{}'",
+					entry.behavior, entry.name
+				))?
+				.def();
+			enemies::register(EnemyDef {
+				name: entry.name,
+				max_hp: entry.hp,
+				size: entry.size.into(),
+				fire_cooldown: entry.fire_cooldown,
+				sprite: entry.sprite,
+				movement: base.movement,
+				shot: base.shot,
+				turn_rate: base.turn_rate,
+				death_shot: base.death_shot,
+				explodes_on_contact: base.explodes_on_contact,
+				releases: base.releases,
+				drops: entry.drop,
+				weak_point: entry.weak_point,
+				front_armor: entry.front_armor,
+				resistances: entry.resistances,
+			})?;
+		}
+		Ok(())
+	}
+
+	fn level_files(&self) -> Vec<PathBuf> {
+		let Ok(entries) = fs::read_dir(self.dir.join("levels")) else {
+			return vec![];
+		};
+		let mut files: Vec<PathBuf> = entries
+			.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+			.filter(|path| {
+				path.is_file()
+					&& path
+						.extension()
+						.is_some_and(|ext| ext == "hbh" || ext == "toml")
+			})
+			.collect();
+		files.sort();
+		files
+	}
+
+	/// Image replacing `base`, it must have the same size to keep the sprite positions
+	fn sheet(&self, file: &str, base: &DynamicImage) -> Result<Option<DynamicImage>, String> {
+		let path = self.dir.join(file);
+		if !path.exists() {
+			return Ok(None);
+		}
+		let image = image::open(&path).map_err(|err| format!("Can't open '{file}': {err}"))?;
+		if image.dimensions() != base.dimensions() {
+			return Err(format!(
+				"'{file}' is {:?} but must be {:?}",
+				image.dimensions(),
+				base.dimensions()
+			));
+		}
+		Ok(Some(image))
+	}
+}
+
+/// Mods in load order, and the errors of the ones that couldn't be read
+fn scan() -> (Vec<ModPackage>, Vec<String>) {
+	let mut mods = vec![];
+	let mut errors = vec![];
+	let Ok(entries) = fs::read_dir(MOD_DIR) else {
+		return (mods, errors);
+	};
+	for dir in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+		if !dir.is_dir() {
+			continue;
+		}
+		match ModPackage::read(dir.clone()) {
+			Ok(package) => mods.push(package),
+			Err(err) => errors.push(format!("{}: {err}", dir.display())),
+		}
+	}
+	// Ties are broken by directory name to have the same order on every system
+	mods.sort_by(|a, b| (a.order, &a.dir).cmp(&(b.order, &b.dir)));
+	(mods, errors)
+}
+
+impl Game {
+	/// Adds the content of the mods after the base game, the errors are kept to be shown on the menu
+	pub fn load_mods(&mut self) {
+		let (mods, mut errors) = scan();
+		for package in mods.iter() {
+			let mod_error = |err: String| format!("Mod '{}': {err}", package.name);
+			// Before the levels that use them
+			if let Err(err) = package.register_enemies() {
+				errors.push(mod_error(err));
+			}
+			for path in package.level_files() {
+				let parsed = fs::read_to_string(&path)
+					.map_err(|err| err.to_string())
+					.and_then(|raw| {
+						let name = path.to_string_lossy();
+						Level::parse_file(self.levels.len() as u32, &name, &raw)
+					});
+				match parsed {
+					Ok(level) => self.levels.push(level),
+					Err(err) => errors.push(mod_error(format!("{}: {err}", path.display()))),
+				}
+			}
+			match package.sheet("font.png", &self.sheets.font) {
+				Ok(Some(font)) => self.sheets.font = font,
+				Ok(None) => {},
+				Err(err) => errors.push(mod_error(err)),
+			}
+			match package.sheet("spritesheet.png", &self.sheets.spritesheet) {
+				Ok(Some(spritesheet)) => self.sheets.spritesheet = spritesheet,
+				Ok(None) => {},
+				Err(err) => errors.push(mod_error(err)),
+			}
+			println!("Mod '{}' loaded", package.name);
+		}
+		for err in errors.iter() {
+			println!("{err}");
+		}
+		self.mod_errors = errors;
+	}
+}
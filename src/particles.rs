@@ -0,0 +1,108 @@
+use cgmath::{Point2, Vector2};
+use std::f32::consts::TAU;
+
+use crate::rng::Rng;
+
+/// Particles alive at once, the bursts emitted past it are cut short
+const MAX_PARTICLES: usize = 600;
+/// Share of the speed kept from one tick to the next
+const DRAG: f32 = 0.92;
+
+pub const EXPLOSION_COLOR: [u8; 4] = [0xff, 0x8c, 0x28, 0xff];
+pub const PLAYER_HIT_COLOR: [u8; 4] = [0xff, 0x3c, 0x64, 0xff];
+pub const IMPACT_COLOR: [u8; 4] = [0xff, 0xf0, 0xb4, 0xff];
+
+/// Burst of particles flying out of a point in every direction
+#[derive(Clone, Copy, Debug)]
+pub struct Burst {
+	pub count: u32,
+	/// Fastest particle, in pixels per 60 Hz tick
+	pub speed: f32,
+	/// Longest life, in ticks
+	pub lifetime: u32,
+	pub color: [u8; 4],
+}
+
+/// Killed enemy
+pub const EXPLOSION: Burst = Burst { count: 24, speed: 4., lifetime: 40, color: EXPLOSION_COLOR };
+/// Hit taken by the player
+pub const PLAYER_HIT: Burst = Burst { count: 32, speed: 5., lifetime: 50, color: PLAYER_HIT_COLOR };
+/// Player shot hitting an enemy
+pub const IMPACT: Burst = Burst { count: 5, speed: 2., lifetime: 12, color: IMPACT_COLOR };
+
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+	pub pos: Point2<f32>,
+	vel: Vector2<f32>,
+	/// Ticks left, the slot is free once at 0
+	ticks_left: u32,
+	lifetime: u32,
+	pub color: [u8; 4],
+}
+
+impl Particle {
+	/// Color faded with the life left
+	pub fn shown_color(&self) -> [u8; 4] {
+		let [r, g, b, a] = self.color;
+		let fade = self.ticks_left as f32 / self.lifetime as f32;
+		[r, g, b, (a as f32 * fade).round() as u8]
+	}
+
+	fn alive(&self) -> bool {
+		self.ticks_left > 0
+	}
+}
+
+/// Pool of the particles, the slots of the dead ones are reused by the next bursts. Only drawn,
+/// they have their own generator so the rolls of the level stay the same with or without them
+#[derive(Clone, Debug)]
+pub struct Particles {
+	pool: Vec<Particle>,
+	rng: Rng,
+}
+
+impl Default for Particles {
+	fn default() -> Particles {
+		Particles {
+			pool: Vec::with_capacity(MAX_PARTICLES),
+			rng: Rng::new(Rng::seed_from_time()),
+		}
+	}
+}
+
+impl Particles {
+	pub fn emit(&mut self, pos: Point2<f32>, burst: Burst) {
+		for _ in 0..burst.count {
+			let angle = self.rng.next_f32() * TAU;
+			let speed = burst.speed * (0.3 + 0.7 * self.rng.next_f32());
+			let lifetime = (burst.lifetime as f32 * (0.5 + 0.5 * self.rng.next_f32())) as u32;
+			let particle = Particle {
+				pos,
+				vel: Vector2::new(angle.cos(), angle.sin()) * speed,
+				ticks_left: lifetime.max(1),
+				lifetime: lifetime.max(1),
+				color: burst.color,
+			};
+			if let Some(slot) = self.pool.iter_mut().find(|slot| !slot.alive()) {
+				*slot = particle;
+			} else if self.pool.len() < MAX_PARTICLES {
+				self.pool.push(particle);
+			} else {
+				return;
+			}
+		}
+	}
+
+	/// Moves the particles by one tick, slowing them down
+	pub fn update(&mut self) {
+		for particle in self.pool.iter_mut().filter(|particle| particle.alive()) {
+			particle.pos += particle.vel;
+			particle.vel *= DRAG;
+			particle.ticks_left -= 1;
+		}
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+		self.pool.iter().filter(|particle| particle.alive())
+	}
+}
@@ -0,0 +1,65 @@
+use directories::ProjectDirs;
+use std::{fs, path::PathBuf};
+
+use crate::error::GameError;
+
+/// Kind of user data, each one has its own directory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserDir {
+	Config,
+	/// Practice quick saves and the progress toward the second loop
+	Saves,
+	Replays,
+	_Screenshots,
+	/// World dumps and other debug output
+	Logs,
+	/// Statistics of the levels played, when exported
+	Stats,
+}
+
+const USER_DIRS: [UserDir; 6] = [
+	UserDir::Config,
+	UserDir::Saves,
+	UserDir::Replays,
+	UserDir::_Screenshots,
+	UserDir::Logs,
+	UserDir::Stats,
+];
+
+impl UserDir {
+	fn name(self) -> &'static str {
+		match self {
+			UserDir::Config => "config",
+			UserDir::Saves => "saves",
+			UserDir::Replays => "replays",
+			UserDir::_Screenshots => "screenshots",
+			UserDir::Logs => "logs",
+			UserDir::Stats => "stats",
+		}
+	}
+
+	/// Platform directory, e.g. `~/.local/share/holybullethell/replays` on Linux.
+	/// Falls back to the working directory if the platform has no home directory
+	pub fn path(self) -> PathBuf {
+		let Some(dirs) = ProjectDirs::from("", "", "Holy Bullet Hell") else {
+			return PathBuf::from(".").join(self.name());
+		};
+		match self {
+			UserDir::Config => dirs.config_dir().to_path_buf(),
+			UserDir::Logs => dirs.data_local_dir().join(self.name()),
+			_ => dirs.data_dir().join(self.name()),
+		}
+	}
+}
+
+/// Creates the user directories missing, on the first run
+pub fn create_all() -> Result<(), GameError> {
+	for dir in USER_DIRS {
+		let path = dir.path();
+		if !path.exists() {
+			fs::create_dir_all(&path).map_err(|err| GameError::config(&path, err))?;
+			println!("Created {}", path.display());
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,102 @@
+use winit::{event::ElementState, keyboard::Key};
+
+use crate::{
+	game::{Game, Inputs, MenuChoice, PauseChoice, RunState},
+	replay::ReplayMode,
+	sound::SoundBase,
+};
+
+/// Entries of the pause menu, from top to bottom
+const PAUSE_ENTRIES: [PauseChoice; 4] = [
+	PauseChoice::Resume,
+	PauseChoice::Restart,
+	PauseChoice::Options,
+	PauseChoice::Quit,
+];
+
+impl Game {
+	/// Stops the level, the keys held are released so nothing stays pressed when it resumes
+	pub fn pause(&mut self) {
+		self.audio.play_sound(SoundBase::MenuSelect);
+		self.inputs = Inputs::default();
+		self.state = RunState::Paused(PauseChoice::Resume);
+	}
+
+	pub fn pause_key_handling(&mut self, key_state: &ElementState, key: &Key) {
+		use winit::keyboard::NamedKey::*;
+		if key_state == &ElementState::Released {
+			return;
+		}
+		let RunState::Paused(choice) = self.state else {
+			unreachable!("Not in pause state")
+		};
+		match key {
+			Key::Named(Escape) => {
+				self.audio.play_sound(SoundBase::MenuBack);
+				self.state = RunState::Playing;
+			},
+			Key::Named(ArrowDown) | Key::Named(ArrowUp) => {
+				self.audio.play_sound(SoundBase::MenuMove);
+				let n = PAUSE_ENTRIES.len();
+				let index = PAUSE_ENTRIES
+					.iter()
+					.position(|&entry| entry == choice)
+					.unwrap();
+				let step = if key == &Key::Named(ArrowDown) {
+					1
+				} else {
+					n - 1
+				};
+				self.state = RunState::Paused(PAUSE_ENTRIES[(index + step) % n]);
+			},
+			Key::Named(Enter) => {
+				self.audio.play_sound(SoundBase::MenuSelect);
+				match choice {
+					PauseChoice::Resume => self.state = RunState::Playing,
+					PauseChoice::Restart => self.restart_level(),
+					PauseChoice::Options => {
+						self.options_from_pause = true;
+						self.state = RunState::Menu(MenuChoice::Resolution);
+					},
+					PauseChoice::Quit => self.leave_level(),
+				}
+			},
+			_ => {},
+		}
+	}
+
+	/// Options opened from the pause menu go back to it instead of the main menu once left
+	pub fn back_to_pause(&mut self) {
+		if self.options_from_pause && self.state == RunState::Menu(MenuChoice::Options) {
+			self.options_from_pause = false;
+			self.state = RunState::Paused(PauseChoice::Options);
+		}
+	}
+
+	/// Plays the level again from the start. The replay of the run left is saved, a replay being
+	/// watched goes back to its beginning
+	fn restart_level(&mut self) {
+		if matches!(self.replay, ReplayMode::Playback(_)) {
+			self.seek(0);
+		} else if let Err(err) = self.finish_replay() {
+			self.fail(err);
+			return;
+		} else {
+			self.start_level(self.infos.level_id.unwrap_or_default());
+		}
+		self.state = RunState::Playing;
+		self.update_presence();
+	}
+
+	/// Goes back to the title screen, saving the replay of the level
+	fn leave_level(&mut self) {
+		match self.finish_replay() {
+			Ok(()) => {
+				self.world = None;
+				self.state = RunState::Menu(MenuChoice::Play);
+				self.update_presence();
+			},
+			Err(err) => self.fail(err),
+		}
+	}
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+
+/// Slots of entities reused once their entity is removed, the pool keeps its room so a dense
+/// pattern fired every few ticks doesn't allocate. The index of an entity stays the same until it
+/// is removed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "PoolData<T>", into = "PoolData<T>")]
+#[serde(bound(
+	serialize = "T: Clone + Serialize",
+	deserialize = "T: Deserialize<'de>"
+))]
+pub struct Pool<T> {
+	slots: Vec<Option<T>>,
+	/// Empty slots, the last one freed is the first one reused
+	free: Vec<usize>,
+	len: usize,
+	/// Room asked for when created, kept through the dumps
+	capacity: usize,
+}
+
+/// Pool as it is dumped, with the room it had and its free slots so a loaded world fills the same
+/// slots in the same order
+#[derive(Serialize, Deserialize)]
+struct PoolData<T> {
+	capacity: usize,
+	slots: Vec<Option<T>>,
+	free: Vec<usize>,
+}
+
+impl<T> Pool<T> {
+	pub fn with_capacity(capacity: usize) -> Pool<T> {
+		Pool {
+			slots: Vec::with_capacity(capacity),
+			free: Vec::with_capacity(capacity),
+			len: 0,
+			capacity,
+		}
+	}
+
+	/// Puts `value` in a free slot, or a new one, and returns its index
+	pub fn push(&mut self, value: T) -> usize {
+		self.len += 1;
+		match self.free.pop() {
+			Some(index) => {
+				self.slots[index] = Some(value);
+				index
+			},
+			None => {
+				self.slots.push(Some(value));
+				self.slots.len() - 1
+			},
+		}
+	}
+
+	pub fn get(&self, index: usize) -> Option<&T> {
+		self.slots.get(index).and_then(Option::as_ref)
+	}
+
+	pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+		self.slots.get_mut(index).and_then(Option::as_mut)
+	}
+
+	/// Frees the slot at `index`, returning what was in it
+	pub fn remove(&mut self, index: usize) -> Option<T> {
+		let value = self.slots.get_mut(index)?.take()?;
+		self.free.push(index);
+		self.len -= 1;
+		Some(value)
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Frees every slot, the room stays
+	pub fn clear(&mut self) {
+		self.slots.clear();
+		self.free.clear();
+		self.len = 0;
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &T> {
+		self.slots.iter().flatten()
+	}
+
+	pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+		self.slots.iter_mut().flatten()
+	}
+
+	/// Entities with their index, in the order of the slots
+	pub fn indexed(&self) -> impl Iterator<Item = (usize, &T)> {
+		self
+			.slots
+			.iter()
+			.enumerate()
+			.filter_map(|(index, slot)| slot.as_ref().map(|value| (index, value)))
+	}
+
+	/// Removes the entities for which `keep` is false, in the order of the slots
+	pub fn retain_mut(&mut self, mut keep: impl FnMut(&mut T) -> bool) {
+		for index in 0..self.slots.len() {
+			if let Some(value) = &mut self.slots[index] {
+				if !keep(value) {
+					self.remove(index);
+				}
+			}
+		}
+	}
+
+	pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+		self.retain_mut(|value| keep(value));
+	}
+}
+
+impl<T> Default for Pool<T> {
+	fn default() -> Pool<T> {
+		Pool::with_capacity(0)
+	}
+}
+
+impl<T> Extend<T> for Pool<T> {
+	fn extend<I: IntoIterator<Item = T>>(&mut self, values: I) {
+		for value in values {
+			self.push(value);
+		}
+	}
+}
+
+impl<T> From<PoolData<T>> for Pool<T> {
+	fn from(data: PoolData<T>) -> Pool<T> {
+		let mut slots = data.slots;
+		slots.reserve(data.capacity.saturating_sub(slots.len()));
+		let mut free = data.free;
+		free.reserve(data.capacity.saturating_sub(free.len()));
+		Pool {
+			len: slots.iter().flatten().count(),
+			slots,
+			free,
+			capacity: data.capacity,
+		}
+	}
+}
+
+impl<T> From<Pool<T>> for PoolData<T> {
+	fn from(pool: Pool<T>) -> PoolData<T> {
+		PoolData { capacity: pool.capacity, slots: pool.slots, free: pool.free }
+	}
+}
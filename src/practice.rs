@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{HashMap, VecDeque},
+	fs,
+	path::PathBuf,
+};
+use winit::{event::ElementState, keyboard::Key};
+
+use crate::{
+	error::GameError,
+	game::{Game, RunState},
+	gameplay::{Event, World},
+	paths::UserDir,
+};
+
+/// Ticks between two rewind snapshots, half a second at 60 Hz
+const SNAPSHOT_INTERVAL: u64 = 30;
+/// Snapshots kept, 10 seconds of play
+const REWIND_CAPACITY: usize = 20;
+/// How far back the rewind key goes, 3 seconds at 60 Hz
+const REWIND_TICKS: u64 = 180;
+
+const SAVE_SLOTS: usize = 3;
+
+/// Recent snapshots of the world, oldest first
+// The worlds are kept serialized, like the dumps. Their timers are game times, read from the clock
+// of the world, so they are right as they are when it is restored
+#[derive(Clone, Debug, Default)]
+pub struct RewindBuffer {
+	snapshots: VecDeque<(u64, String)>,
+}
+
+impl RewindBuffer {
+	/// Forgets the snapshots and starts again from `world`
+	pub fn reset(&mut self, world: &World) {
+		self.snapshots.clear();
+		self
+			.snapshots
+			.push_back((world.tick, serde_json::to_string(world).unwrap()));
+	}
+
+	/// Takes a snapshot every `SNAPSHOT_INTERVAL` ticks, forgetting the oldest one when full
+	pub fn record(&mut self, world: &World) {
+		if !world.tick.is_multiple_of(SNAPSHOT_INTERVAL) {
+			return;
+		}
+		if self.snapshots.len() == REWIND_CAPACITY {
+			self.snapshots.pop_front();
+		}
+		self
+			.snapshots
+			.push_back((world.tick, serde_json::to_string(world).unwrap()));
+	}
+
+	/// World from about `REWIND_TICKS` before `tick`, or the oldest one kept.
+	/// The snapshots taken after it are dropped
+	fn rewind(&mut self, tick: u64) -> Option<World> {
+		let target = tick.saturating_sub(REWIND_TICKS);
+		let index = self
+			.snapshots
+			.iter()
+			.rposition(|(t, _)| *t <= target)
+			.unwrap_or(0);
+		self.snapshots.truncate(index + 1);
+		self
+			.snapshots
+			.back()
+			.and_then(|(_, json)| World::from_json(json).ok())
+	}
+}
+
+/// Part of a level grinded in practice, from one of its events and with the player optionally
+/// invincible
+#[derive(Clone, Debug, Default)]
+pub struct Section {
+	/// Event the levels start at, their beginning when none. Given by `--start-event`
+	pub start_event: Option<u32>,
+	/// Start picked on the level selection for some levels, by level id
+	starts: HashMap<u32, Option<u32>>,
+	pub invincible: bool,
+	/// Starts of the level, from zero again when another one is played
+	pub attempts: u32,
+	level_id: Option<u32>,
+}
+
+impl Section {
+	/// Counts a start of the level `id`
+	pub fn attempt(&mut self, id: u32) {
+		if self.level_id != Some(id) {
+			self.level_id = Some(id);
+			self.attempts = 0;
+		}
+		self.attempts += 1;
+	}
+
+	/// Event the level `id` starts at
+	pub fn start_of(&self, id: u32) -> Option<u32> {
+		self.starts.get(&id).copied().unwrap_or(self.start_event)
+	}
+
+	/// Starts the level `id` at the next of its `events`, at its beginning again after the last
+	pub fn next_start(&mut self, id: u32, events: &[Event]) {
+		let current = self.start_of(id).unwrap_or(0);
+		let next = events
+			.iter()
+			.map(|evt| evt.id)
+			.filter(|evt_id| *evt_id > current)
+			.min();
+		self.starts.insert(id, next);
+	}
+}
+
+/// What a quick save holds
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	level_id: Option<u32>,
+	world: World,
+}
+
+#[derive(Clone, Debug)]
+pub struct QuickSaves {
+	/// Slot used by the save and load keys
+	pub slot: usize,
+	used: [bool; SAVE_SLOTS],
+}
+
+impl QuickSaves {
+	/// Slots saved in a previous session are kept
+	pub fn new() -> QuickSaves {
+		QuickSaves {
+			slot: 0,
+			used: std::array::from_fn(|slot| slot_path(slot).exists()),
+		}
+	}
+
+	pub fn is_used(&self) -> bool {
+		self.used[self.slot]
+	}
+}
+
+fn slot_path(slot: usize) -> PathBuf {
+	UserDir::Saves
+		.path()
+		.join(format!("slot_{}.json", slot + 1))
+}
+
+impl Game {
+	fn quick_save(&mut self) -> Result<(), GameError> {
+		let Some(world) = self.world.clone() else {
+			return Ok(());
+		};
+		let state = SaveState { level_id: self.infos.level_id, world };
+		let slot = self.quick_saves.slot;
+		let path = slot_path(slot);
+		let json = serde_json::to_string(&state).map_err(|err| GameError::user_file(&path, err))?;
+		fs::write(&path, json).map_err(|err| GameError::user_file(&path, err))?;
+		self.quick_saves.used[slot] = true;
+		Ok(())
+	}
+
+	fn quick_load(&mut self) -> Result<(), GameError> {
+		if !self.quick_saves.is_used() {
+			return Ok(());
+		}
+		let path = slot_path(self.quick_saves.slot);
+		let json = fs::read_to_string(&path).map_err(|err| GameError::user_file(&path, err))?;
+		let state: SaveState =
+			serde_json::from_str(&json).map_err(|err| GameError::user_file(&path, err))?;
+		// The snapshots belong to the timeline that is left
+		self.rewind.reset(&state.world);
+		self.infos.level_id = state.level_id;
+		self.world = Some(state.world);
+		Ok(())
+	}
+
+	pub fn practice_key_handling(&mut self, key_state: &ElementState, key: &Key) {
+		use winit::keyboard::NamedKey::*;
+		if !self.config.practice || self.state != RunState::Playing {
+			return;
+		}
+		if key_state == &ElementState::Released {
+			return;
+		}
+		// Named keys only, the letters can be bound to the controls
+		let result = match key {
+			Key::Named(Backspace) => {
+				let tick = self.world.as_ref().map_or(0, |world| world.tick);
+				if let Some(world) = self.rewind.rewind(tick) {
+					self.world = Some(world);
+				}
+				Ok(())
+			},
+			Key::Named(F5) => self.quick_save(),
+			Key::Named(F6) => {
+				self.quick_saves.slot = (self.quick_saves.slot + 1) % SAVE_SLOTS;
+				Ok(())
+			},
+			Key::Named(F8) => self.quick_load(),
+			Key::Named(F7) => {
+				self.section.invincible = !self.section.invincible;
+				if let Some(world) = &mut self.world {
+					world.invincible = self.section.invincible;
+				}
+				Ok(())
+			},
+			Key::Named(F2) => {
+				if let Some(id) = self.infos.level_id {
+					self.start_level(id);
+				}
+				Ok(())
+			},
+			_ => Ok(()),
+		};
+		if let Err(err) = result {
+			self.fail(err);
+		}
+	}
+}
@@ -0,0 +1,233 @@
+use serde::Serialize;
+use std::{
+	io::{self, Read, Write},
+	path::PathBuf,
+	sync::mpsc::{self, Sender},
+	thread,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+	game::{Game, RunState},
+	replay::ReplayMode,
+};
+
+/// Discord opens the first free one of these sockets
+const IPC_SLOTS: u32 = 10;
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+
+/// What the player is doing, as shown on their Discord profile
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Activity {
+	/// First line, the stage and score or the boss being fought
+	details: String,
+	/// Second line, the mode and difficulty. Discord refuses empty lines
+	#[serde(skip_serializing_if = "String::is_empty")]
+	state: String,
+	/// Shown as the time elapsed since the start
+	#[serde(skip_serializing_if = "Option::is_none")]
+	timestamps: Option<Timestamps>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct Timestamps {
+	/// Unix time, in seconds
+	start: u64,
+}
+
+#[derive(Serialize)]
+struct Handshake<'a> {
+	v: u32,
+	client_id: &'a str,
+}
+
+#[derive(Serialize)]
+struct Command<'a> {
+	cmd: &'static str,
+	args: CommandArgs<'a>,
+	nonce: String,
+}
+
+#[derive(Serialize)]
+struct CommandArgs<'a> {
+	pid: u32,
+	/// None clears the activity
+	activity: Option<&'a Activity>,
+}
+
+trait Ipc: Read + Write {}
+impl<T: Read + Write> Ipc for T {}
+
+/// Connection to the Discord client running on the same machine
+struct Connection {
+	stream: Box<dyn Ipc>,
+	nonce: u64,
+}
+
+impl Connection {
+	/// `client_id` is the id of an application of the Discord developer portal, Discord refuses
+	/// the handshake without a registered one
+	fn open(client_id: &str) -> io::Result<Connection> {
+		let stream = (0..IPC_SLOTS)
+			.find_map(|slot| open_socket(slot).ok())
+			.ok_or(io::Error::new(
+				io::ErrorKind::NotFound,
+				"Discord isn't running",
+			))?;
+		let mut connection = Connection { stream, nonce: 0 };
+		let handshake = Handshake { v: 1, client_id };
+		connection.send(OP_HANDSHAKE, &handshake)?;
+		Ok(connection)
+	}
+
+	/// Writes a message and waits for its answer, which is only read to keep the socket flowing
+	fn send(&mut self, op: u32, payload: &impl Serialize) -> io::Result<()> {
+		let json = serde_json::to_vec(payload)?;
+		self.stream.write_all(&op.to_le_bytes())?;
+		self.stream.write_all(&(json.len() as u32).to_le_bytes())?;
+		self.stream.write_all(&json)?;
+
+		let mut header = [0; 8];
+		self.stream.read_exact(&mut header)?;
+		let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+		io::copy(&mut (&mut self.stream).take(len as u64), &mut io::sink())?;
+		Ok(())
+	}
+
+	fn set_activity(&mut self, activity: Option<&Activity>) -> io::Result<()> {
+		self.nonce += 1;
+		let command = Command {
+			cmd: "SET_ACTIVITY",
+			args: CommandArgs { pid: std::process::id(), activity },
+			nonce: self.nonce.to_string(),
+		};
+		self.send(OP_FRAME, &command)
+	}
+}
+
+#[cfg(unix)]
+fn open_socket(slot: u32) -> io::Result<Box<dyn Ipc>> {
+	let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+		.into_iter()
+		.find_map(std::env::var_os)
+		.map_or(PathBuf::from("/tmp"), PathBuf::from);
+	let stream = std::os::unix::net::UnixStream::connect(dir.join(format!("discord-ipc-{slot}")))?;
+	Ok(Box::new(stream))
+}
+
+#[cfg(windows)]
+fn open_socket(slot: u32) -> io::Result<Box<dyn Ipc>> {
+	let path = PathBuf::from(format!(r"\\?\pipe\discord-ipc-{slot}"));
+	let pipe = std::fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.open(path)?;
+	Ok(Box::new(pipe))
+}
+
+/// Publishes the activity to Discord from its own thread, so a slow or missing client never holds
+/// up a frame. Nothing is started before the first activity
+#[derive(Default)]
+pub struct Presence {
+	sender: Option<Sender<Option<Activity>>>,
+	/// Last activity sent, the same one isn't sent twice
+	last: Option<Activity>,
+}
+
+impl Presence {
+	/// `None` clears the activity from the profile. The thread connects with `client_id`, the one
+	/// given when it starts
+	fn set(&mut self, activity: Option<Activity>, client_id: &str) {
+		if activity == self.last {
+			return;
+		}
+		self.last = activity.clone();
+		let sender = self.sender.get_or_insert_with(|| {
+			let client_id = client_id.to_string();
+			let (sender, receiver) = mpsc::channel::<Option<Activity>>();
+			thread::spawn(move || {
+				let mut connection = None;
+				for activity in receiver {
+					if connection.is_none() {
+						connection = Connection::open(&client_id).ok();
+					}
+					// Discord was closed, it is looked for again with the next activity
+					if let Some(open) = &mut connection {
+						if open.set_activity(activity.as_ref()).is_err() {
+							connection = None;
+						}
+					}
+				}
+			});
+			sender
+		});
+		// The thread only stops with the game
+		let _ = sender.send(activity);
+	}
+}
+
+impl Game {
+	/// Sends what the player is doing to Discord, if enabled in the options with a client id.
+	/// Called when a level starts or is left and when a boss appears or is beaten
+	pub fn update_presence(&mut self) {
+		if !self.config.discord_presence || self.config.discord_client_id.is_empty() {
+			return;
+		}
+		let activity = self.activity();
+		self
+			.presence
+			.set(Some(activity), &self.config.discord_client_id);
+	}
+
+	/// Removes the activity, when the option is turned off
+	pub fn clear_presence(&mut self) {
+		self.presence.set(None, &self.config.discord_client_id);
+	}
+
+	fn activity(&self) -> Activity {
+		let Some(world) = &self.world else {
+			let details = if matches!(self.state, RunState::Victory(_) | RunState::Credits) {
+				"Cleared the game"
+			} else {
+				"In the menus"
+			};
+			return Activity { details: details.into(), state: String::new(), timestamps: None };
+		};
+		let level_name = self
+			.infos
+			.level_id
+			.and_then(|id| self.levels.get(id as usize))
+			.map_or("Custom stage".to_string(), |level| level.name.to_string());
+		let boss = world
+			.enemies
+			.iter()
+			.find(|enemy| enemy.boss.is_some())
+			.map(|enemy| enemy.variant.def().name.clone());
+		let details = match boss {
+			Some(name) => format!("{level_name}, fighting the {name} boss"),
+			None => format!("{level_name}, {} points", world.score),
+		};
+		let mode = if matches!(self.replay, ReplayMode::Playback(_)) {
+			"Watching a replay"
+		} else if self.config.practice {
+			"Practice"
+		} else if world.modifiers.second_loop {
+			"Second loop"
+		} else if world.assist.enabled {
+			"Assist mode"
+		} else {
+			"Playing"
+		};
+		let start = SystemTime::now()
+			.checked_sub(self.infos.since_level_begin())
+			.and_then(|start| start.duration_since(UNIX_EPOCH).ok())
+			.unwrap_or(Duration::ZERO);
+		Activity {
+			details,
+			state: format!("{mode}, {}", world.difficulty.name()),
+			timestamps: Some(Timestamps { start: start.as_secs() }),
+		}
+	}
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fs,
+	path::PathBuf,
+};
+
+use crate::{
+	error::GameError,
+	game::{Difficulty, Level, LevelMode},
+	paths::UserDir,
+};
+
+/// Endless runs kept in the leaderboard
+const ENDLESS_RECORDS: usize = 10;
+/// Scores kept for each level
+const HIGH_SCORES: usize = 5;
+
+/// Titles of the levels cleared without continues, the second loop unlocks once all are, the
+/// high scores of each level, the best endless runs and the best daily challenge runs
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+	cleared: BTreeSet<String>,
+	/// Highest score first
+	endless: Vec<EndlessRecord>,
+	/// By level title, highest first
+	scores: BTreeMap<String, Vec<ScoreRecord>>,
+	/// Best daily challenge run of each day, by days since the Unix epoch
+	daily: BTreeMap<u64, EndlessRecord>,
+}
+
+/// Entry of the high scores of a level, the total of its end of stage tally
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreRecord {
+	pub score: u64,
+	pub difficulty: Difficulty,
+}
+
+/// Entry of the endless leaderboard
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EndlessRecord {
+	pub score: u64,
+	/// Waves sent before the run ended
+	pub waves: u32,
+	/// In-game time survived, in seconds
+	pub time: f32,
+	pub difficulty: Difficulty,
+}
+
+fn progress_path() -> PathBuf {
+	UserDir::Saves.path().join("progress.json")
+}
+
+impl Progress {
+	/// Nothing is cleared on the first run
+	pub fn load() -> Result<Progress, GameError> {
+		let path = progress_path();
+		if !path.exists() {
+			return Ok(Progress::default());
+		}
+		let json = fs::read_to_string(&path).map_err(|err| GameError::user_file(&path, err))?;
+		serde_json::from_str(&json).map_err(|err| GameError::user_file(&path, err))
+	}
+
+	/// Records the clear and saves the progress right away
+	pub fn clear(&mut self, level: &Level) -> Result<(), GameError> {
+		if !self.cleared.insert(level.name.to_string()) {
+			return Ok(());
+		}
+		self.save()
+	}
+
+	/// Adds the run to the leaderboard if it makes it, and saves the progress right away
+	pub fn record_endless(&mut self, record: EndlessRecord) -> Result<(), GameError> {
+		let rank = self
+			.endless
+			.iter()
+			.position(|best| best.score < record.score)
+			.unwrap_or(self.endless.len());
+		if rank >= ENDLESS_RECORDS {
+			return Ok(());
+		}
+		self.endless.insert(rank, record);
+		self.endless.truncate(ENDLESS_RECORDS);
+		self.save()
+	}
+
+	/// Adds the score to the high scores of the level if it makes it, and saves the progress right
+	/// away. Returns its place, from 0
+	pub fn record_score(
+		&mut self,
+		level_name: &str,
+		record: ScoreRecord,
+	) -> Result<Option<usize>, GameError> {
+		let scores = self.scores.entry(level_name.to_string()).or_default();
+		let rank = scores
+			.iter()
+			.position(|best| best.score < record.score)
+			.unwrap_or(scores.len());
+		if rank >= HIGH_SCORES {
+			return Ok(None);
+		}
+		scores.insert(rank, record);
+		scores.truncate(HIGH_SCORES);
+		self.save().map(|_| Some(rank))
+	}
+
+	/// Highest score of the level
+	pub fn best_score(&self, level_name: &str) -> Option<&ScoreRecord> {
+		self
+			.scores
+			.get(level_name)
+			.and_then(|scores| scores.first())
+	}
+
+	/// Keeps the run if it is the best of its day, and saves the progress right away
+	pub fn record_daily(&mut self, day: u64, record: EndlessRecord) -> Result<(), GameError> {
+		if self
+			.daily
+			.get(&day)
+			.is_some_and(|best| best.score >= record.score)
+		{
+			return Ok(());
+		}
+		self.daily.insert(day, record);
+		self.save()
+	}
+
+	pub fn best_daily(&self, day: u64) -> Option<&EndlessRecord> {
+		self.daily.get(&day)
+	}
+
+	/// Highest scoring endless run
+	pub fn best_endless(&self) -> Option<&EndlessRecord> {
+		self.endless.first()
+	}
+
+	fn save(&self) -> Result<(), GameError> {
+		let path = progress_path();
+		let json = serde_json::to_string(self).map_err(|err| GameError::user_file(&path, err))?;
+		fs::write(&path, json).map_err(|err| GameError::user_file(&path, err))
+	}
+
+	/// Every stage is cleared, the endless and boss rush levels don't count
+	pub fn second_loop_unlocked(&self, levels: &[Level]) -> bool {
+		let mut stages = levels
+			.iter()
+			.filter(|level| level.mode == LevelMode::Stage)
+			.peekable();
+		stages.peek().is_some() && stages.all(|level| self.cleared.contains(&*level.name))
+	}
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::{
+	fs,
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use winit::{event::ElementState, keyboard::Key};
+
+use crate::{
+	error::GameError,
+	game::{Difficulty, Game, Inputs, RunState},
+	gameplay::{Character, GameClock, Weapon, TICK},
+	paths::UserDir,
+	rules::{Assist, Modifiers},
+};
+
+/// Playback speeds, cycled through with the speed key
+const SPEEDS: [u32; 3] = [1, 2, 4];
+/// Ticks skipped by the jump keys, 10 seconds at 60 Hz
+const JUMP_TICKS: u64 = 600;
+/// Longest time simulated in one frame, the game slows down instead of freezing to catch up after
+/// a stall
+const MAX_FRAME_STEP: Duration = Duration::from_millis(100);
+
+/// What the simulation received during one tick
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayFrame {
+	pub dt: Duration,
+	pub inputs: Inputs,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+	pub level_id: u32,
+	pub modifiers: Modifiers,
+	pub assist: Assist,
+	pub character: Character,
+	pub weapon: Weapon,
+	pub difficulty: Difficulty,
+	/// Seed of the world random generator
+	pub seed: u64,
+	/// One frame per tick, indexed by `World::tick`
+	pub frames: Vec<ReplayFrame>,
+	/// Real time of the run, from the level start to when it was left
+	pub rta: Duration,
+	/// In-game time of the run, the game clock at the end of the frames
+	pub igt: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct Playback {
+	pub replay: Replay,
+	speed_index: usize,
+	pub paused: bool,
+}
+
+impl Playback {
+	pub fn speed(&self) -> u32 {
+		SPEEDS[self.speed_index]
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum ReplayMode {
+	Off,
+	/// The current run is recorded, it is saved when it ends
+	Recording(Replay),
+	/// The simulation follows a replay instead of the player
+	Playback(Playback),
+}
+
+impl Game {
+	pub fn start_playback(&mut self, path: &Path) -> Result<(), GameError> {
+		let raw = fs::read_to_string(path).map_err(|err| GameError::user_file(path, err))?;
+		let replay: Replay =
+			serde_json::from_str(&raw).map_err(|err| GameError::user_file(path, err))?;
+		let level_id = replay.level_id;
+		if level_id as usize >= self.levels.len() {
+			let reason = format!("level {level_id} doesn't exist");
+			return Err(GameError::user_file(path, reason));
+		}
+		// The seed of the daily challenge is in the replay
+		self.daily = None;
+		self.replay = ReplayMode::Playback(Playback { replay, speed_index: 0, paused: false });
+		self.start_level(level_id);
+		self.state = RunState::Playing;
+		Ok(())
+	}
+
+	/// Saves the replay if the run was recorded, and stops any replay
+	pub fn finish_replay(&mut self) -> Result<(), GameError> {
+		let replay = std::mem::replace(&mut self.replay, ReplayMode::Off);
+		if let ReplayMode::Recording(mut replay) = replay {
+			replay.rta = self.infos.since_level_begin();
+			replay.igt = replay
+				.frames
+				.iter()
+				.map(|frame| GameClock::step(frame.dt))
+				.sum();
+			if !replay.frames.is_empty() {
+				let stamp = SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.unwrap_or_default()
+					.as_secs();
+				let path = UserDir::Replays.path().join(format!("replay_{stamp}.json"));
+				let json =
+					serde_json::to_string(&replay).map_err(|err| GameError::user_file(&path, err))?;
+				fs::write(&path, json).map_err(|err| GameError::user_file(&path, err))?;
+				println!("Replay saved to {}", path.display());
+			}
+		}
+		Ok(())
+	}
+
+	/// Advances the simulation by as many fixed ticks as the time of the frame holds, the rest is
+	/// kept for the next frame. A replay is followed tick by tick with the steps it recorded, the
+	/// replays from before the fixed ticks have one step per frame
+	pub fn update(&mut self) {
+		let speed = match &self.replay {
+			ReplayMode::Playback(playback) if playback.paused => 0,
+			ReplayMode::Playback(playback) => playback.speed(),
+			_ => 1,
+		};
+		self.infos.accumulator += self.infos.dt.min(MAX_FRAME_STEP) * speed;
+		while self.state == RunState::Playing {
+			let step = match &self.replay {
+				ReplayMode::Playback(playback) => {
+					let tick = self.world.as_ref().unwrap().tick as usize;
+					match playback.replay.frames.get(tick) {
+						Some(frame) => frame.dt,
+						None => break,
+					}
+				},
+				_ => TICK,
+			};
+			if self.infos.accumulator < step {
+				break;
+			}
+			self.infos.accumulator -= step;
+			match &mut self.replay {
+				ReplayMode::Off => self.tick(step),
+				ReplayMode::Recording(replay) => {
+					replay
+						.frames
+						.push(ReplayFrame { dt: step, inputs: self.inputs.clone() });
+					self.tick(step);
+				},
+				ReplayMode::Playback(_) => {
+					if !self.playback_step() {
+						break;
+					}
+				},
+			}
+		}
+	}
+
+	/// Plays the next frame of the replay, returns false once the replay is over
+	fn playback_step(&mut self) -> bool {
+		// The run ended during this frame
+		if self.state != RunState::Playing {
+			return false;
+		}
+		let ReplayMode::Playback(playback) = &self.replay else {
+			return false;
+		};
+		let tick = self.world.as_ref().unwrap().tick as usize;
+		let Some(frame) = playback.replay.frames.get(tick).cloned() else {
+			return false;
+		};
+		self.inputs = frame.inputs;
+		self.tick(frame.dt);
+		true
+	}
+
+	/// Moves the playback to `target`, simulating without drawing
+	pub fn seek(&mut self, target: u64) {
+		let ReplayMode::Playback(playback) = &self.replay else {
+			return;
+		};
+		// The world can't go back in time, so it starts over
+		if target < self.world.as_ref().unwrap().tick {
+			self.start_level(playback.replay.level_id);
+		}
+		while self.world.as_ref().unwrap().tick < target && self.playback_step() {}
+	}
+
+	pub fn replay_key_handling(&mut self, key_state: &ElementState, key: &Key) {
+		use winit::keyboard::NamedKey::*;
+		let ReplayMode::Playback(playback) = &mut self.replay else {
+			return;
+		};
+		// The pause and options menus use the same keys
+		if self.state != RunState::Playing || key_state == &ElementState::Released {
+			return;
+		}
+		let tick = self.world.as_ref().unwrap().tick;
+		// The controls can be bound to any letter
+		match key {
+			Key::Named(Space) => playback.paused = !playback.paused,
+			Key::Named(F1) => {
+				playback.speed_index = (playback.speed_index + 1) % SPEEDS.len();
+			},
+			Key::Named(PageDown) => self.seek(tick + JUMP_TICKS),
+			Key::Named(PageUp) => self.seek(tick.saturating_sub(JUMP_TICKS)),
+			Key::Named(Home) => self.seek(0),
+			_ => {},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{assets, game::Level, gameplay::World, rng::Rng, rules::Rules};
+
+	/// Keys pressed at random, held for a few ticks like a player would
+	fn random_inputs(rng: &mut Rng) -> Inputs {
+		let mut pressed = || rng.next_f32() < 0.5;
+		Inputs {
+			left: pressed(),
+			right: pressed(),
+			up: pressed(),
+			down: pressed(),
+			shoot: pressed(),
+			dash: pressed(),
+			focus: pressed(),
+			missile: pressed(),
+			..Inputs::default()
+		}
+	}
+
+	#[test]
+	fn replays_play_back_to_the_recorded_world() {
+		let raw = assets::read("levels/level1.hbh").unwrap();
+		let level = Level::parse(0, std::str::from_utf8(&raw).unwrap()).unwrap();
+		let rules = Rules::default();
+		let seed = 42;
+
+		let mut recorded = World::start(level.playfield, level.event_list.clone(), &rules, seed);
+		recorded.invincible = true;
+		let mut rng = Rng::new(7);
+		let mut frames = vec![];
+		let mut inputs = Inputs::default();
+		for tick in 0..1800 {
+			if tick % 8 == 0 {
+				inputs = random_inputs(&mut rng);
+			}
+			recorded.step(&inputs, TICK);
+			frames.push(ReplayFrame { dt: TICK, inputs: inputs.clone() });
+		}
+		let replay = Replay {
+			level_id: level.id,
+			modifiers: rules.modifiers.clone(),
+			assist: rules.assist.clone(),
+			character: rules.character,
+			weapon: rules.weapon,
+			difficulty: rules.difficulty,
+			seed,
+			frames,
+			rta: Duration::ZERO,
+			igt: Duration::ZERO,
+		};
+		let json = serde_json::to_string(&replay).unwrap();
+		let replay: Replay = serde_json::from_str(&json).unwrap();
+
+		// Rules rebuilt from the replay, as when it is started
+		let rules = Rules {
+			modifiers: replay.modifiers.clone(),
+			assist: replay.assist.clone(),
+			character: replay.character,
+			weapon: replay.weapon,
+			difficulty: replay.difficulty,
+			..Rules::default()
+		};
+		let mut played = World::start(level.playfield, level.event_list, &rules, replay.seed);
+		played.invincible = true;
+		for frame in &replay.frames {
+			played.step(&frame.inputs, frame.dt);
+		}
+
+		// The run went through the enemies of the level
+		assert!(recorded.stats.shots > 0 && recorded.stats.grazes > 0);
+		assert_eq!(
+			serde_json::to_value(&played).unwrap(),
+			serde_json::to_value(&recorded).unwrap()
+		);
+	}
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::{
+	error::GameError,
+	game::Difficulty,
+	gameplay::{Character, Weapon, DT_60},
+	paths::UserDir,
+};
+
+/// Written to the config directory on the first run, with comments for each key
+const DEFAULT_RULES_FILE: &str = include_str!("../rules.toml");
+
+/// Gameplay tunables, read from `rules.toml` in the config directory at startup so they can be changed without recompiling.
+/// Missing keys keep their default value
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+	/// Player hp at the start of a level, shown as lives
+	pub player_hp: u32,
+	/// Seconds between two player shots
+	pub fire_cooldown: f32,
+	/// Seconds of invulnerability after the player is hit
+	pub immunity: f32,
+	/// Seconds between two dashes
+	pub dash_cooldown: f32,
+	/// Distance in pixels covered by a dash
+	pub dash_distance: f32,
+	/// Seconds of invulnerability from the start of a dash
+	pub dash_immunity: f32,
+	/// Seconds between two homing missiles
+	pub missile_cooldown: f32,
+	/// Homing missiles at the start of a level
+	pub missiles: u32,
+	/// Bombs at the start of a level
+	pub bombs: u32,
+	/// Distance in pixels outside the playfield at which projectiles are removed
+	pub despawn_margin: f32,
+	/// Multiplies the chance of every drop of the enemy drop tables
+	pub drop_rate: f32,
+	/// Overlay applied to the rules when the second loop is played
+	pub second_loop: SecondLoop,
+	/// Picked in the modifiers menu, not in the file
+	#[serde(skip)]
+	pub modifiers: Modifiers,
+	/// Picked on the level selection, not in the file
+	#[serde(skip)]
+	pub character: Character,
+	/// Picked on the level selection, not in the file
+	#[serde(skip)]
+	pub weapon: Weapon,
+	/// Copied from the settings when a level starts, or from the replay being watched
+	#[serde(skip)]
+	pub assist: Assist,
+	/// Copied from the settings when a level starts, or from the replay being watched
+	#[serde(skip)]
+	pub difficulty: Difficulty,
+}
+
+impl Default for Rules {
+	fn default() -> Rules {
+		Rules {
+			player_hp: 5,
+			fire_cooldown: 15. * DT_60,
+			immunity: 2.,
+			dash_cooldown: 1.,
+			dash_distance: 80.,
+			dash_immunity: 0.25,
+			missile_cooldown: 1.,
+			missiles: 3,
+			bombs: 3,
+			despawn_margin: 64.,
+			drop_rate: 1.,
+			second_loop: SecondLoop::default(),
+			modifiers: Modifiers::default(),
+			character: Character::default(),
+			weapon: Weapon::default(),
+			assist: Assist::default(),
+			difficulty: Difficulty::default(),
+		}
+	}
+}
+
+/// Harder rules of the second loop, unlocked by clearing every level without continues
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecondLoop {
+	/// Enemies fire a last aimed shot when they die
+	pub revenge_bullets: bool,
+	/// Multiplies the time between two enemy shots
+	pub fire_cooldown_factor: f32,
+	/// Multiplies the speed of the enemy bullets
+	pub bullet_speed_factor: f32,
+}
+
+impl Default for SecondLoop {
+	fn default() -> SecondLoop {
+		SecondLoop {
+			revenge_bullets: true,
+			fire_cooldown_factor: 0.75,
+			bullet_speed_factor: 1.25,
+		}
+	}
+}
+
+impl Rules {
+	/// Reads the rules file of the config directory, it is created with the defaults if missing
+	pub fn load() -> Result<Rules, GameError> {
+		let path = UserDir::Config.path().join("rules.toml");
+		if !path.exists() {
+			fs::write(&path, DEFAULT_RULES_FILE).map_err(|err| GameError::config(&path, err))?;
+			return Ok(Rules::default());
+		}
+		let raw = fs::read_to_string(&path).map_err(|err| GameError::config(&path, err))?;
+		toml::from_str(&raw).map_err(|err| GameError::config(&path, err))
+	}
+}
+
+/// Changes to a run, kept in its replay. All but the mirrored stage and the second loop are cheats,
+/// a run using any of them isn't scored
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Modifiers {
+	/// Speed of the enemy bullets, in percent of their normal speed
+	pub bullet_speed: u32,
+	pub double_damage: bool,
+	/// Using a bomb doesn't take it from the stock
+	pub infinite_bombs: bool,
+	/// Spawns, paths and patterns are mirrored across the vertical axis of the playfield
+	pub mirrored: bool,
+	/// Plays with the second loop rules, once unlocked
+	pub second_loop: bool,
+}
+
+impl Default for Modifiers {
+	fn default() -> Modifiers {
+		Modifiers {
+			bullet_speed: 100,
+			double_damage: false,
+			infinite_bombs: false,
+			mirrored: false,
+			second_loop: false,
+		}
+	}
+}
+
+impl Modifiers {
+	pub const BULLET_SPEED_STEP: u32 = 10;
+	pub const BULLET_SPEED_RANGE: (u32, u32) = (50, 200);
+
+	pub fn is_scoring(&self) -> bool {
+		Modifiers { mirrored: false, second_loop: false, ..self.clone() } == Modifiers::default()
+	}
+
+	pub fn bullet_speed_factor(&self) -> f32 {
+		self.bullet_speed as f32 / 100.
+	}
+
+	pub fn damage_factor(&self) -> f32 {
+		if self.double_damage {
+			2.
+		} else {
+			1.
+		}
+	}
+}
+
+/// Assist mode, making the game more approachable. Unlike the modifiers it isn't a cheat, an
+/// assisted run is still scored but marked as assisted on its summary and replay
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Assist {
+	pub enabled: bool,
+	/// Lives added at the start of a level
+	pub extra_hp: u32,
+	/// Invulnerability after a hit, in percent of the normal one
+	pub immunity: u32,
+	/// Speed of the enemy bullets, in percent of their normal speed
+	pub bullet_speed: u32,
+}
+
+impl Default for Assist {
+	fn default() -> Assist {
+		Assist { enabled: false, extra_hp: 2, immunity: 150, bullet_speed: 80 }
+	}
+}
+
+impl Assist {
+	pub const EXTRA_HP_RANGE: (u32, u32) = (0, 5);
+	pub const IMMUNITY_STEP: u32 = 25;
+	pub const IMMUNITY_RANGE: (u32, u32) = (100, 300);
+	pub const BULLET_SPEED_STEP: u32 = 5;
+	pub const BULLET_SPEED_RANGE: (u32, u32) = (50, 100);
+
+	/// Brings values edited by hand back to what the menu can reach
+	pub fn clamp(&mut self) {
+		let clamp = |value: u32, (min, max): (u32, u32)| value.clamp(min, max);
+		self.extra_hp = clamp(self.extra_hp, Assist::EXTRA_HP_RANGE);
+		self.immunity = clamp(self.immunity, Assist::IMMUNITY_RANGE);
+		self.bullet_speed = clamp(self.bullet_speed, Assist::BULLET_SPEED_RANGE);
+	}
+
+	pub fn extra_hp(&self) -> u32 {
+		if self.enabled {
+			self.extra_hp
+		} else {
+			0
+		}
+	}
+
+	pub fn immunity_factor(&self) -> f32 {
+		if self.enabled {
+			self.immunity as f32 / 100.
+		} else {
+			1.
+		}
+	}
+
+	pub fn bullet_speed_factor(&self) -> f32 {
+		if self.enabled {
+			self.bullet_speed as f32 / 100.
+		} else {
+			1.
+		}
+	}
+}
@@ -0,0 +1,77 @@
+use cgmath::Point2;
+use serde::{Deserialize, Serialize};
+use std::{
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf},
+};
+
+use crate::error::GameError;
+
+/// Id of the player in the log, enemies are numbered from 1
+pub const PLAYER_ID: u32 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogKind {
+	Spawn,
+	Hit,
+	Kill,
+	/// The player lost all their HP
+	Death,
+}
+
+/// One line of the session log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+	pub tick: u64,
+	pub kind: LogKind,
+	pub entity: u32,
+	pub x: f32,
+	pub y: f32,
+}
+
+impl LogEntry {
+	pub fn new(tick: u64, kind: LogKind, entity: u32, pos: Point2<f32>) -> LogEntry {
+		LogEntry { tick, kind, entity, x: pos.x, y: pos.y }
+	}
+}
+
+/// Writes gameplay events as JSON lines, does nothing when no file was given
+#[derive(Default)]
+pub struct SessionLog {
+	writer: Option<BufWriter<File>>,
+	/// Named in the errors
+	path: PathBuf,
+}
+
+impl SessionLog {
+	pub fn to_file(path: &Path) -> Result<SessionLog, GameError> {
+		let file = File::create(path).map_err(|err| GameError::user_file(path, err))?;
+		Ok(SessionLog { writer: Some(BufWriter::new(file)), path: path.into() })
+	}
+
+	/// The log is closed after an error, the game goes on without it
+	pub fn write(&mut self, mut entries: impl Iterator<Item = LogEntry>) -> Result<(), GameError> {
+		let Some(writer) = &mut self.writer else {
+			return Ok(());
+		};
+		let result = entries.try_for_each(|entry| {
+			serde_json::to_writer(&mut *writer, &entry).map_err(io::Error::from)?;
+			writer.write_all(b"\n")
+		});
+		result.map_err(|err| self.close(err))
+	}
+
+	pub fn flush(&mut self) -> Result<(), GameError> {
+		let Some(writer) = &mut self.writer else {
+			return Ok(());
+		};
+		writer.flush().map_err(|err| self.close(err))
+	}
+
+	fn close(&mut self, reason: impl std::fmt::Display) -> GameError {
+		self.writer = None;
+		GameError::user_file(&self.path, reason)
+	}
+}
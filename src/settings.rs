@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use winit::{event::ElementState, keyboard::Key};
+
+use crate::{
+	draw::{ResizableWindow, N_SIZES},
+	error::GameError,
+	game::{Config, FullscreenMode, Game},
+	paths::UserDir,
+	sound::SoundBase,
+};
+
+/// Change of a volume option for each step, in percent
+pub const VOLUME_STEP: u32 = 10;
+
+/// Action with a key that can be changed in the controls menu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+	Up,
+	Down,
+	Left,
+	Right,
+	Shoot,
+	Dash,
+	Hyper,
+	/// Held to move slower, for precise dodges
+	Focus,
+	/// Secondary fire, launches a homing missile
+	Missile,
+	Bomb,
+}
+
+impl Action {
+	/// Order of the controls menu
+	pub const ALL: [Action; 10] = [
+		Action::Up,
+		Action::Down,
+		Action::Left,
+		Action::Right,
+		Action::Shoot,
+		Action::Dash,
+		Action::Hyper,
+		Action::Focus,
+		Action::Missile,
+		Action::Bomb,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Action::Up => "Up",
+			Action::Down => "Down",
+			Action::Left => "Left",
+			Action::Right => "Right",
+			Action::Shoot => "Shoot",
+			Action::Dash => "Dash",
+			Action::Hyper => "Hyper",
+			Action::Focus => "Focus",
+			Action::Missile => "Missile",
+			Action::Bomb => "Bomb",
+		}
+	}
+
+	fn index(self) -> usize {
+		Action::ALL
+			.iter()
+			.position(|action| *action == self)
+			.unwrap()
+	}
+
+	/// Next entry of the controls menu, None for the last one
+	pub fn next(self) -> Option<Action> {
+		Action::ALL.get(self.index() + 1).copied()
+	}
+
+	/// Previous entry of the controls menu, None for the first one
+	pub fn prev(self) -> Option<Action> {
+		self.index().checked_sub(1).map(|index| Action::ALL[index])
+	}
+}
+
+/// Name of a key in the bindings: the character it types, or the name of the arrow
+pub fn key_name(key: &Key) -> Option<String> {
+	use winit::keyboard::NamedKey::*;
+	match key {
+		Key::Character(c) => Some(c.to_lowercase()),
+		Key::Named(ArrowUp) => Some("arrowup".into()),
+		Key::Named(ArrowDown) => Some("arrowdown".into()),
+		Key::Named(ArrowLeft) => Some("arrowleft".into()),
+		Key::Named(ArrowRight) => Some("arrowright".into()),
+		Key::Named(Shift) => Some("shift".into()),
+		_ => None,
+	}
+}
+
+/// Tells if the name could come from `key_name`
+fn is_key_name(name: &str) -> bool {
+	name.chars().count() == 1
+		|| matches!(
+			name,
+			"arrowup" | "arrowdown" | "arrowleft" | "arrowright" | "shift"
+		)
+}
+
+/// Key name as shown in the menus, the font has no arrows
+fn shown_key(name: &str) -> &str {
+	match name {
+		"arrowup" => "Up arrow",
+		"arrowdown" => "Down arrow",
+		"arrowleft" => "Left arrow",
+		"arrowright" => "Right arrow",
+		"shift" => "Shift",
+		name => name,
+	}
+}
+
+/// Keys of the actions, by `key_name`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+	pub up: String,
+	pub down: String,
+	pub left: String,
+	pub right: String,
+	pub shoot: String,
+	pub dash: String,
+	pub hyper: String,
+	pub focus: String,
+	pub missile: String,
+	pub bomb: String,
+}
+
+impl Default for Bindings {
+	fn default() -> Bindings {
+		ControlPreset::Classic.bindings()
+	}
+}
+
+impl Bindings {
+	pub fn key(&self, action: Action) -> &str {
+		match action {
+			Action::Up => &self.up,
+			Action::Down => &self.down,
+			Action::Left => &self.left,
+			Action::Right => &self.right,
+			Action::Shoot => &self.shoot,
+			Action::Dash => &self.dash,
+			Action::Hyper => &self.hyper,
+			Action::Focus => &self.focus,
+			Action::Missile => &self.missile,
+			Action::Bomb => &self.bomb,
+		}
+	}
+
+	fn key_mut(&mut self, action: Action) -> &mut String {
+		match action {
+			Action::Up => &mut self.up,
+			Action::Down => &mut self.down,
+			Action::Left => &mut self.left,
+			Action::Right => &mut self.right,
+			Action::Shoot => &mut self.shoot,
+			Action::Dash => &mut self.dash,
+			Action::Hyper => &mut self.hyper,
+			Action::Focus => &mut self.focus,
+			Action::Missile => &mut self.missile,
+			Action::Bomb => &mut self.bomb,
+		}
+	}
+
+	/// Key of the action as shown in the menus
+	pub fn shown_key(&self, action: Action) -> &str {
+		shown_key(self.key(action))
+	}
+
+	/// Action bound to the key, if any
+	pub fn action(&self, key: &Key) -> Option<Action> {
+		let name = key_name(key)?;
+		Action::ALL
+			.into_iter()
+			.find(|action| self.key(*action) == name)
+	}
+
+	/// The action already using the key, if any, takes the previous key of `action`
+	fn bind(&mut self, action: Action, key: String) {
+		if let Some(other) = Action::ALL
+			.into_iter()
+			.find(|other| *other != action && self.key(*other) == key)
+		{
+			*self.key_mut(other) = self.key(action).to_string();
+		}
+		*self.key_mut(action) = key;
+	}
+
+	/// Bindings edited by hand with an unknown key or a key used twice go back to the defaults, the
+	/// game could be left without a way to move or shoot otherwise
+	fn check(&mut self) {
+		for action in Action::ALL {
+			let key = self.key_mut(action);
+			*key = key.to_lowercase();
+		}
+		let valid = Action::ALL.into_iter().all(|action| {
+			let key = self.key(action);
+			is_key_name(key)
+				&& Action::ALL
+					.into_iter()
+					.all(|other| other == action || self.key(other) != key)
+		});
+		if !valid {
+			*self = Bindings::default();
+		}
+	}
+
+	/// Summary of the layout, shown under the controls menu
+	pub fn preview(&self) -> String {
+		let moves = [Action::Up, Action::Left, Action::Down, Action::Right]
+			.map(|action| self.shown_key(action).replace(" arrow", ""));
+		format!(
+			"Move: {}   Shoot: {}   Dash: {}   Hyper: {}   Focus: {}   Missile: {}   Bomb: {}",
+			moves.join(" "),
+			self.shown_key(Action::Shoot),
+			self.shown_key(Action::Dash),
+			self.shown_key(Action::Hyper),
+			self.shown_key(Action::Focus),
+			self.shown_key(Action::Missile),
+			self.shown_key(Action::Bomb),
+		)
+	}
+}
+
+/// Ready-made layouts, picked in the controls menu before changing single keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlPreset {
+	/// Arrows and X, C, V, the default
+	Classic,
+	ArrowsZx,
+	Wasd,
+	/// Everything on the numpad, with num lock on
+	Numpad,
+	/// Mirror of the arrows and Z/X layout: moving with the left hand, the buttons on the right
+	LeftHanded,
+}
+
+impl ControlPreset {
+	pub const ALL: [ControlPreset; 5] = [
+		ControlPreset::Classic,
+		ControlPreset::ArrowsZx,
+		ControlPreset::Wasd,
+		ControlPreset::Numpad,
+		ControlPreset::LeftHanded,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			ControlPreset::Classic => "Classic",
+			ControlPreset::ArrowsZx => "Arrows + Z/X",
+			ControlPreset::Wasd => "WASD + J/K",
+			ControlPreset::Numpad => "One-handed numpad",
+			ControlPreset::LeftHanded => "Left-handed",
+		}
+	}
+
+	pub fn bindings(self) -> Bindings {
+		let keys = match self {
+			ControlPreset::Classic => [
+				"arrowup",
+				"arrowdown",
+				"arrowleft",
+				"arrowright",
+				"x",
+				"c",
+				"v",
+				"shift",
+				"z",
+				"b",
+			],
+			ControlPreset::ArrowsZx => [
+				"arrowup",
+				"arrowdown",
+				"arrowleft",
+				"arrowright",
+				"z",
+				"x",
+				"c",
+				"shift",
+				"v",
+				"b",
+			],
+			ControlPreset::Wasd => ["w", "s", "a", "d", "j", "k", "l", "shift", "i", "u"],
+			ControlPreset::Numpad => ["8", "5", "4", "6", "0", ".", "+", "-", "*", "/"],
+			ControlPreset::LeftHanded => ["w", "s", "a", "d", "/", ".", ",", "m", "n", "b"],
+		};
+		let [up, down, left, right, shoot, dash, hyper, focus, missile, bomb] =
+			keys.map(String::from);
+		Bindings { up, down, left, right, shoot, dash, hyper, focus, missile, bomb }
+	}
+
+	/// Preset with exactly these bindings, None once a key was changed by hand
+	pub fn matching(bindings: &Bindings) -> Option<ControlPreset> {
+		ControlPreset::ALL
+			.into_iter()
+			.find(|preset| preset.bindings() == *bindings)
+	}
+
+	/// Preset after the current one, the first one when the bindings are custom
+	pub fn cycle(bindings: &Bindings, step_right: bool) -> ControlPreset {
+		let n = ControlPreset::ALL.len();
+		let Some(current) = ControlPreset::matching(bindings) else {
+			return ControlPreset::ALL[0];
+		};
+		let index = ControlPreset::ALL
+			.iter()
+			.position(|preset| *preset == current)
+			.unwrap();
+		if step_right {
+			ControlPreset::ALL[(index + 1) % n]
+		} else {
+			ControlPreset::ALL[(index + n - 1) % n]
+		}
+	}
+}
+
+fn settings_path() -> PathBuf {
+	UserDir::Config.path().join("settings.toml")
+}
+
+impl Config {
+	/// Reads the settings saved by the options menu. On the first run the defaults are written,
+	/// so the file can be edited by hand before the options were ever opened
+	pub fn load() -> Result<Config, GameError> {
+		let path = settings_path();
+		if !path.exists() {
+			let config = Config::new();
+			config.save()?;
+			return Ok(config);
+		}
+		let raw = fs::read_to_string(&path).map_err(|err| GameError::config(&path, err))?;
+		let mut config: Config = toml::from_str(&raw).map_err(|err| GameError::config(&path, err))?;
+		// Edited by hand to values the menu can't reach
+		config.resolution_choice = config.resolution_choice.min(N_SIZES - 1);
+		config.master_volume = config.master_volume.min(100);
+		config.effects_volume = config.effects_volume.min(100);
+		config.assist.clamp();
+		config.bindings.check();
+		Ok(config)
+	}
+
+	/// Writes the settings to the config directory, replacing the previous ones
+	pub fn save(&self) -> Result<(), GameError> {
+		let path = settings_path();
+		let raw = toml::to_string(self).map_err(|err| GameError::user_file(&path, err))?;
+		fs::write(&path, raw).map_err(|err| GameError::user_file(&path, err))
+	}
+}
+
+impl Game {
+	/// Called after every change in the options menu, so nothing is lost if the game crashes
+	pub fn save_settings(&mut self) {
+		if let Err(err) = self.config.save() {
+			self.fail(err);
+		}
+	}
+
+	pub fn apply_volume(&mut self) {
+		self.audio.set_volume(
+			self.config.master_volume as f64 / 100.,
+			self.config.effects_volume as f64 / 100.,
+		);
+	}
+
+	/// F11 or Alt+Enter goes from windowed to borderless, then exclusive fullscreen and back to
+	/// the last windowed resolution. Returns whether the key was taken, Alt+Enter isn't also an
+	/// Enter for the menus
+	pub fn fullscreen_key_handling(
+		&mut self,
+		key_state: &ElementState,
+		key: &Key,
+		alt: bool,
+	) -> bool {
+		use winit::keyboard::NamedKey::*;
+		if !(key == &Key::Named(F11) || alt && key == &Key::Named(Enter)) {
+			return false;
+		}
+		if key_state == &ElementState::Released {
+			return true;
+		}
+		// Last entry is the screen size, the only fullscreen one
+		let fullscreen = N_SIZES - 1;
+		if self.config.resolution_choice != fullscreen {
+			self.config.windowed_choice = self.config.resolution_choice;
+			self.config.resolution_choice = fullscreen;
+			self.config.fullscreen_mode = FullscreenMode::Borderless;
+		} else if self.config.fullscreen_mode == FullscreenMode::Borderless {
+			self.config.fullscreen_mode = FullscreenMode::Exclusive;
+		} else {
+			self.config.resolution_choice = self.config.windowed_choice.min(fullscreen - 1);
+		}
+		self.window.request_window_resize(&self.config);
+		self.save_settings();
+		true
+	}
+
+	/// Gives the next character or arrow key pressed to the action waiting for it, Escape keeps
+	/// the current key
+	pub fn rebind_key_handling(&mut self, action: Action, key_state: &ElementState, key: &Key) {
+		use winit::keyboard::NamedKey::*;
+		if key_state == &ElementState::Released {
+			return;
+		}
+		match key {
+			Key::Named(Escape) => {
+				self.audio.play_sound(SoundBase::MenuBack);
+				self.rebinding = None;
+			},
+			key => {
+				let Some(name) = key_name(key) else {
+					return;
+				};
+				self.audio.play_sound(SoundBase::MenuSelect);
+				self.config.bindings.bind(action, name);
+				self.rebinding = None;
+				self.save_settings();
+			},
+		}
+	}
+}
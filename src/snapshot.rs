@@ -0,0 +1,149 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+	error::GameError,
+	game::{Game, RunState},
+	gameplay::{World, DT_60},
+	paths::UserDir,
+	replay::ReplayMode,
+};
+
+// The timers are game times, stored as a number of 60 Hz ticks to keep the dumps readable.
+
+fn to_ticks(duration: Duration) -> u64 {
+	(duration.as_secs_f32() / DT_60).round() as u64
+}
+
+fn from_ticks(ticks: u64) -> Duration {
+	Duration::from_secs_f32(ticks as f32 * DT_60)
+}
+
+/// Durations as ticks
+pub mod ticks {
+	use super::*;
+
+	pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+		to_ticks(*value).serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+		Ok(from_ticks(u64::deserialize(deserializer)?))
+	}
+}
+
+/// Optional durations as ticks
+pub mod opt_ticks {
+	use super::*;
+
+	pub fn serialize<S>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		value.map(to_ticks).serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Ok(Option::<u64>::deserialize(deserializer)?.map(from_ticks))
+	}
+}
+
+/// Maps of durations as ticks
+pub mod ticks_map {
+	use super::*;
+	use std::collections::HashMap;
+
+	pub fn serialize<S>(value: &HashMap<u32, Duration>, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let ticks: HashMap<u32, u64> = value.iter().map(|(id, t)| (*id, to_ticks(*t))).collect();
+		ticks.serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D>(deserializer: D) -> Result<HashMap<u32, Duration>, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let ticks = HashMap::<u32, u64>::deserialize(deserializer)?;
+		Ok(ticks
+			.into_iter()
+			.map(|(id, t)| (id, from_ticks(t)))
+			.collect())
+	}
+}
+
+impl World {
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	pub fn from_json(json: &str) -> serde_json::Result<World> {
+		serde_json::from_str(json)
+	}
+}
+
+/// Most recent dump, the file names are sorted by creation date
+fn latest_dump() -> Option<PathBuf> {
+	fs::read_dir(UserDir::Logs.path())
+		.ok()?
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+		.max()
+}
+
+impl Game {
+	/// Writes the current world to the dump directory
+	pub fn dump_world(&self) -> Result<(), GameError> {
+		let Some(world) = &self.world else {
+			return Ok(());
+		};
+		let stamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_millis();
+		let path = UserDir::Logs.path().join(format!("world_{stamp}.json"));
+		fs::write(&path, world.to_json()).map_err(|err| GameError::user_file(&path, err))?;
+		println!("World dumped to {}", path.display());
+		Ok(())
+	}
+
+	/// The dump keys only work in practice and in debug builds
+	pub fn dump_keys(&self) -> bool {
+		self.config.practice || cfg!(debug_assertions)
+	}
+
+	/// Loads a dumped world and resumes playing from it
+	pub fn load_world(&mut self, path: &Path) -> Result<(), GameError> {
+		let json = fs::read_to_string(path).map_err(|err| GameError::user_file(path, err))?;
+		let mut world = World::from_json(&json).map_err(|err| GameError::user_file(path, err))?;
+		if !self.config.practice {
+			// A run picked up from a dump can't be replayed nor ranked
+			self.replay = ReplayMode::Off;
+			world.tampered = true;
+		}
+		self.rewind.reset(&world);
+		self.world = Some(world);
+		self.infos.level_id = None;
+		self.state = RunState::Playing;
+		println!("World loaded from {}", path.display());
+		Ok(())
+	}
+
+	pub fn load_latest_dump(&mut self) -> Result<(), GameError> {
+		match latest_dump() {
+			Some(path) => self.load_world(&path),
+			None => {
+				println!("No world dump in '{}'", UserDir::Logs.path().display());
+				Ok(())
+			},
+		}
+	}
+}
@@ -1,14 +1,25 @@
-use std::{collections::HashMap, path::Path, time::Duration};
+use std::{collections::HashMap, io::Cursor, time::Duration};
 
 use kira::{
 	manager::{AudioManager, AudioManagerSettings},
 	sound::{
-		static_sound::{StaticSoundData, StaticSoundHandle},
+		static_sound::{StaticSoundData, StaticSoundHandle, StaticSoundSettings},
 		PlaybackState,
 	},
+	track::{
+		effect::filter::{FilterBuilder, FilterHandle, FilterMode},
+		TrackBuilder, TrackHandle,
+	},
 	tween::Tween,
 };
 
+use crate::{assets, error::GameError, game::Game, gameplay::MILESTONES};
+
+/// Cutoff of the music filter before the first score milestone, in hertz
+const MUSIC_BASE_CUTOFF: f64 = 2_500.;
+/// The music filter moves to a new cutoff in this time
+const MUSIC_CUTOFF_FADE: Duration = Duration::from_secs(2);
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum SoundBase {
 	PlayerShoot,
@@ -16,7 +27,11 @@ pub enum SoundBase {
 	MenuSelect,
 	MenuBack,
 	MenuMove,
-	_GameMusic,
+	EnemyHit,
+	/// An enemy dies
+	Explosion,
+	Pickup,
+	PlayerHit,
 }
 
 type PlayEntry = (usize, SoundBase);
@@ -26,25 +41,43 @@ pub struct Audio {
 	data: HashMap<SoundBase, StaticSoundData>,
 	id_counter: usize,
 	playing: HashMap<PlayEntry, StaticSoundHandle>,
+	/// Amplitude of the sound effects, relative to the master volume
+	effects_volume: f64,
+	/// File of the music playing, looped until another one is asked for
+	music_name: Option<String>,
+	music: Option<StaticSoundHandle>,
+	/// Mixer track of the music, where the filter is
+	music_track: TrackHandle,
+	/// Low-pass filter of the music, opened up by the score milestones
+	music_filter: FilterHandle,
+	music_cutoff: f64,
 }
 
 impl Audio {
 	pub fn new() -> Audio {
-		let mut audio = Audio {
-			manager: AudioManager::new(AudioManagerSettings::default()).unwrap(),
+		let mut manager = AudioManager::new(AudioManagerSettings::default()).unwrap();
+		let mut music_builder = TrackBuilder::new();
+		let music_filter = music_builder.add_effect(
+			FilterBuilder::new()
+				.mode(FilterMode::LowPass)
+				.cutoff(MUSIC_BASE_CUTOFF),
+		);
+		let music_track = manager.add_sub_track(music_builder).unwrap();
+		Audio {
+			manager,
 			data: HashMap::new(),
 			id_counter: 0,
 			playing: HashMap::new(),
-		};
-		audio.load_sounds();
-		audio
+			effects_volume: 1.,
+			music_name: None,
+			music: None,
+			music_track,
+			music_filter,
+			music_cutoff: MUSIC_BASE_CUTOFF,
+		}
 	}
 
-	fn load_sounds(&mut self) {
-		let sound_dir: &Path = Path::new("./assets/audio");
-		if !sound_dir.exists() {
-			panic!("Audio directory doesn't exist");
-		}
+	pub fn load_sounds(&mut self) -> Result<(), GameError> {
 		// Import all sounds
 		//? Better way to do this?
 		for (sound_type, file_name) in &[
@@ -52,22 +85,82 @@ impl Audio {
 			(SoundBase::MenuBack, "menu_back.wav"),
 			(SoundBase::MenuMove, "menu_move.wav"),
 			(SoundBase::MenuSelect, "menu_select.wav"),
+			(SoundBase::EnemyHit, "enemy_hit.wav"),
+			(SoundBase::Explosion, "explosion.wav"),
+			(SoundBase::Pickup, "pickup.wav"),
+			(SoundBase::PlayerHit, "player_hit.wav"),
 		] {
-			self.data.insert(
-				*sound_type,
-				StaticSoundData::from_file(sound_dir.join(file_name), Default::default()).unwrap(),
-			);
+			let name = format!("audio/{file_name}");
+			let file = assets::read(&name).ok_or(GameError::asset(&name, "missing file"))?;
+			let cursor = Cursor::new(file.into_owned());
+			let data = StaticSoundData::from_cursor(cursor, Default::default())
+				.map_err(|err| GameError::asset(&name, err))?;
+			self.data.insert(*sound_type, data);
 		}
+		Ok(())
+	}
+
+	/// Volumes as amplitudes, 1 being the volume of the files. The master volume applies to every
+	/// sound, the effects one only to the sounds played afterward
+	pub fn set_volume(&mut self, master: f64, effects: f64) {
+		self
+			.manager
+			.main_track()
+			.set_volume(master, Tween::default())
+			.unwrap();
+		self.effects_volume = effects;
 	}
 
 	pub fn play_sound(&mut self, sound_type: SoundBase) -> usize {
-		let handle = self.manager.play(self.data[&sound_type].clone()).unwrap();
+		let volume = self.effects_volume;
+		let data = self.data[&sound_type].with_modified_settings(|settings| settings.volume(volume));
+		let handle = self.manager.play(data).unwrap();
 		// Gets the sound handle and inserts it into the playing hashmap
 		self.playing.insert((self.id_counter, sound_type), handle);
 		self.id_counter += 1;
 		self.id_counter - 1
 	}
 
+	/// Loops the music in the audio directory, `None` stops it. Nothing changes when it is already
+	/// the one playing. Only the master volume applies to it
+	pub fn set_music(&mut self, file_name: Option<&str>) -> Result<(), GameError> {
+		if self.music_name.as_deref() == file_name {
+			return Ok(());
+		}
+		// Set first, a file that fails to load isn't read again every frame
+		self.music_name = file_name.map(str::to_string);
+		if let Some(mut handle) = self.music.take() {
+			handle.stop(Tween::default()).unwrap();
+		}
+		let Some(file_name) = file_name else {
+			return Ok(());
+		};
+		let name = format!("audio/{file_name}");
+		let file = assets::read(&name).ok_or(GameError::asset(&name, "missing file"))?;
+		let settings = StaticSoundSettings::new()
+			.loop_region(..)
+			.output_destination(&self.music_track);
+		let data = StaticSoundData::from_cursor(Cursor::new(file.into_owned()), settings)
+			.map_err(|err| GameError::asset(&name, err))?;
+		self.music = Some(self.manager.play(data).unwrap());
+		Ok(())
+	}
+
+	/// Fades the music filter to `cutoff`, in hertz
+	pub fn set_music_cutoff(&mut self, cutoff: f64) {
+		if self.music_cutoff == cutoff {
+			return;
+		}
+		self.music_cutoff = cutoff;
+		self
+			.music_filter
+			.set_cutoff(
+				cutoff,
+				Tween { duration: MUSIC_CUTOFF_FADE, ..Default::default() },
+			)
+			.unwrap();
+	}
+
 	pub fn _stop_sound(&mut self, entry: &PlayEntry) {
 		if let Some(mut handle) = self.playing.remove(entry) {
 			handle
@@ -76,7 +169,7 @@ impl Audio {
 		}
 	}
 
-	pub fn _stop_sound_condition(&mut self, condition: impl Fn(&(usize, SoundBase)) -> bool) {
+	pub fn stop_sound_condition(&mut self, condition: impl Fn(&(usize, SoundBase)) -> bool) {
 		self.playing.retain(|key, handle| {
 			if !condition(key) {
 				return true;
@@ -89,7 +182,13 @@ impl Audio {
 	}
 
 	pub fn _stop_sound_by_type(&mut self, sound_type: SoundBase) {
-		self._stop_sound_condition(|(_, sound)| sound != &sound_type);
+		self.stop_sound_condition(|(_, sound)| sound != &sound_type);
+	}
+
+	/// Fades out every sound, used when the game closes
+	pub fn stop_all(&mut self) {
+		self.stop_sound_condition(|_| true);
+		let _ = self.set_music(None);
 	}
 
 	pub fn delete_ended_sounds(&mut self) {
@@ -98,3 +197,27 @@ impl Audio {
 			.retain(|_, handle| handle.state() != PlaybackState::Stopped);
 	}
 }
+
+impl Game {
+	/// Loops the music of the level while it is on screen, paused or over included. The menus are
+	/// silent. The music gets more intense with each score milestone reached
+	pub fn update_music(&mut self) {
+		let music = self
+			.world
+			.as_ref()
+			.and(self.infos.level_id)
+			.and_then(|id| self.levels.get(id as usize))
+			.and_then(|level| level.music.clone());
+		if let Err(err) = self.audio.set_music(music.as_deref()) {
+			self.fail(err);
+		}
+		let cutoff = self
+			.world
+			.as_ref()
+			.and_then(|world| world.milestones.checked_sub(1))
+			.map_or(MUSIC_BASE_CUTOFF, |reached| {
+				MILESTONES[reached].music_cutoff
+			});
+		self.audio.set_music_cutoff(cutoff);
+	}
+}
@@ -0,0 +1,72 @@
+use crate::gameplay::ProjType;
+
+/// Frames shown one after the other, as tiles of the sprite sheet
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+	pub frames: &'static [(u32, u32)],
+	/// 60 Hz ticks each frame is shown
+	pub ticks_per_frame: u32,
+	/// Starts over once the last frame was shown, stops after it otherwise
+	pub looping: bool,
+}
+
+impl Animation {
+	/// Frame shown `ticks` after the start, None once a one shot animation is over
+	pub fn frame(&self, ticks: u32) -> Option<(u32, u32)> {
+		let index = (ticks / self.ticks_per_frame) as usize;
+		if self.looping {
+			Some(self.frames[index % self.frames.len()])
+		} else {
+			self.frames.get(index).copied()
+		}
+	}
+
+	/// Ticks a one shot animation lasts
+	pub fn duration(&self) -> u32 {
+		self.frames.len() as u32 * self.ticks_per_frame
+	}
+}
+
+/// Played where an enemy died
+pub const EXPLOSION: Animation = Animation {
+	frames: &[(0, 3), (1, 3), (2, 3), (3, 3)],
+	ticks_per_frame: 5,
+	looping: false,
+};
+
+/// Option satellites following the player
+pub const OPTION: (u32, u32) = (3, 4);
+
+/// What the player sprite shows, from the direction held
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerPose {
+	Idle,
+	BankLeft,
+	BankRight,
+}
+
+impl PlayerPose {
+	/// Tile of the pose, the faded one while invulnerable after a hit
+	pub fn tile(self, immune: bool) -> (u32, u32) {
+		match (self, immune) {
+			(PlayerPose::Idle, false) => (1, 0),
+			(PlayerPose::Idle, true) => (1, 1),
+			(PlayerPose::BankLeft, false) => (0, 2),
+			(PlayerPose::BankRight, false) => (1, 2),
+			(PlayerPose::BankLeft, true) => (2, 2),
+			(PlayerPose::BankRight, true) => (3, 2),
+		}
+	}
+}
+
+/// Tile of the projectiles, the enemies have theirs in their definition
+pub fn projectile_tile(variant: &ProjType) -> (u32, u32) {
+	match variant {
+		ProjType::Basic => (2, 1),
+		ProjType::Aimed => (3, 1),
+		ProjType::PlayerShoot => (0, 1),
+		ProjType::Homing => (2, 4),
+		// Drawn where the beam comes from
+		ProjType::Laser { .. } => (3, 1),
+	}
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::BTreeMap,
+	fs::OpenOptions,
+	io::Write,
+	path::PathBuf,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::GameError, game::Game, paths::UserDir, replay::ReplayMode};
+
+const CSV_HEADER: &str =
+	"date,stage,difficulty,cleared,scored,assisted,score,kills,deaths,grazes,accuracy,rta,igt";
+/// Ticks between two points of the score curve, one second of in-game time
+const SCORE_SAMPLE_TICKS: u64 = 60;
+
+/// Counters of the level being played, exported with the other statistics when it ends
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunStats {
+	pub kills: u32,
+	/// Lives lost, every hit costs one
+	pub deaths: u32,
+	pub grazes: u32,
+	/// Player bullets fired, one per stream of the shot pattern
+	pub shots: u32,
+	/// Player bullets that hit an enemy
+	pub hits: u32,
+	/// Kills by name of the enemy type
+	pub kills_by_enemy: BTreeMap<String, u32>,
+	/// Score at the end of every second of in-game time
+	pub score_curve: Vec<u64>,
+}
+
+impl RunStats {
+	pub fn kill(&mut self, enemy: &str) {
+		self.kills += 1;
+		*self.kills_by_enemy.entry(enemy.to_string()).or_default() += 1;
+	}
+
+	/// Called every tick, keeps the score once per second
+	pub fn sample_score(&mut self, tick: u64, score: u64) {
+		if tick.is_multiple_of(SCORE_SAMPLE_TICKS) {
+			self.score_curve.push(score);
+		}
+	}
+
+	/// Share of the player bullets that hit an enemy, in percent. Zero when nothing was fired
+	pub fn accuracy(&self) -> f32 {
+		if self.shots == 0 {
+			return 0.;
+		}
+		100. * self.hits as f32 / self.shots as f32
+	}
+}
+
+/// Format of the statistics file, nothing is written when off
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatsExport {
+	#[default]
+	Off,
+	Csv,
+	/// One JSON object per line
+	Json,
+}
+
+impl StatsExport {
+	const ALL: [StatsExport; 3] = [StatsExport::Off, StatsExport::Csv, StatsExport::Json];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			StatsExport::Off => "Off",
+			StatsExport::Csv => "CSV",
+			StatsExport::Json => "JSON",
+		}
+	}
+
+	/// From the command line argument
+	pub fn parse(arg: &str) -> Option<StatsExport> {
+		StatsExport::ALL
+			.into_iter()
+			.find(|format| format.name().eq_ignore_ascii_case(arg))
+	}
+
+	pub fn cycle(self, step_right: bool) -> StatsExport {
+		let n = StatsExport::ALL.len();
+		let index = StatsExport::ALL
+			.iter()
+			.position(|format| *format == self)
+			.unwrap();
+		if step_right {
+			StatsExport::ALL[(index + 1) % n]
+		} else {
+			StatsExport::ALL[(index + n - 1) % n]
+		}
+	}
+
+	/// Every level played is added at the end of the file
+	fn path(self) -> Option<PathBuf> {
+		let name = match self {
+			StatsExport::Off => return None,
+			StatsExport::Csv => "stats.csv",
+			StatsExport::Json => "stats.jsonl",
+		};
+		Some(UserDir::Stats.path().join(name))
+	}
+}
+
+/// Line of the statistics file
+#[derive(Serialize)]
+struct StageStats {
+	/// Unix time of the end of the level, in seconds
+	date: u64,
+	stage: String,
+	difficulty: &'static str,
+	/// False when the player ran out of lives
+	cleared: bool,
+	scored: bool,
+	assisted: bool,
+	score: u64,
+	kills: u32,
+	deaths: u32,
+	grazes: u32,
+	/// In percent
+	accuracy: f32,
+	/// Real time of the level, in seconds
+	rta: f32,
+	/// In-game time of the level, in seconds
+	igt: f32,
+	// Only in the JSON lines, the CSV columns stay the same for the files already written
+	shots: u32,
+	hits: u32,
+	kills_by_enemy: BTreeMap<String, u32>,
+	score_curve: Vec<u64>,
+}
+
+impl StageStats {
+	fn csv_row(&self) -> String {
+		// Stage names come from the level files and may hold commas or quotes
+		let stage = format!("\"{}\"", self.stage.replace('"', "\"\""));
+		format!(
+			"{},{stage},{},{},{},{},{},{},{},{},{:.1},{:.2},{:.2}",
+			self.date,
+			self.difficulty,
+			self.cleared,
+			self.scored,
+			self.assisted,
+			self.score,
+			self.kills,
+			self.deaths,
+			self.grazes,
+			self.accuracy,
+			self.rta,
+			self.igt,
+		)
+	}
+}
+
+impl Game {
+	/// Adds the statistics of the level that just ended to the file of the chosen format. Practice
+	/// runs and replays being watched aren't real runs, they are left out
+	pub fn export_stats(&mut self) {
+		let format = self.config.stats_export();
+		let Some(path) = format.path() else {
+			return;
+		};
+		if self.config.practice || matches!(self.replay, ReplayMode::Playback(_)) {
+			return;
+		}
+		let Some(world) = &self.world else {
+			return;
+		};
+		let stage = self
+			.infos
+			.level_id
+			.and_then(|id| self.levels.get(id as usize))
+			.map_or("Custom stage".to_string(), |level| level.name.to_string());
+		let stats = StageStats {
+			date: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs(),
+			stage,
+			difficulty: world.difficulty.name(),
+			cleared: world.player.hp > 0,
+			scored: world.is_scoring(),
+			assisted: world.assist.enabled,
+			score: world.score,
+			kills: world.stats.kills,
+			deaths: world.stats.deaths,
+			grazes: world.stats.grazes,
+			accuracy: world.stats.accuracy(),
+			rta: self.infos.since_level_begin().as_secs_f32(),
+			igt: world.clock.now().as_secs_f32(),
+			shots: world.stats.shots,
+			hits: world.stats.hits,
+			kills_by_enemy: world.stats.kills_by_enemy.clone(),
+			score_curve: world.stats.score_curve.clone(),
+		};
+		let line = match format {
+			StatsExport::Csv => stats.csv_row(),
+			_ => match serde_json::to_string(&stats) {
+				Ok(json) => json,
+				Err(err) => return self.fail(GameError::user_file(&path, err)),
+			},
+		};
+		let header = format == StatsExport::Csv && !path.exists();
+		let result = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&path)
+			.and_then(|mut file| {
+				if header {
+					writeln!(file, "{CSV_HEADER}")?;
+				}
+				writeln!(file, "{line}")
+			});
+		if let Err(err) = result {
+			self.fail(GameError::user_file(&path, err));
+		}
+	}
+}
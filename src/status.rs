@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+/// Ticks a slow lasts, hitting a slowed enemy again restarts it
+const SLOW_TICKS: u32 = 120;
+/// Speed of a slowed enemy, relative to its normal speed
+const SLOW_FACTOR: f32 = 0.5;
+/// Ticks a burn lasts after its last stack was added
+const BURN_TICKS: u32 = 180;
+const MAX_BURN_STACKS: u32 = 3;
+/// Damage of each burn stack every tick, 3 HP per second
+const BURN_DAMAGE: f32 = 0.05;
+/// Ticks a stun lasts, a stunned enemy can't be stunned again before it ends
+const STUN_TICKS: u32 = 45;
+
+/// Effect carried by the special player shots
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+	/// Halves the speed of the enemy
+	Slow,
+	/// Damages the enemy over time, stacks up to `MAX_BURN_STACKS`
+	Burn,
+	/// Stops the enemy from moving and shooting
+	Stun,
+}
+
+impl StatusKind {
+	/// Special shots go through the effects in this order
+	pub const CYCLE: [StatusKind; 3] = [StatusKind::Slow, StatusKind::Burn, StatusKind::Stun];
+}
+
+/// Effects active on an enemy, as ticks left for each one
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StatusEffects {
+	slow: u32,
+	burn: u32,
+	burn_stacks: u32,
+	stun: u32,
+}
+
+impl StatusEffects {
+	pub fn apply(&mut self, kind: StatusKind) {
+		match kind {
+			StatusKind::Slow => self.slow = SLOW_TICKS,
+			StatusKind::Burn => {
+				self.burn_stacks = (self.burn_stacks + 1).min(MAX_BURN_STACKS);
+				self.burn = BURN_TICKS;
+			},
+			// Stuns don't chain, the enemy gets to act between two of them
+			StatusKind::Stun => {
+				if self.stun == 0 {
+					self.stun = STUN_TICKS;
+				}
+			},
+		}
+	}
+
+	/// Counts down the effects, returns the burn damage of this tick
+	pub fn tick(&mut self) -> f32 {
+		self.slow = self.slow.saturating_sub(1);
+		self.stun = self.stun.saturating_sub(1);
+		if self.burn == 0 {
+			return 0.;
+		}
+		self.burn -= 1;
+		let damage = BURN_DAMAGE * self.burn_stacks as f32;
+		if self.burn == 0 {
+			self.burn_stacks = 0;
+		}
+		damage
+	}
+
+	/// Multiplies the speed of the enemy
+	pub fn speed_factor(&self) -> f32 {
+		if self.stun > 0 {
+			0.
+		} else if self.slow > 0 {
+			SLOW_FACTOR
+		} else {
+			1.
+		}
+	}
+
+	pub fn is_stunned(&self) -> bool {
+		self.stun > 0
+	}
+
+	/// Effect shown on the enemy, the one hindering it the most when there are several
+	pub fn shown(&self) -> Option<StatusKind> {
+		if self.stun > 0 {
+			Some(StatusKind::Stun)
+		} else if self.burn > 0 {
+			Some(StatusKind::Burn)
+		} else if self.slow > 0 {
+			Some(StatusKind::Slow)
+		} else {
+			None
+		}
+	}
+}
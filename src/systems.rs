@@ -0,0 +1,84 @@
+use cgmath::{Point2, Vector2, Zero};
+use std::time::Duration;
+
+use crate::{
+	body::{Collider, Mobile},
+	coords::RectF,
+	enemies::EnemyType,
+	game::Inputs,
+	gameplay::{Projectile, StageTransform},
+	hazard::{self, ActiveHazard},
+};
+
+/// Movement system: every body follows the velocity its kind steered it to, the hazards it is in
+/// slowing and pushing the ones drifting with them
+pub fn movement<'a>(
+	bodies: impl Iterator<Item = &'a mut dyn Mobile>,
+	hazards: &[ActiveHazard],
+	dt: f32,
+) {
+	for mobile in bodies {
+		let Some(speed_factor) = mobile.speed_factor() else {
+			continue;
+		};
+		let (hazard_factor, push) = if mobile.drifts() {
+			hazard::effect_at(hazards, mobile.body().pos)
+		} else {
+			(1., Vector2::zero())
+		};
+		mobile
+			.body_mut()
+			.advance(speed_factor * hazard_factor, push, dt);
+	}
+}
+
+/// Collision system: index of the first of `others` overlapping `entity`
+pub fn first_contact<'a, T: Collider + 'a>(
+	entity: &impl Collider,
+	others: impl IntoIterator<Item = (usize, &'a T)>,
+) -> Option<usize> {
+	others
+		.into_iter()
+		.find(|(_, other)| entity.collides(*other))
+		.map(|(index, _)| index)
+}
+
+/// What the shooters see of the world during a tick
+pub struct Aim<'a> {
+	pub inputs: &'a Inputs,
+	pub now: Duration,
+	pub dt: f32,
+	/// Position of the player
+	pub target: Point2<f32>,
+	/// Speed of the enemy bullets, with the rank
+	pub bullet_speed: f32,
+	pub rank_factor: f32,
+	pub transform: StageTransform,
+	pub bounds: RectF,
+	/// The enemies hold their fire after a boss phase
+	pub hold_fire: bool,
+}
+
+/// What is fired during a tick, added to the world once every shooter fired
+#[derive(Default)]
+pub struct Volley {
+	pub shots: Vec<Projectile>,
+	/// Fired by the player, for the stats
+	pub player_shots: u32,
+	/// Enemies let out by the carriers, from where
+	pub released: Vec<(Point2<f32>, EnemyType)>,
+}
+
+/// Entities firing projectiles, when their cooldowns allow
+pub trait Shooter {
+	fn shoot(&mut self, aim: &Aim, volley: &mut Volley);
+}
+
+/// Shooting system: gathers what every shooter fires this tick
+pub fn shooting<'a>(shooters: impl Iterator<Item = &'a mut dyn Shooter>, aim: &Aim) -> Volley {
+	let mut volley = Volley::default();
+	for shooter in shooters {
+		shooter.shoot(aim, &mut volley);
+	}
+	volley
+}
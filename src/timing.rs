@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use crate::{game::Game, replay::ReplayMode};
+
+/// Clock timing a run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clock {
+	/// Real time attack, the wall clock since the level started, pauses and menus included
+	Rta,
+	/// In-game time, only what the simulation went through
+	Igt,
+}
+
+impl Clock {
+	pub fn name(self) -> &'static str {
+		match self {
+			Clock::Rta => "RTA",
+			Clock::Igt => "IGT",
+		}
+	}
+}
+
+/// `m:ss.cc`, with the hundredths
+pub fn format_time(time: Duration) -> String {
+	let secs = time.as_secs();
+	let hundredths = time.subsec_millis() / 10;
+	format!("{}:{:02}.{hundredths:02}", secs / 60, secs % 60)
+}
+
+impl Game {
+	/// Clock shown while playing. Practice runs and replays use the in-game time, the rewinds and
+	/// the playback speed make the real time meaningless there
+	pub fn shown_clock(&self) -> Clock {
+		if self.config.practice || matches!(self.replay, ReplayMode::Playback(_)) {
+			Clock::Igt
+		} else {
+			Clock::Rta
+		}
+	}
+
+	pub fn clock_time(&self, clock: Clock) -> Duration {
+		match clock {
+			Clock::Rta => self.infos.since_level_begin(),
+			Clock::Igt => self
+				.world
+				.as_ref()
+				.map_or(Duration::ZERO, |world| world.clock.now()),
+		}
+	}
+}
@@ -0,0 +1,73 @@
+use cgmath::{Point2, Vector2, Zero};
+use std::{f32::consts::TAU, time::Duration};
+
+use crate::{
+	coords::Dimensions,
+	draw::DRAW_CONSTANTS,
+	enemies::EnemyType,
+	gameplay::{Projectile, DT_60},
+	rng::Rng,
+};
+
+/// Ticks between two patterns fired by the scene
+const EMIT_TICKS: f32 = 40.;
+/// Speed of the ambient bullets, slower than in the game to stay in the background
+const BULLET_SPEED: f32 = 2.;
+/// Ticks for the logo to go up and down once
+const BOB_PERIOD_TICKS: f32 = 180.;
+/// Height of the logo movement, in base resolution pixels
+const BOB_AMPLITUDE: f32 = 6.;
+
+/// Ambient scene behind the title menu: invisible enemies fire their patterns from above the
+/// screen and the bullets drift through it. Nothing can be hit, it only moves
+pub struct TitleScene {
+	pub projectiles: Vec<Projectile>,
+	rng: Rng,
+	/// Time since the scene started, in 60 Hz ticks so it doesn't depend on the frame rate
+	ticks: f32,
+	next_emit: f32,
+}
+
+impl TitleScene {
+	pub fn new() -> TitleScene {
+		TitleScene {
+			projectiles: vec![],
+			rng: Rng::new(Rng::seed_from_time()),
+			ticks: 0.,
+			next_emit: 0.,
+		}
+	}
+
+	/// Size of the scene, it fills the base resolution
+	fn dims() -> Dimensions<f32> {
+		DRAW_CONSTANTS.sizes[0].into_dim::<f32>()
+	}
+
+	pub fn update(&mut self, dt: Duration) {
+		let dt_ticks = dt.as_secs_f32() / DT_60;
+		self.ticks += dt_ticks;
+		let dims = TitleScene::dims();
+		if self.ticks >= self.next_emit {
+			self.next_emit += EMIT_TICKS;
+			let variant = if self.rng.next_f32() < 0.5 {
+				EnemyType::BASIC
+			} else {
+				EnemyType::SNIPER
+			};
+			let pos = Point2::new(self.rng.next_f32() * dims.w, -10.);
+			let target = Point2::new(self.rng.next_f32() * dims.w, dims.h);
+			let shot = (variant.def().shot)(pos, target, BULLET_SPEED);
+			self.projectiles.extend(shot);
+		}
+		let bounds = dims.into_rect().grow(20.);
+		self.projectiles.retain_mut(|proj| {
+			proj.body.advance(1., Vector2::zero(), dt.as_secs_f32());
+			bounds.contains(proj.body.pos)
+		});
+	}
+
+	/// Vertical offset of the logo, in base resolution pixels
+	pub fn logo_offset(&self) -> f32 {
+		BOB_AMPLITUDE * (self.ticks / BOB_PERIOD_TICKS * TAU).sin()
+	}
+}